@@ -0,0 +1,102 @@
+/*!
+Benchmarks the pattern scanner over the demo binaries.
+
+Run with `cargo bench --bench scanner`.
+*/
+
+extern crate pelite;
+
+use std::time::{Duration, Instant};
+
+use pelite::FileMap;
+use pelite::pattern;
+use pelite::util::{Backend, Finder};
+use pelite::{pe32, pe64};
+use pelite::pe32::Pe as Pe32;
+use pelite::pe64::Pe as Pe64;
+
+/// Time spent on each measurement.
+const MEASURE_TIME: u64 = 200;
+
+const PATTERNS: &[&str] = &[
+	// Rare literal prefix
+	"4C8B41'? 4C2BC2 ????????? 0FB60A 420FB60402 2BC8 75% 8B15${'} 85 C9",
+	// Common literal prefix
+	"488B05${'}",
+	"E8${'}",
+	// Short prefix of common bytes
+	"00 ? 00",
+	// Without literal prefix
+	"? 8B?? C3",
+	"(E8|E9)${'}",
+];
+
+//----------------------------------------------------------------
+
+// Runs the closure repeatedly and returns the average time per iteration
+fn measure<F: FnMut() -> usize>(mut f: F) -> (Duration, usize) {
+	let start = Instant::now();
+	let mut iterations = 0u32;
+	let mut result = 0;
+	while start.elapsed() < Duration::from_millis(MEASURE_TIME) {
+		result = f();
+		iterations += 1;
+	}
+	(start.elapsed() / iterations, result)
+}
+
+fn report(name: &str, len: usize, (time, result): (Duration, usize)) {
+	let nanos = time.as_secs() as f64 * 1e9 + time.subsec_nanos() as f64;
+	println!("{:<72} {:>10.0} ns {:>8.1} MB/s {:>6}", name, nanos, len as f64 / nanos * 1e3, result);
+}
+
+fn main() {
+	let pats: Vec<_> = PATTERNS.iter().map(|pat| (pat, pattern::parse(pat).unwrap())).collect();
+
+	// Scan the code sections with every pattern
+	let file_map = FileMap::open("demo/Demo64.dll").unwrap();
+	let file = pe64::PeFile::from_bytes(&file_map).unwrap();
+	let scanner = file.scanner();
+	let len: usize = scanner.code_ranges().as_slice().iter().map(|range| (range.end - range.start) as usize).sum();
+	println!("demo/Demo64.dll");
+	for &(name, ref pat) in &pats {
+		report(name, len, measure(|| scanner.matches_code(pat).count()));
+	}
+	let many: Vec<&[pattern::Atom]> = pats.iter().map(|&(_, ref pat)| &pat[..]).collect();
+	report("matches_many", len, measure(|| scanner.matches_many_code(&many).count()));
+
+	let file_map = FileMap::open("demo/Demo.dll").unwrap();
+	let file = pe32::PeFile::from_bytes(&file_map).unwrap();
+	let scanner = file.scanner();
+	let len: usize = scanner.code_ranges().as_slice().iter().map(|range| (range.end - range.start) as usize).sum();
+	println!("\ndemo/Demo.dll");
+	for &(name, ref pat) in &pats {
+		report(name, len, measure(|| scanner.matches_code(pat).count()));
+	}
+
+	// Compare the search backends over the whole files
+	let mut haystack = Vec::new();
+	haystack.extend_from_slice(FileMap::open("demo/Demo64.dll").unwrap().as_ref());
+	haystack.extend_from_slice(FileMap::open("demo/Demo.dll").unwrap().as_ref());
+	println!("\nsubstring search over {} bytes", haystack.len());
+	let needles: &[&[u8]] = &[b"\x8B\x15", b"\x48\x8B\x05", b"\x0F\xB6\x0A\x42\x0F\xB6\x04\x02", b"\x00\x00\x00\x00\x00\x00\x00\x01"];
+	for &backend in &[Backend::Portable, Backend::Sse2, Backend::Avx2] {
+		if !backend.is_available() {
+			println!("{:?} is not available", backend);
+			continue;
+		}
+		for &needle in needles {
+			let finder = Finder::with_backend(needle, backend);
+			let name = format!("{:?} {:02X?}", backend, needle);
+			report(&name, haystack.len(), measure(|| {
+				let mut count = 0;
+				let mut i = 0;
+				while let Some(pos) = finder.find(&haystack[i..]) {
+					count += 1;
+					i += pos + 1;
+				}
+				count
+			}));
+		}
+	}
+}
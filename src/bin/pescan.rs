@@ -0,0 +1,152 @@
+/*!
+Runs a signature database against PE32 and PE32+ files and prints the offsets of the signatures.
+
+See the [`sigdb`](../pelite/sigdb/index.html) module for the format of the database.
+
+```bat
+cargo run --bin pescan -- "demo\Demo64.sig" "demo\Demo64.dll"
+```
+
+The machine readable output has a tab separated line for every signature and file:
+the file, the name, `ok` or `stale`, the number of matches, the number of matches expected and the comma separated offsets.
+Signatures whose number of matches differs from the expected count have gone stale.
+
+The exit code is `1` if any signature has gone stale and `2` if a file could not be read.
+*/
+
+extern crate pelite;
+
+use std::env;
+use std::process;
+
+use pelite::sigdb::Entry;
+
+//----------------------------------------------------------------
+
+const HELP_TEXT: &str = "\
+Signature scanner for PE binaries.
+
+Usage:
+    pescan [-m | --machine] [-a | --all] DATABASE FILE...
+
+Options:
+    -m, --machine  Print tab separated lines for every signature and file.
+    -a, --all      Scan all the sections instead of the executable sections.
+";
+
+fn main() {
+	let mut machine = false;
+	let mut all = false;
+	let mut paths = Vec::new();
+	for arg in env::args_os().skip(1) {
+		if arg == "-m" || arg == "--machine" {
+			machine = true;
+		}
+		else if arg == "-a" || arg == "--all" {
+			all = true;
+		}
+		else if arg == "-h" || arg == "--help" {
+			print!("{}", HELP_TEXT);
+			return;
+		}
+		else {
+			paths.push(arg);
+		}
+	}
+	if paths.len() < 2 {
+		print!("{}", HELP_TEXT);
+		process::exit(2);
+	}
+
+	let entries = match pelite::sigdb::load(&paths[0]) {
+		Ok(entries) => entries,
+		Err(err) => {
+			eprintln!("pescan: {}: {}", paths[0].to_string_lossy(), err);
+			process::exit(2);
+		},
+	};
+
+	let mut code = 0;
+	for path in &paths[1..] {
+		let name = path.to_string_lossy();
+		let results = match pelite::FileMap::open(path) {
+			Ok(map) => {
+				// Try PE32 and PE32+
+				pelite::pe32::PeFile::from_bytes(&map).map(|file| scan_pe32(file, &entries, all))
+					.or_else(|_| pelite::pe64::PeFile::from_bytes(&map).map(|file| scan_pe64(file, &entries, all)))
+					.map_err(|err| err.to_string())
+			},
+			Err(err) => Err(err.to_string()),
+		};
+		match results {
+			Ok(results) => {
+				if !report(&name, &entries, &results, machine) {
+					code = if code == 0 { 1 } else { code };
+				}
+			},
+			Err(err) => {
+				eprintln!("pescan: {}: {}", name, err);
+				code = 2;
+			},
+		}
+	}
+	process::exit(code);
+}
+
+//----------------------------------------------------------------
+
+fn scan_pe32(file: pelite::pe32::PeFile, entries: &[Entry], all: bool) -> Vec<Vec<u32>> {
+	use pelite::pe32::Pe;
+
+	let scanner = file.scanner();
+	let ranges = if all { scanner.section_ranges(|_| true) } else { scanner.code_ranges() };
+	entries.iter().map(|entry| {
+		let mut save = vec![0; entry.save_len()];
+		let mut matches = scanner.matches(&entry.pattern, ranges.clone());
+		let mut found = Vec::new();
+		while matches.next_into(&mut save) {
+			found.push(save[entry.slot]);
+		}
+		found
+	}).collect()
+}
+
+fn scan_pe64(file: pelite::pe64::PeFile, entries: &[Entry], all: bool) -> Vec<Vec<u32>> {
+	use pelite::pe64::Pe;
+
+	let scanner = file.scanner();
+	let ranges = if all { scanner.section_ranges(|_| true) } else { scanner.code_ranges() };
+	entries.iter().map(|entry| {
+		let mut save = vec![0; entry.save_len()];
+		let mut matches = scanner.matches(&entry.pattern, ranges.clone());
+		let mut found = Vec::new();
+		while matches.next_into(&mut save) {
+			found.push(save[entry.slot]);
+		}
+		found
+	}).collect()
+}
+
+// Prints the results and returns if none of the signatures went stale
+fn report(path: &str, entries: &[Entry], results: &[Vec<u32>], machine: bool) -> bool {
+	let width = entries.iter().map(|entry| entry.name.len()).max().unwrap_or(0);
+	if !machine {
+		println!("{}", path);
+	}
+	let mut ok = true;
+	for (entry, found) in entries.iter().zip(results) {
+		let stale = found.len() != entry.count;
+		ok &= !stale;
+		let offsets = found.iter().map(|offset| format!("{:08X}", offset)).collect::<Vec<_>>();
+		if machine {
+			println!("{}\t{}\t{}\t{}\t{}\t{}", path, entry.name, if stale { "stale" } else { "ok" }, found.len(), entry.count, offsets.join(","));
+		}
+		else if stale {
+			println!("  {:<width$}  stale: {} matches, expected {}", entry.name, found.len(), entry.count, width = width);
+		}
+		else {
+			println!("  {:<width$}  {}", entry.name, offsets.join(" "), width = width);
+		}
+	}
+	ok
+}
@@ -369,6 +369,18 @@ pub const RT_ANIICON: u16      = 22;
 pub const RT_HTML: u16         = 23;
 pub const RT_MANIFEST: u16     = 24;
 
+pub const LANG_NEUTRAL: u16        = 0x00;
+pub const LANG_ENGLISH: u16        = 0x09;
+pub const SUBLANG_NEUTRAL: u16     = 0x00;
+pub const SUBLANG_DEFAULT: u16     = 0x01;
+pub const SUBLANG_ENGLISH_US: u16  = 0x01;
+pub const LANG_USER_DEFAULT: u16   = LANG_NEUTRAL | SUBLANG_DEFAULT << 10;
+pub const LANG_SYSTEM_DEFAULT: u16 = LANG_NEUTRAL | 0x02 << 10;
+
+pub const CREATEPROCESS_MANIFEST_RESOURCE_ID: u16                 = 1;
+pub const ISOLATIONAWARE_MANIFEST_RESOURCE_ID: u16                = 2;
+pub const ISOLATIONAWARE_NOSTATICIMPORT_MANIFEST_RESOURCE_ID: u16 = 3;
+
 #[repr(C, packed)]
 pub struct IMAGE_RESOURCE_DIRECTORY {
 	pub Characteristics: u32,
@@ -396,6 +408,180 @@ pub struct IMAGE_RESOURCE_DATA_ENTRY {
 	pub Reserved: u32,
 }
 
+pub const VS_FFI_SIGNATURE: u32     = 0xFEEF04BD;
+pub const VS_FFI_STRUCVERSION: u32  = 0x00010000;
+
+pub const VS_FF_DEBUG: u32          = 0x01;
+pub const VS_FF_PRERELEASE: u32     = 0x02;
+pub const VS_FF_PATCHED: u32        = 0x04;
+pub const VS_FF_PRIVATEBUILD: u32   = 0x08;
+pub const VS_FF_INFOINFERRED: u32   = 0x10;
+pub const VS_FF_SPECIALBUILD: u32   = 0x20;
+
+pub const VOS_UNKNOWN: u32          = 0x00000000;
+pub const VOS_DOS: u32              = 0x00010000;
+pub const VOS_OS216: u32            = 0x00020000;
+pub const VOS_OS232: u32            = 0x00030000;
+pub const VOS_NT: u32               = 0x00040000;
+pub const VOS__WINDOWS16: u32       = 0x00000001;
+pub const VOS__PM16: u32            = 0x00000002;
+pub const VOS__PM32: u32            = 0x00000003;
+pub const VOS__WINDOWS32: u32       = 0x00000004;
+
+pub const VFT_UNKNOWN: u32          = 0;
+pub const VFT_APP: u32              = 1;
+pub const VFT_DLL: u32              = 2;
+pub const VFT_DRV: u32              = 3;
+pub const VFT_FONT: u32             = 4;
+pub const VFT_VXD: u32              = 5;
+pub const VFT_STATIC_LIB: u32       = 7;
+
+#[repr(C, packed)]
+pub struct VS_FIXEDFILEINFO {
+	pub dwSignature: u32,
+	pub dwStrucVersion: u32,
+	pub dwFileVersionMS: u32,
+	pub dwFileVersionLS: u32,
+	pub dwProductVersionMS: u32,
+	pub dwProductVersionLS: u32,
+	pub dwFileFlagsMask: u32,
+	pub dwFileFlags: u32,
+	pub dwFileOS: u32,
+	pub dwFileType: u32,
+	pub dwFileSubtype: u32,
+	pub dwFileDateMS: u32,
+	pub dwFileDateLS: u32,
+}
+
+// Header of the RT_GROUP_ICON and RT_GROUP_CURSOR resources, idType is 1 for icons and 2 for cursors
+#[repr(C, packed)]
+pub struct GRPICONDIR {
+	pub idReserved: u16,
+	pub idType: u16,
+	pub idCount: u16,
+}
+
+// For cursors bWidth and bHeight are a single word each and bColorCount, bReserved are the height word instead
+#[repr(C, packed)]
+pub struct GRPICONDIRENTRY {
+	pub bWidth: u8,
+	pub bHeight: u8,
+	pub bColorCount: u8,
+	pub bReserved: u8,
+	pub wPlanes: u16,
+	pub wBitCount: u16,
+	pub dwBytesInRes: u32,
+	pub nId: u16,
+}
+
+pub const FVIRTKEY: u16  = 0x01;
+pub const FNOINVERT: u16 = 0x02;
+pub const FSHIFT: u16    = 0x04;
+pub const FCONTROL: u16  = 0x08;
+pub const FALT: u16      = 0x10;
+
+// Entry of the RT_ACCELERATOR resource, the last entry has the 0x80 flag set
+#[repr(C, packed)]
+pub struct ACCELTABLEENTRY {
+	pub fFlags: u16,
+	pub wAnsi: u16,
+	pub wId: u16,
+	pub padding: u16,
+}
+
+#[repr(C, packed)]
+pub struct MESSAGE_RESOURCE_DATA {
+	pub NumberOfBlocks: u32,
+	// MESSAGE_RESOURCE_BLOCK Blocks[NumberOfBlocks];
+}
+
+#[repr(C, packed)]
+pub struct MESSAGE_RESOURCE_BLOCK {
+	pub LowId: u32,
+	pub HighId: u32,
+	pub OffsetToEntries: u32,
+}
+
+// Length is the size of the entry in bytes including the text and its padding
+#[repr(C, packed)]
+pub struct MESSAGE_RESOURCE_ENTRY {
+	pub Length: u16,
+	pub Flags: u16,
+	// BYTE Text[Length - 4];
+}
+
+pub const MESSAGE_RESOURCE_ANSI: u16    = 0x0000;
+pub const MESSAGE_RESOURCE_UNICODE: u16 = 0x0001;
+pub const MESSAGE_RESOURCE_UTF8: u16    = 0x0002;
+
+pub const BI_RGB: u32            = 0;
+pub const BI_RLE8: u32           = 1;
+pub const BI_RLE4: u32           = 2;
+pub const BI_BITFIELDS: u32      = 3;
+pub const BI_JPEG: u32           = 4;
+pub const BI_PNG: u32            = 5;
+pub const BI_ALPHABITFIELDS: u32 = 6;
+
+// Color space of the BITMAPV5HEADER with an embedded ICC profile, 'MBED'
+pub const PROFILE_EMBEDDED: u32 = 0x4D424544;
+
+#[repr(C, packed)]
+pub struct BITMAPINFOHEADER {
+	pub biSize: u32,
+	pub biWidth: i32,
+	pub biHeight: i32,
+	pub biPlanes: u16,
+	pub biBitCount: u16,
+	pub biCompression: u32,
+	pub biSizeImage: u32,
+	pub biXPelsPerMeter: i32,
+	pub biYPelsPerMeter: i32,
+	pub biClrUsed: u32,
+	pub biClrImportant: u32,
+}
+
+// Extends the BITMAPINFOHEADER, the BITMAPV4HEADER ends after bV5GammaBlue
+#[repr(C, packed)]
+pub struct BITMAPV5HEADER {
+	pub bV5Size: u32,
+	pub bV5Width: i32,
+	pub bV5Height: i32,
+	pub bV5Planes: u16,
+	pub bV5BitCount: u16,
+	pub bV5Compression: u32,
+	pub bV5SizeImage: u32,
+	pub bV5XPelsPerMeter: i32,
+	pub bV5YPelsPerMeter: i32,
+	pub bV5ClrUsed: u32,
+	pub bV5ClrImportant: u32,
+	pub bV5RedMask: u32,
+	pub bV5GreenMask: u32,
+	pub bV5BlueMask: u32,
+	pub bV5AlphaMask: u32,
+	pub bV5CSType: u32,
+	pub bV5Endpoints: [i32; 9],
+	pub bV5GammaRed: u32,
+	pub bV5GammaGreen: u32,
+	pub bV5GammaBlue: u32,
+	pub bV5Intent: u32,
+	pub bV5ProfileData: u32,
+	pub bV5ProfileSize: u32,
+	pub bV5Reserved: u32,
+}
+
+pub const DS_SETFONT: u32   = 0x40;
+pub const DS_SHELLFONT: u32 = 0x48;
+
+pub const MF_GRAYED: u16       = 0x0001;
+pub const MF_DISABLED: u16     = 0x0002;
+pub const MF_CHECKED: u16      = 0x0008;
+pub const MF_POPUP: u16        = 0x0010;
+pub const MF_MENUBARBREAK: u16 = 0x0020;
+pub const MF_MENUBREAK: u16    = 0x0040;
+pub const MF_END: u16          = 0x0080;
+pub const MF_HELP: u16         = 0x4000;
+pub const MF_SEPARATOR: u16    = 0x0800;
+
 //----------------------------------------------------------------
 // Sourced from <Windows.h>
 
@@ -477,6 +663,15 @@ pub const IMAGE_DEBUG_TYPE_OMAP_TO_SRC: u32 = 7;
 pub const IMAGE_DEBUG_TYPE_OMAP_FROM_SRC: u32 = 8;
 pub const IMAGE_DEBUG_TYPE_BORLAND: u32 = 9;
 pub const IMAGE_DEBUG_TYPE_CLSID: u32 = 11;
+pub const IMAGE_DEBUG_TYPE_VC_FEATURE: u32 = 12;
+pub const IMAGE_DEBUG_TYPE_POGO: u32 = 13;
+pub const IMAGE_DEBUG_TYPE_ILTCG: u32 = 14;
+pub const IMAGE_DEBUG_TYPE_MPX: u32 = 15;
+pub const IMAGE_DEBUG_TYPE_REPRO: u32 = 16;
+pub const IMAGE_DEBUG_TYPE_EMBEDDED_PORTABLE_PDB: u32 = 17;
+pub const IMAGE_DEBUG_TYPE_SPGO: u32 = 18;
+pub const IMAGE_DEBUG_TYPE_PDBCHECKSUM: u32 = 19;
+pub const IMAGE_DEBUG_TYPE_EX_DLLCHARACTERISTICS: u32 = 20;
 
 #[repr(C, packed)]
 pub struct IMAGE_DEBUG_DIRECTORY {
@@ -518,6 +713,49 @@ pub struct IMAGE_DEBUG_MISC {
 	pub Name: [u8; 0],
 }
 
+// Number of object files compiled with each feature
+#[repr(C, packed)]
+pub struct IMAGE_DEBUG_VC_FEATURE {
+	pub PreVCPP11: u32,
+	pub CCpp: u32,
+	pub GS: u32,
+	pub SDL: u32,
+	pub GuardN: u32,
+}
+
+// |0-7    |8-10  |11     |12    |13      |14-15  |
+// |cbProlog|cbRegs|fHasSEH|fUseBP|reserved|cbFrame|
+#[repr(C, packed)]
+pub struct FPO_DATA {
+	pub ulOffStart: u32,
+	pub cbProcSize: u32,
+	pub cdwLocals: u32,
+	pub cdwParams: u16,
+	pub Attributes: u16,
+}
+
+pub const FRAME_FPO: u16 = 0;
+pub const FRAME_TRAP: u16 = 1;
+pub const FRAME_TSS: u16 = 2;
+pub const FRAME_NONFPO: u16 = 3;
+
+// Signatures of the POGO debug info
+pub const IMAGE_DEBUG_POGO_SIGNATURE_LTCG: u32 = 0x4C544347;
+pub const IMAGE_DEBUG_POGO_SIGNATURE_PGI: u32 = 0x50474900;
+pub const IMAGE_DEBUG_POGO_SIGNATURE_PGU: u32 = 0x50475500;
+
+// Signature of the embedded portable PDB debug info, 'MPDB'
+pub const IMAGE_DEBUG_EMBEDDED_PORTABLE_PDB_SIGNATURE: u32 = 0x4244504D;
+
+pub const IMAGE_DLLCHARACTERISTICS_EX_CET_COMPAT: u32                                 = 0x01;
+pub const IMAGE_DLLCHARACTERISTICS_EX_CET_COMPAT_STRICT_MODE: u32                     = 0x02;
+pub const IMAGE_DLLCHARACTERISTICS_EX_CET_SET_CONTEXT_IP_VALIDATION_RELAXED_MODE: u32 = 0x04;
+pub const IMAGE_DLLCHARACTERISTICS_EX_CET_DYNAMIC_APIS_ALLOW_IN_PROC: u32             = 0x08;
+pub const IMAGE_DLLCHARACTERISTICS_EX_CET_RESERVED_1: u32                             = 0x10;
+pub const IMAGE_DLLCHARACTERISTICS_EX_CET_RESERVED_2: u32                             = 0x20;
+pub const IMAGE_DLLCHARACTERISTICS_EX_FORWARD_CFI_COMPAT: u32                         = 0x40;
+pub const IMAGE_DLLCHARACTERISTICS_EX_HOTPATCH_COMPATIBLE: u32                        = 0x80;
+
 //----------------------------------------------------------------
 
 use util::Pod;
@@ -535,6 +773,15 @@ unsafe impl Pod for IMAGE_IMPORT_DESCRIPTOR {}
 unsafe impl Pod for IMAGE_RESOURCE_DIRECTORY {}
 unsafe impl Pod for IMAGE_RESOURCE_DIRECTORY_ENTRY {}
 unsafe impl Pod for IMAGE_RESOURCE_DATA_ENTRY {}
+unsafe impl Pod for VS_FIXEDFILEINFO {}
+unsafe impl Pod for GRPICONDIR {}
+unsafe impl Pod for GRPICONDIRENTRY {}
+unsafe impl Pod for ACCELTABLEENTRY {}
+unsafe impl Pod for MESSAGE_RESOURCE_DATA {}
+unsafe impl Pod for MESSAGE_RESOURCE_BLOCK {}
+unsafe impl Pod for MESSAGE_RESOURCE_ENTRY {}
+unsafe impl Pod for BITMAPINFOHEADER {}
+unsafe impl Pod for BITMAPV5HEADER {}
 unsafe impl Pod for IMAGE_BASE_RELOCATION {}
 unsafe impl Pod for IMAGE_BASE_RELOC_TYPEOFFSET {}
 unsafe impl Pod for IMAGE_TLS_DIRECTORY32 {}
@@ -544,3 +791,5 @@ unsafe impl Pod for IMAGE_DEBUG_DIRECTORY {}
 unsafe impl Pod for IMAGE_DEBUG_CV_INFO_PDB20 {}
 unsafe impl Pod for IMAGE_DEBUG_CV_INFO_PDB70 {}
 unsafe impl Pod for IMAGE_DEBUG_MISC {}
+unsafe impl Pod for IMAGE_DEBUG_VC_FEATURE {}
+unsafe impl Pod for FPO_DATA {}
@@ -0,0 +1,469 @@
+/*!
+Length Disassembler.
+
+Decodes the length of x86 (IA-32) and x64 (x86-64) instructions and where their displacement and immediate fields are
+without decoding the instructions themselves.
+
+Supports the legacy, REX, VEX and EVEX prefixes, the one, two and three byte opcode maps, ModRM and SIB addressing.
+
+The decoder is table driven, each opcode has flags describing its ModRM and immediate operands.
+
+# Examples
+
+```
+# #![allow(unused_variables)]
+use pelite::lde::{self, Mode};
+use pelite::pe64::{Pe, PeFile};
+
+# #[allow(dead_code)]
+fn example(file: PeFile, rva: u32) -> pelite::Result<()> {
+	// Get the bytes of the code at the rva
+	let bytes = file.slice_bytes(rva)?;
+
+	// Step over the first 10 instructions
+	for (offset, insn) in lde::iter(bytes, Mode::X64).take(10) {
+		println!("{:08X}: {} bytes", rva as usize + offset, insn.len);
+	}
+
+	// The call in `call func` follows the relative offset in its immediate
+	let insn = lde::decode(&[0xE8, 0x10, 0x00, 0x00, 0x00], Mode::X64)?;
+	assert_eq!(insn.len, 5);
+	assert_eq!(insn.imm_range(), 1..5);
+	assert_eq!(insn.target(&[0xE8, 0x10, 0x00, 0x00, 0x00], 0x1000), Some(0x1015));
+
+	Ok(())
+}
+```
+*/
+
+use std::ops::Range;
+
+use error::{Error, Result};
+
+/// Processor mode.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum Mode {
+	/// 32-bit protected mode, IA-32.
+	X86,
+	/// 64-bit long mode, x86-64.
+	X64,
+}
+
+/// Max length of an instruction.
+pub const MAX_LEN: usize = 15;
+
+//----------------------------------------------------------------
+// Opcode flags
+
+/// Has a ModRM byte.
+const M: u8 = 0x01;
+/// Has a byte immediate.
+const B: u8 = 0x02;
+/// Has a word immediate.
+const W: u8 = 0x04;
+/// Has a word or dword immediate depending on the operand size.
+const Z: u8 = 0x08;
+/// Has a word, dword or qword immediate depending on the operand size.
+const V: u8 = 0x10;
+/// The immediate is a relative branch offset.
+const R: u8 = 0x20;
+/// Is a legacy prefix.
+const P: u8 = 0x40;
+/// Is invalid in 64-bit mode.
+const X: u8 = 0x80;
+/// Is invalid.
+const I: u8 = 0xFF;
+
+static ONE_BYTE: [u8; 256] = [
+	//  0      1      2      3      4      5      6      7      8      9      A      B      C      D      E      F
+	M,     M,     M,     M,     B,     Z,     X,     X,     M,     M,     M,     M,     B,     Z,     X,     0,     // 0
+	M,     M,     M,     M,     B,     Z,     X,     X,     M,     M,     M,     M,     B,     Z,     X,     X,     // 1
+	M,     M,     M,     M,     B,     Z,     P,     X,     M,     M,     M,     M,     B,     Z,     P,     X,     // 2
+	M,     M,     M,     M,     B,     Z,     P,     X,     M,     M,     M,     M,     B,     Z,     P,     X,     // 3
+	0,     0,     0,     0,     0,     0,     0,     0,     0,     0,     0,     0,     0,     0,     0,     0,     // 4
+	0,     0,     0,     0,     0,     0,     0,     0,     0,     0,     0,     0,     0,     0,     0,     0,     // 5
+	X,     X,     M|X,   M,     P,     P,     P,     P,     Z,     M|Z,   B,     M|B,   0,     0,     0,     0,     // 6
+	B|R,   B|R,   B|R,   B|R,   B|R,   B|R,   B|R,   B|R,   B|R,   B|R,   B|R,   B|R,   B|R,   B|R,   B|R,   B|R,   // 7
+	M|B,   M|Z,   M|B|X, M|B,   M,     M,     M,     M,     M,     M,     M,     M,     M,     M,     M,     M,     // 8
+	0,     0,     0,     0,     0,     0,     0,     0,     0,     0,     Z|W|X, 0,     0,     0,     0,     0,     // 9
+	0,     0,     0,     0,     0,     0,     0,     0,     B,     Z,     0,     0,     0,     0,     0,     0,     // A
+	B,     B,     B,     B,     B,     B,     B,     B,     V,     V,     V,     V,     V,     V,     V,     V,     // B
+	M|B,   M|B,   W,     0,     M|X,   M|X,   M|B,   M|Z,   W|B,   0,     W,     0,     0,     B,     X,     0,     // C
+	M,     M,     M,     M,     B|X,   B|X,   X,     0,     M,     M,     M,     M,     M,     M,     M,     M,     // D
+	B|R,   B|R,   B|R,   B|R,   B,     B,     B,     B,     Z|R,   Z|R,   Z|W|X, B|R,   0,     0,     0,     0,     // E
+	P,     0,     P,     P,     0,     0,     M,     M,     0,     0,     0,     0,     0,     0,     M,     M,     // F
+];
+
+static TWO_BYTE: [u8; 256] = [
+	//  0      1      2      3      4      5      6      7      8      9      A      B      C      D      E      F
+	M,     M,     M,     M,     I,     0,     0,     0,     0,     0,     I,     0,     I,     M,     0,     M|B,   // 0
+	M,     M,     M,     M,     M,     M,     M,     M,     M,     M,     M,     M,     M,     M,     M,     M,     // 1
+	M,     M,     M,     M,     I,     I,     I,     I,     M,     M,     M,     M,     M,     M,     M,     M,     // 2
+	0,     0,     0,     0,     0,     0,     0,     0,     M,     I,     M|B,   I,     I,     I,     I,     I,     // 3
+	M,     M,     M,     M,     M,     M,     M,     M,     M,     M,     M,     M,     M,     M,     M,     M,     // 4
+	M,     M,     M,     M,     M,     M,     M,     M,     M,     M,     M,     M,     M,     M,     M,     M,     // 5
+	M,     M,     M,     M,     M,     M,     M,     M,     M,     M,     M,     M,     M,     M,     M,     M,     // 6
+	M|B,   M|B,   M|B,   M|B,   M,     M,     M,     0,     M,     M,     I,     I,     M,     M,     M,     M,     // 7
+	Z|R,   Z|R,   Z|R,   Z|R,   Z|R,   Z|R,   Z|R,   Z|R,   Z|R,   Z|R,   Z|R,   Z|R,   Z|R,   Z|R,   Z|R,   Z|R,   // 8
+	M,     M,     M,     M,     M,     M,     M,     M,     M,     M,     M,     M,     M,     M,     M,     M,     // 9
+	0,     0,     0,     M,     M|B,   M,     I,     I,     0,     0,     0,     M,     M|B,   M,     M,     M,     // A
+	M,     M,     M,     M,     M,     M,     M,     M,     M,     M,     M|B,   M,     M,     M,     M,     M,     // B
+	M,     M,     M|B,   M,     M|B,   M|B,   M|B,   M,     0,     0,     0,     0,     0,     0,     0,     0,     // C
+	M,     M,     M,     M,     M,     M,     M,     M,     M,     M,     M,     M,     M,     M,     M,     M,     // D
+	M,     M,     M,     M,     M,     M,     M,     M,     M,     M,     M,     M,     M,     M,     M,     M,     // E
+	M,     M,     M,     M,     M,     M,     M,     M,     M,     M,     M,     M,     M,     M,     M,     M,     // F
+];
+
+//----------------------------------------------------------------
+
+/// Instruction layout.
+///
+/// The offsets are relative to the start of the instruction, absent fields have an offset and size of zero.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct Insn {
+	/// Length of the instruction in bytes.
+	pub len: u8,
+	/// Offset of the opcode following the prefixes, including any `0F` escape bytes.
+	pub opcode: u8,
+	/// Offset of the ModRM byte.
+	pub modrm: u8,
+	/// Offset of the displacement.
+	pub disp_offset: u8,
+	/// Size of the displacement in bytes.
+	pub disp_size: u8,
+	/// Offset of the immediate.
+	pub imm_offset: u8,
+	/// Size of the immediate in bytes.
+	///
+	/// Instructions with two immediates such as `enter` and far pointers report them as one.
+	pub imm_size: u8,
+	/// The immediate is a relative branch offset, eg. `call`, `jmp` and `jcc`.
+	pub rel: bool,
+	/// The displacement is relative to the next instruction, only in 64-bit mode.
+	pub rip: bool,
+}
+impl Insn {
+	/// Returns the range of the displacement in the instruction bytes.
+	pub fn disp_range(&self) -> Range<usize> {
+		self.disp_offset as usize..(self.disp_offset + self.disp_size) as usize
+	}
+	/// Returns the range of the immediate in the instruction bytes.
+	pub fn imm_range(&self) -> Range<usize> {
+		self.imm_offset as usize..(self.imm_offset + self.imm_size) as usize
+	}
+	/// Reads the sign extended displacement from the instruction bytes.
+	pub fn disp(&self, bytes: &[u8]) -> Option<i64> {
+		read(bytes, self.disp_range())
+	}
+	/// Reads the sign extended immediate from the instruction bytes.
+	pub fn imm(&self, bytes: &[u8]) -> Option<i64> {
+		read(bytes, self.imm_range())
+	}
+	/// Returns the address referenced relative to the instruction at the given address.
+	///
+	/// This is the branch target of relative branches or the address of a RIP-relative operand.
+	pub fn target(&self, bytes: &[u8], address: u64) -> Option<u64> {
+		let offset = if self.rel { self.imm(bytes)? } else if self.rip { self.disp(bytes)? } else { return None };
+		Some(address.wrapping_add(self.len as u64).wrapping_add(offset as u64))
+	}
+}
+
+// Reads a sign extended little endian integer
+fn read(bytes: &[u8], range: Range<usize>) -> Option<i64> {
+	if range.start == range.end {
+		return None;
+	}
+	let field = bytes.get(range)?;
+	let value = field.iter().rev().fold(0u64, |acc, &byte| acc << 8 | byte as u64);
+	let shift = 64 - 8 * field.len() as u32;
+	Some(((value << shift) as i64) >> shift)
+}
+
+/// Decodes the instruction at the start of the bytes.
+///
+/// Returns [`Err(OOB)`](../enum.Error.html#variant.OOB) if the bytes end before the instruction does
+/// and [`Err(Corrupt)`](../enum.Error.html#variant.Corrupt) if the opcode is invalid or the instruction is longer than [`MAX_LEN`](constant.MAX_LEN.html).
+pub fn decode(bytes: &[u8], mode: Mode) -> Result<Insn> {
+	let x64 = mode == Mode::X64;
+	let byte = |i: usize| bytes.get(i).cloned().ok_or(Error::OOB);
+	let mut i = 0;
+
+	// Legacy and REX prefixes
+	let mut opsize = false;
+	let mut adsize = false;
+	let mut rex_w = false;
+	let mut op;
+	loop {
+		op = byte(i)?;
+		if ONE_BYTE[op as usize] == P {
+			match op {
+				0x66 => opsize = true,
+				0x67 => adsize = true,
+				_ => (),
+			}
+			// REX must immediately precede the opcode
+			rex_w = false;
+		}
+		else if x64 && op & 0xF0 == 0x40 {
+			rex_w = op & 0x08 != 0;
+		}
+		else {
+			break;
+		}
+		i += 1;
+		if i >= MAX_LEN {
+			return Err(Error::Corrupt);
+		}
+	}
+	// REX.W overrides the operand size prefix
+	if rex_w {
+		opsize = false;
+	}
+
+	let mut insn = Insn::default();
+	insn.opcode = i as u8;
+	i += 1;
+	let flags;
+	match op {
+		// Two and three byte opcodes
+		0x0F => {
+			let op2 = byte(i)?;
+			i += 1;
+			flags = match op2 {
+				0x38 => { i += 1; M },
+				0x3A => { i += 1; M | B },
+				_ => TWO_BYTE[op2 as usize],
+			};
+			if flags == I {
+				return Err(Error::Corrupt);
+			}
+			// Near branches are always 32-bit in 64-bit mode
+			if flags & R != 0 && x64 {
+				opsize = false;
+			}
+		},
+		// VEX and EVEX prefixes, these are LES, LDS and BOUND with a memory operand in 32-bit mode
+		0xC4 | 0xC5 | 0x62 if x64 || byte(i)? >= 0xC0 => {
+			let (map, len) = match op {
+				0xC5 => (1, 2),
+				0xC4 => (byte(i)? & 0x1F, 3),
+				_ => (byte(i)? & 0x07, 4),
+			};
+			i += len - 1;
+			let op2 = byte(i)?;
+			i += 1;
+			flags = match map {
+				1 if op2 == 0x77 && op != 0x62 => 0,
+				1 => M | (TWO_BYTE[op2 as usize] & B),
+				2 | 5 | 6 => M,
+				3 => M | B,
+				_ => return Err(Error::Corrupt),
+			};
+		},
+		_ => {
+			flags = ONE_BYTE[op as usize];
+			if x64 && flags & X != 0 {
+				return Err(Error::Corrupt);
+			}
+			if flags & R != 0 && x64 {
+				opsize = false;
+			}
+		},
+	}
+
+	// ModRM, SIB and displacement
+	let mut imm_size = 0;
+	if flags & M != 0 {
+		let modrm = byte(i)?;
+		insn.modrm = i as u8;
+		i += 1;
+		let md = modrm >> 6;
+		let rm = modrm & 7;
+		let disp_size = if !x64 && adsize {
+			// 16-bit addressing has no SIB
+			match (md, rm) {
+				(0, 6) => 2,
+				(1, _) => 1,
+				(2, _) => 2,
+				_ => 0,
+			}
+		}
+		else {
+			let base = if md != 3 && rm == 4 {
+				let sib = byte(i)?;
+				i += 1;
+				sib & 7
+			}
+			else {
+				rm
+			};
+			match (md, rm, base) {
+				(0, 5, _) => {
+					insn.rip = x64;
+					4
+				},
+				(0, _, 5) => 4,
+				(1, _, _) => 1,
+				(2, _, _) => 4,
+				_ => 0,
+			}
+		};
+		if disp_size != 0 {
+			insn.disp_offset = i as u8;
+			insn.disp_size = disp_size;
+			i += disp_size as usize;
+		}
+		// Only the TEST forms of group 3 have an immediate
+		if (op == 0xF6 || op == 0xF7) && insn.opcode as usize + 1 == insn.modrm as usize && modrm & 0x30 == 0 {
+			imm_size = if op == 0xF6 { 1 } else if opsize { 2 } else { 4 };
+		}
+	}
+
+	// Immediates
+	if flags != I {
+		if flags & B != 0 {
+			imm_size += 1;
+		}
+		if flags & W != 0 {
+			imm_size += 2;
+		}
+		if flags & Z != 0 {
+			imm_size += if opsize { 2 } else { 4 };
+		}
+		if flags & V != 0 {
+			imm_size += if rex_w { 8 } else if opsize { 2 } else { 4 };
+		}
+	}
+	// Memory offsets of `mov` have the size of an address
+	if ONE_BYTE[op as usize] == 0 && op >= 0xA0 && op <= 0xA3 && insn.opcode as usize + 1 == i {
+		imm_size = match (x64, adsize) {
+			(true, false) => 8,
+			(true, true) | (false, false) => 4,
+			(false, true) => 2,
+		};
+	}
+	if imm_size != 0 {
+		insn.imm_offset = i as u8;
+		insn.imm_size = imm_size;
+		insn.rel = flags & R != 0;
+		i += imm_size as usize;
+	}
+
+	if i > MAX_LEN {
+		return Err(Error::Corrupt);
+	}
+	if i > bytes.len() {
+		return Err(Error::OOB);
+	}
+	insn.len = i as u8;
+	Ok(insn)
+}
+
+/// Iterates over the instructions in the bytes.
+///
+/// Yields the offset and layout of every instruction until the end of the bytes or an instruction fails to decode.
+pub fn iter(bytes: &[u8], mode: Mode) -> Iter {
+	Iter { bytes, mode, offset: 0 }
+}
+
+/// Iterator over instructions.
+///
+/// Created with the function [`iter`](fn.iter.html).
+#[derive(Clone, Debug)]
+pub struct Iter<'a> {
+	bytes: &'a [u8],
+	mode: Mode,
+	offset: usize,
+}
+impl<'a> Iter<'a> {
+	/// Returns the offset of the next instruction.
+	pub fn offset(&self) -> usize {
+		self.offset
+	}
+}
+impl<'a> Iterator for Iter<'a> {
+	type Item = (usize, Insn);
+	fn next(&mut self) -> Option<(usize, Insn)> {
+		let insn = decode(self.bytes.get(self.offset..)?, self.mode).ok()?;
+		let offset = self.offset;
+		self.offset += insn.len as usize;
+		Some((offset, insn))
+	}
+}
+
+//----------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn len(bytes: &[u8], mode: Mode) -> Result<u8> {
+		decode(bytes, mode).map(|insn| insn.len)
+	}
+
+	#[test]
+	fn lengths() {
+		let cases: &[(Mode, &[u8])] = &[
+			(Mode::X64, &[0xC3]),                                                 // ret
+			(Mode::X64, &[0x48, 0x83, 0xEC, 0x28]),                               // sub rsp, 28h
+			(Mode::X64, &[0x48, 0x8B, 0x05, 0x11, 0x22, 0x33, 0x44]),             // mov rax, [rip+44332211h]
+			(Mode::X64, &[0x48, 0xB8, 1, 2, 3, 4, 5, 6, 7, 8]),                   // mov rax, imm64
+			(Mode::X64, &[0x66, 0xB8, 1, 2]),                                     // mov ax, imm16
+			(Mode::X64, &[0x48, 0x89, 0x5C, 0x24, 0x08]),                         // mov [rsp+8], rbx
+			(Mode::X64, &[0x65, 0x48, 0x8B, 0x04, 0x25, 0x58, 0, 0, 0]),          // mov rax, gs:[58h]
+			(Mode::X64, &[0xC7, 0x44, 0x24, 0x20, 1, 0, 0, 0]),                   // mov dword [rsp+20h], 1
+			(Mode::X64, &[0xF7, 0xC1, 1, 0, 0, 0]),                               // test ecx, 1
+			(Mode::X64, &[0x66, 0x48, 0x81, 0xC0, 1, 0, 0, 0]),                   // add rax, 1
+			(Mode::X64, &[0x66, 0x48, 0xF7, 0xC1, 1, 0, 0, 0]),                   // test rcx, 1
+			(Mode::X64, &[0xF7, 0xD9]),                                           // neg ecx
+			(Mode::X64, &[0x0F, 0x85, 1, 2, 3, 4]),                               // jne rel32
+			(Mode::X64, &[0x66, 0x0F, 0x3A, 0x0F, 0xC1, 0x08]),                   // palignr xmm0, xmm1, 8
+			(Mode::X64, &[0xC5, 0xF8, 0x77]),                                     // vzeroupper
+			(Mode::X64, &[0xC4, 0xE3, 0x79, 0x14, 0xC0, 0x01]),                   // vpextrb eax, xmm0, 1
+			(Mode::X64, &[0x62, 0xF1, 0x7C, 0x48, 0x10, 0x44, 0x24, 0x01]),       // vmovups zmm0, [rsp+40h]
+			(Mode::X64, &[0xA1, 1, 2, 3, 4, 5, 6, 7, 8]),                         // mov eax, [moffs64]
+			(Mode::X64, &[0x66, 0x66, 0x0F, 0x1F, 0x84, 0, 0, 0, 0, 0]),          // nop word [rax+rax]
+			(Mode::X86, &[0xA1, 1, 2, 3, 4]),                                     // mov eax, [moffs32]
+			(Mode::X86, &[0x40]),                                                 // inc eax
+			(Mode::X86, &[0x66, 0xE8, 1, 2]),                                     // call rel16
+			(Mode::X86, &[0x67, 0x8B, 0x46, 0x10]),                               // mov eax, [bp+10h]
+			(Mode::X86, &[0xC4, 0x06]),                                           // les eax, [esi]
+			(Mode::X86, &[0x9A, 1, 2, 3, 4, 5, 6]),                               // call far ptr16:32
+			(Mode::X86, &[0xC8, 0x10, 0x00, 0x00]),                               // enter 10h, 0
+			(Mode::X86, &[0x8B, 0x0D, 1, 2, 3, 4]),                               // mov ecx, [abs32]
+		];
+		for &(mode, bytes) in cases {
+			assert_eq!(len(bytes, mode), Ok(bytes.len() as u8), "{:?} {:02X?}", mode, bytes);
+			// Truncated instructions are out of bounds
+			assert_eq!(len(&bytes[..bytes.len() - 1], mode), Err(Error::OOB), "{:?} {:02X?}", mode, bytes);
+		}
+	}
+
+	#[test]
+	fn fields() {
+		let bytes = [0x48, 0x8B, 0x05, 0x10, 0x00, 0x00, 0x00];
+		let insn = decode(&bytes, Mode::X64).unwrap();
+		assert_eq!((insn.opcode, insn.modrm), (1, 2));
+		assert_eq!(insn.disp_range(), 3..7);
+		assert!(insn.rip && !insn.rel);
+		assert_eq!(insn.target(&bytes, 0x1000), Some(0x1017));
+
+		let bytes = [0x81, 0x7C, 0x24, 0xF0, 0x44, 0x33, 0x22, 0x11];
+		let insn = decode(&bytes, Mode::X86).unwrap();
+		assert_eq!(insn.disp(&bytes), Some(-0x10));
+		assert_eq!(insn.imm(&bytes), Some(0x11223344));
+		assert_eq!(insn.target(&bytes, 0), None);
+
+		let bytes = [0xEB, 0xFE];
+		let insn = decode(&bytes, Mode::X64).unwrap();
+		assert_eq!(insn.target(&bytes, 0x1000), Some(0x1000));
+
+		assert_eq!(decode(&[0x06], Mode::X64), Err(Error::Corrupt));
+		assert_eq!(decode(&[0x0F, 0x0B], Mode::X64).map(|insn| insn.len), Ok(2));
+		assert_eq!(decode(&[0x66; 16], Mode::X64), Err(Error::Corrupt));
+
+		let code = [0x55, 0x48, 0x89, 0xE5, 0xE8, 0, 0, 0, 0, 0x5D, 0xC3];
+		let offsets: Vec<usize> = iter(&code, Mode::X64).map(|(offset, _)| offset).collect();
+		assert_eq!(offsets, [0, 1, 4, 9, 10]);
+	}
+}
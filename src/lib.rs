@@ -24,6 +24,10 @@ pub mod util;
 
 pub mod pattern;
 
+pub mod lde;
+
+pub mod sigdb;
+
 mod error;
 pub use self::error::{Error, Result};
 
@@ -44,3 +48,7 @@ pub use pe32 as pe;
 pub use pe64 as pe;
 
 pub mod resources;
+
+pub mod pdb;
+
+pub mod symstore;
@@ -36,10 +36,12 @@ This requires knowledge with reverse engineering programs.
 Here's a resource to learn more about signature scanning: [wiki.alliedmods.net](https://wiki.alliedmods.net/Signature_scanning).
 */
 
-use std::{error, fmt, mem};
+use std::{error, fmt, mem, slice, str};
 
 /// Max recursion depth.
-pub const STACK_SIZE: usize = 4;
+pub const STACK_SIZE: usize = 16;
+/// Max save slots addressable by a pattern.
+pub const MAX_SLOTS: usize = 256;
 /// Special skip value to indicate to use platform pointer size instead.
 pub(crate) const PTR_SKIP: i8 = -128;
 
@@ -65,6 +67,9 @@ impl error::Error for ParsePatError {
 			PatError::StackError => "stack error",
 			PatError::SyntaxError => "invalid syntax",
 			PatError::UnclosedQuote => "unclosed quote",
+			PatError::GroupTooLong => "group too long",
+			PatError::BadRepeat => "bad repeat",
+			PatError::DuplicateName => "duplicate name",
 		}
 	}
 }
@@ -78,6 +83,9 @@ enum PatError {
 	StackError,
 	SyntaxError,
 	UnclosedQuote,
+	GroupTooLong,
+	BadRepeat,
+	DuplicateName,
 }
 
 //----------------------------------------------------------------
@@ -115,6 +123,49 @@ pub enum Atom {
 	///
 	/// Reads the pointer under the cursor, translates it to an RVA, assigns it to the cursor and continues matching.
 	Ptr,
+	/// Sets the mask applied to the byte under the cursor by the next `Byte` atom.
+	///
+	/// The byte matches if the masked byte under the cursor equals the `Byte` argument.
+	Fuzzy(u8),
+	/// Starts an alternative.
+	///
+	/// When matching fails before the alternative's `Break`, matching resumes at the next alternative given by the relative atom index argument with the cursor restored.
+	/// The last alternative isn't started with a `Case`, its failure fails the whole alternation.
+	///
+	/// Alternations are limited to a nesting depth of [`STACK_SIZE`](constant.STACK_SIZE.html).
+	Case(u8),
+	/// Ends an alternative which matched.
+	///
+	/// Skips the given number of atoms past the remaining alternatives. Once an alternative matched the others are never tried.
+	Break(u8),
+	/// Matches the following `Byte` atom, optionally preceded by its `Fuzzy` mask, up to the given number of times.
+	///
+	/// The repetition is greedy and does not backtrack.
+	Many(u8),
+	/// Reads the byte under the cursor into the save slot and advances the cursor.
+	ReadU8(u8),
+	/// Reads the signed byte under the cursor, sign extended, into the save slot and advances the cursor.
+	ReadI8(u8),
+	/// Reads the word under the cursor into the save slot and advances the cursor.
+	ReadU16(u8),
+	/// Reads the signed word under the cursor, sign extended, into the save slot and advances the cursor.
+	ReadI16(u8),
+	/// Reads the dword under the cursor into the save slot and advances the cursor.
+	ReadU32(u8),
+	/// Reads the signed dword under the cursor into the save slot and advances the cursor.
+	ReadI32(u8),
+	/// Reads the qword under the cursor into the save slot and the next and advances the cursor.
+	///
+	/// The low dword is saved in the given slot and the high dword in the next slot.
+	ReadU64(u8),
+	/// Compares the cursor with the value in the save slot.
+	///
+	/// Matching fails if they are not equal.
+	Check(u8),
+	/// Checks the cursor is aligned to `2^N` bytes.
+	///
+	/// Matching fails if it isn't aligned.
+	Aligned(u8),
 }
 
 /// Patterns are a vector of [`Atom`](enum.Atom.html)s.
@@ -143,8 +194,13 @@ pub type Pattern = Vec<Atom>;
 ///
 ///   A common pattern is `${'}` which follows a relative jump and saves the destination address before returning back to continue matching.
 ///
-///   The saved cursors can be accessed through the `Match` struct starting from `Match.1` for the first backtick limited by the size of [`Match`](struct.Match.html).
+///   The saved cursors can be accessed through the `Match` struct starting from `Match.1` for the first backtick or value read limited by the size of [`Match`](struct.Match.html).
 ///   `Match.0` is reserved for the address of the start of the pattern match.
+///   Patterns may use up to [`MAX_SLOTS`](constant.MAX_SLOTS.html) slots, scan into a larger save buffer to access the slots which don't fit in `Match`.
+///
+/// * `'<name>`, `u4<name>`
+///
+///   Names the save slot of a backtick or value read, eg. `E8${'<func>}`. See [`parse_named`](fn.parse_named.html) to look up the slots by name.
 ///
 /// * `?`
 ///
@@ -187,6 +243,45 @@ pub type Pattern = Vec<Atom>;
 ///
 ///   The bytes used to jump (1 for `%`, 4 for `$` and 4 or 8 for `*` depending on bitness) are skipped when returning.
 ///
+/// * `A?`, `?A`
+///
+///   A hexadecimal digit and a question mark match a single nibble, the other nibble is a wildcard.
+///
+///   For compatibility `?` followed by a full byte, eg. `?AA`, is a byte wildcard followed by a byte.
+///
+/// * `AA&MM`
+///
+///   Matches the byte masked by the hexadecimal mask following the ampersand, eg. `8B&C7` matches `8B` with any register in its ModRM.
+///
+/// * `(AA|BB)`
+///
+///   Matches the first of the alternative subpatterns separated by `|` which matches.
+///   Alternatives do not backtrack: once an alternative matched the others are not tried if the rest of the pattern fails.
+///
+///   Alternations can be nested up to a depth of [`STACK_SIZE`](constant.STACK_SIZE.html), each alternative is limited to 255 atoms.
+///
+/// * `AA[N]`, `AA[N-M]`
+///
+///   Matches the preceding byte, including its mask or nibble wildcard, exactly `N` or between `N` and `M` times (up to 255).
+///
+///   The repetition is greedy and does not backtrack, eg. `90[0-8] 90` never matches.
+///
+/// * `u1`, `i1`, `u2`, `i2`, `u4`, `i4`, `u8`
+///
+///   Reads the unsigned or signed 1, 2, 4 or 8 byte value under the cursor into the next save slot and advances the cursor past it.
+///
+///   Saves the value instead of the cursor, eg. `8B81 u4` captures the field offset of the `mov`.
+///   Signed values are sign extended, use `m.1 as i32` to get the value back.
+///   The 8 byte value takes two slots, the low dword followed by the high dword.
+///
+/// * `=N`
+///
+///   Checks the cursor equals the value in save slot `N`, eg. `E8${=1}` checks a call targets the cursor saved earlier.
+///
+/// * `@N`
+///
+///   Checks the cursor is aligned to `2^N` bytes, eg. `@4` for 16 byte alignment.
+///
 /// * `"literal"`
 ///
 ///   Match raw bytes in between the quotes. Handy for matching string constants.
@@ -212,20 +307,110 @@ pub type Pattern = Vec<Atom>;
 ///
 /// let pat = parse("E9${'} C3").unwrap();
 /// assert_eq!(pat, &MY_PATTERN);
+///
+/// // Matches both `je` and `jne` with any ModRM register for the `mov` and padding in between
+/// let pat = parse("(74|75)? 8B&C7 90[0-8] C3").unwrap();
 /// ```
 #[inline]
 pub fn parse<P: ?Sized + AsRef<str>>(pat: &P) -> Result<Pattern, ParsePatError> {
-	parse_pat(pat.as_ref()).map_err(|e| ParsePatError(e))
+	parse_pat(pat.as_ref(), &mut Vec::new()).map_err(|e| ParsePatError(e))
+}
+
+/// Parses a pattern string with named captures.
+///
+/// Same as [`parse`](fn.parse.html) but also returns the save slots of the names given to backticks and value reads.
+///
+/// # Examples
+///
+/// ```
+/// use pelite::pattern::{parse_named, Atom};
+///
+/// let (pat, names) = parse_named("8B15 u4<disp> E8${'<func>}").unwrap();
+/// assert_eq!(pat[3], Atom::ReadU32(1));
+/// assert_eq!(names.slot("disp"), Some(1));
+/// assert_eq!(names.slot("func"), Some(2));
+/// assert_eq!(names.slot("other"), None);
+/// ```
+pub fn parse_named<P: ?Sized + AsRef<str>>(pat: &P) -> Result<(Pattern, Names), ParsePatError> {
+	let mut names = Vec::new();
+	let pat = parse_pat(pat.as_ref(), &mut names).map_err(|e| ParsePatError(e))?;
+	Ok((pat, Names(names)))
+}
+
+/// Named save slots of a pattern.
+///
+/// Created by [`parse_named`](fn.parse_named.html).
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Names(Vec<(String, usize)>);
+impl Names {
+	/// Returns the save slot of the name.
+	pub fn slot(&self, name: &str) -> Option<usize> {
+		self.0.iter().find(|&&(ref it, _)| it == name).map(|&(_, slot)| slot)
+	}
+	/// Returns the value of the name in the save buffer.
+	pub fn get(&self, name: &str, save: &[u32]) -> Option<u32> {
+		self.slot(name).and_then(|slot| save.get(slot).cloned())
+	}
+	/// Iterates over the names and their save slots in pattern order.
+	pub fn iter(&self) -> slice::Iter<(String, usize)> {
+		self.0.iter()
+	}
 }
 
-fn parse_pat(pat: &str) -> Result<Pattern, PatError> {
+// Alternation being parsed
+struct Group {
+	// Index of the Case atom of the current alternative
+	case: usize,
+	// Indices of the Break atoms ending the previous alternatives
+	breaks: Vec<usize>,
+	// Recursion depth at the start of the group
+	depth: usize,
+}
+
+fn hex_digit(chr: u8) -> Option<u8> {
+	match chr {
+		b'0'...b'9' => Some(chr - b'0'),
+		b'A'...b'F' => Some(chr - b'A' + 0xA),
+		b'a'...b'f' => Some(chr - b'a' + 0xA),
+		_ => None,
+	}
+}
+
+// Parses the optional `<name>` of the save slot
+fn parse_name(bytes: &[u8], i: &mut usize, slot: usize, names: &mut Vec<(String, usize)>) -> Result<(), PatError> {
+	if bytes.get(*i) != Some(&b'<') {
+		return Ok(());
+	}
+	let len = bytes[*i + 1..].iter().position(|&chr| chr == b'>').ok_or(PatError::SyntaxError)?;
+	let name = &bytes[*i + 1..*i + 1 + len];
+	let valid = name.iter().all(|&chr| match chr { b'0'...b'9' | b'A'...b'Z' | b'a'...b'z' | b'_' => true, _ => false });
+	if name.is_empty() || !valid {
+		return Err(PatError::SyntaxError);
+	}
+	let name = str::from_utf8(name).unwrap();
+	if names.iter().any(|&(ref it, _)| it == name) {
+		return Err(PatError::DuplicateName);
+	}
+	names.push((name.to_owned(), slot));
+	*i += len + 2;
+	Ok(())
+}
+
+fn parse_pat(pat: &str, names: &mut Vec<(String, usize)>) -> Result<Pattern, PatError> {
 	let mut result = Vec::with_capacity(pat.len() / 2);
 	result.push(Atom::Save(0));
-	let mut iter = pat.as_bytes().iter();
+	let bytes = pat.as_bytes();
+	let mut i = 0;
 	let mut depth = 0;
 	let mut save = 1;
 	let mut jump = None;
-	while let Some(&chr) = iter.next() {
+	let mut groups: Vec<Group> = Vec::new();
+	// End of the last closed group, its atoms must not be modified
+	let mut group_end = 0;
+	// End of the last alternation, the offsets of its breaks must not change
+	let mut break_end = 0;
+	while let Some(&chr) = bytes.get(i) {
+		i += 1;
 		match chr {
 			// Follow signed 1 byte jump
 			b'%' => {
@@ -263,38 +448,172 @@ fn parse_pat(pat: &str) -> Result<Pattern, PatError> {
 			// End recursive operator
 			b'}' => {
 				// Unbalanced recursion
-				if depth <= 0 {
+				if depth <= groups.last().map(|group| group.depth).unwrap_or(0) {
 					return Err(PatError::StackError);
 				}
 				depth -= 1;
 				result.push(Atom::Pop);
 				jump = None;
 			},
-			// Match a byte
+			// Start an alternation
+			b'(' => {
+				// Limited nesting depth
+				if groups.len() >= STACK_SIZE {
+					return Err(PatError::StackOverflow);
+				}
+				groups.push(Group { case: result.len(), breaks: Vec::new(), depth });
+				result.push(Atom::Case(0));
+				jump = None;
+			},
+			// Start the next alternative
+			b'|' => {
+				let group = groups.last_mut().ok_or(PatError::SyntaxError)?;
+				if depth != group.depth {
+					return Err(PatError::StackError);
+				}
+				group.breaks.push(result.len());
+				result.push(Atom::Break(0));
+				// Failing the previous alternative continues at this one
+				let next = result.len() - group.case;
+				if next > 255 {
+					return Err(PatError::GroupTooLong);
+				}
+				result[group.case] = Atom::Case(next as u8);
+				group.case = result.len();
+				result.push(Atom::Case(0));
+				jump = None;
+			},
+			// End an alternation
+			b')' => {
+				let group = groups.pop().ok_or(PatError::StackError)?;
+				if depth != group.depth {
+					return Err(PatError::StackError);
+				}
+				// The last alternative has nothing left to try
+				result.remove(group.case);
+				// The atoms of nested groups moved down with it
+				if break_end > group.case {
+					break_end -= 1;
+				}
+				// Without alternatives the group is transparent, merge the skips around its start
+				if group.breaks.is_empty() && group.case != break_end {
+					if let (Atom::Skip(before), Some(&Atom::Skip(after))) = (result[group.case - 1], result.get(group.case)) {
+						let total = before as i32 + after as i32;
+						if total <= 127 {
+							result[group.case - 1] = Atom::Skip(total as i8);
+							result.remove(group.case);
+							if break_end > group.case {
+								break_end -= 1;
+							}
+						}
+						else {
+							result[group.case - 1] = Atom::Skip(127);
+							result[group.case] = Atom::Skip((total - 127) as i8);
+						}
+					}
+				}
+				// Matched alternatives skip to the end of the group
+				for &index in &group.breaks {
+					let skip = result.len() - index - 1;
+					if skip > 255 {
+						return Err(PatError::GroupTooLong);
+					}
+					result[index] = Atom::Break(skip as u8);
+				}
+				group_end = result.len();
+				if !group.breaks.is_empty() {
+					break_end = result.len();
+				}
+				jump = None;
+			},
+			// Match a byte or a nibble
 			b'0'...b'9' | b'A'...b'F' | b'a'...b'f' => {
-				if let Some(&chr2) = iter.next() {
-					// High nibble of the byte
-					let hi = if chr >= b'a' { chr - b'a' + 0xA }
-						else if chr >= b'A' { chr - b'A' + 0xA }
-						else { chr - b'0' };
-					// Low nibble of the byte
-					let lo = if chr2 >= b'a' && chr2 <= b'f' { chr2 - b'a' + 0xA }
-						else if chr2 >= b'A' && chr2 <= b'F' { chr2 - b'A' + 0xA }
-						else if chr2 >= b'0' && chr2 <= b'9' { chr2 - b'0' }
-						else { return Err(PatError::UnpairedHexDigit); };
-					// Add byte to the pattern
-					result.push(Atom::Byte((hi << 4) + lo));
-					jump = None;
+				let hi = hex_digit(chr).unwrap();
+				match bytes.get(i) {
+					Some(&b'?') => {
+						result.push(Atom::Fuzzy(0xF0));
+						result.push(Atom::Byte(hi << 4));
+					},
+					Some(&chr2) => {
+						let lo = hex_digit(chr2).ok_or(PatError::UnpairedHexDigit)?;
+						result.push(Atom::Byte((hi << 4) + lo));
+					},
+					None => return Err(PatError::UnpairedHexDigit),
 				}
-				else {
-					return Err(PatError::UnpairedHexDigit);
+				i += 1;
+				jump = None;
+			},
+			// Mask the previous byte
+			b'&' => {
+				let mask = match (bytes.get(i).and_then(|&chr| hex_digit(chr)), bytes.get(i + 1).and_then(|&chr| hex_digit(chr))) {
+					(Some(hi), Some(lo)) => (hi << 4) + lo,
+					_ => return Err(PatError::UnpairedHexDigit),
+				};
+				i += 2;
+				let len = result.len();
+				if len == group_end {
+					return Err(PatError::SyntaxError);
 				}
+				let byte = match result[len - 1] {
+					Atom::Byte(byte) => byte,
+					_ => return Err(PatError::SyntaxError),
+				};
+				// Combine with the mask of a nibble wildcard
+				match result[len - 2] {
+					Atom::Fuzzy(fuzzy) if len - 2 >= group_end => {
+						result[len - 2] = Atom::Fuzzy(fuzzy & mask);
+						result[len - 1] = Atom::Byte(byte & fuzzy & mask);
+					},
+					// A full mask matches the byte as is
+					_ if mask == 0xFF => (),
+					_ => {
+						result[len - 1] = Atom::Fuzzy(mask);
+						result.push(Atom::Byte(byte & mask));
+					},
+				}
+				jump = None;
+			},
+			// Repeat the previous byte
+			b'[' => {
+				let close = bytes[i..].iter().position(|&chr| chr == b']').ok_or(PatError::BadRepeat)?;
+				let range = str::from_utf8(&bytes[i..i + close]).map_err(|_| PatError::BadRepeat)?;
+				i += close + 1;
+				let (min, max) = match range.find('-') {
+					Some(dash) => (range[..dash].parse::<u8>(), range[dash + 1..].parse::<u8>()),
+					None => (range.parse::<u8>(), range.parse::<u8>()),
+				};
+				let (min, max) = match (min, max) {
+					(Ok(min), Ok(max)) if min <= max => (min, max),
+					_ => return Err(PatError::BadRepeat),
+				};
+				// Take the byte with its mask
+				let len = result.len();
+				if len == group_end || len < 2 {
+					return Err(PatError::BadRepeat);
+				}
+				let atoms = match (result[len - 2], result[len - 1]) {
+					(Atom::Fuzzy(mask), Atom::Byte(byte)) if len - 2 >= group_end => vec![Atom::Fuzzy(mask), Atom::Byte(byte)],
+					(_, Atom::Byte(byte)) => vec![Atom::Byte(byte)],
+					_ => return Err(PatError::BadRepeat),
+				};
+				result.truncate(len - atoms.len());
+				for _ in 0..min {
+					result.extend_from_slice(&atoms);
+				}
+				if max > min {
+					result.push(Atom::Many(max - min));
+					result.extend_from_slice(&atoms);
+				}
+				// The repeated atoms must not be modified any further
+				group_end = result.len();
+				jump = None;
 			},
 			// Match raw bytes
 			b'"' => {
 				jump = None;
 				loop {
-					if let Some(&chr) = iter.next() {
+					if let Some(&chr) = bytes.get(i) {
+						i += 1;
 						if chr != b'"' {
 							result.push(Atom::Byte(chr));
 						}
@@ -307,26 +626,81 @@ fn parse_pat(pat: &str) -> Result<Pattern, PatError> {
 					}
 				}
 			},
+			// Read a value into the next slot
+			b'u' | b'i' => {
+				let size = bytes.get(i).cloned();
+				i += 1;
+				let atom = match (chr, size) {
+					(b'u', Some(b'1')) => Atom::ReadU8(save as u8),
+					(b'i', Some(b'1')) => Atom::ReadI8(save as u8),
+					(b'u', Some(b'2')) => Atom::ReadU16(save as u8),
+					(b'i', Some(b'2')) => Atom::ReadI16(save as u8),
+					(b'u', Some(b'4')) => Atom::ReadU32(save as u8),
+					(b'i', Some(b'4')) => Atom::ReadI32(save as u8),
+					(b'u', Some(b'8')) => Atom::ReadU64(save as u8),
+					_ => return Err(PatError::SyntaxError),
+				};
+				// Limited save space
+				let slots = if size == Some(b'8') { 2 } else { 1 };
+				if save + slots > MAX_SLOTS {
+					return Err(PatError::SaveOverflow);
+				}
+				result.push(atom);
+				parse_name(bytes, &mut i, save, names)?;
+				save += slots;
+				jump = None;
+			},
+			// Compare the cursor or its alignment
+			b'=' | b'@' => {
+				let len = bytes[i..].iter().take_while(|&&chr| chr >= b'0' && chr <= b'9').count();
+				let arg = str::from_utf8(&bytes[i..i + len]).unwrap().parse::<u8>().map_err(|_| PatError::SyntaxError)?;
+				i += len;
+				if chr == b'=' {
+					if arg as usize >= save {
+						return Err(PatError::SaveOverflow);
+					}
+					result.push(Atom::Check(arg));
+				}
+				else {
+					if arg >= 32 {
+						return Err(PatError::SyntaxError);
+					}
+					result.push(Atom::Aligned(arg));
+				}
+				jump = None;
+			},
 			// Save the cursor
 			b'\'' => {
 				// Limited save space
-				if save >= (MAX_SAVE as u8) {
+				if save >= MAX_SLOTS {
 					return Err(PatError::SaveOverflow);
 				}
-				result.push(Atom::Save(save));
+				result.push(Atom::Save(save as u8));
+				parse_name(bytes, &mut i, save, names)?;
 				save += 1;
 				jump = None;
 			},
-			// Skip bytes
+			// Skip bytes or match a nibble
 			b'?' => {
 				jump = None;
-				// Coalescence skips together
-				if let Some(&mut Atom::Skip(ref mut s)) = result.last_mut() {
-					if *s != PTR_SKIP && *s < 127i8 {
-						*s += 1;
+				// A nibble unless followed by a whole byte
+				if let Some(lo) = bytes.get(i).and_then(|&chr| hex_digit(chr)) {
+					if bytes.get(i + 1).and_then(|&chr| hex_digit(chr)).is_none() {
+						result.push(Atom::Fuzzy(0x0F));
+						result.push(Atom::Byte(lo));
+						i += 1;
 						continue;
 					}
 				}
+				// Coalescence skips together
+				if result.len() != break_end {
+					if let Some(&mut Atom::Skip(ref mut s)) = result.last_mut() {
+						if *s != PTR_SKIP && *s < 127i8 {
+							*s += 1;
+							continue;
+						}
+					}
+				}
 				result.push(Atom::Skip(1));
 			},
 			// Allow spaces as padding
@@ -337,11 +711,11 @@ fn parse_pat(pat: &str) -> Result<Pattern, PatError> {
 			},
 		}
 	}
-	if depth != 0 {
+	if depth != 0 || !groups.is_empty() {
 		return Err(PatError::StackError);
 	}
-	// Remove redundant atoms
-	while match result.last() {
+	// Remove redundant atoms, unless the offsets of a group refer to them
+	while result.len() > break_end && match result.last() {
 		Some(&Atom::Skip(_)) | Some(&Atom::Pop) => true,
 		_ => false,
 	} {
@@ -352,6 +726,242 @@ fn parse_pat(pat: &str) -> Result<Pattern, PatError> {
 
 //----------------------------------------------------------------
 
+/// Formats a pattern in the syntax accepted by [`parse`](fn.parse.html).
+///
+/// # Remarks
+///
+/// The output is canonical: bytes are merged in uppercase hex runs, printable runs of at least 4 bytes are quoted literals,
+/// skips are merged `?` runs, jumps with their `Push` and `Pop` are `${ }` blocks and words are separated by a single space.
+///
+/// For any pattern returned by `parse` the output parses back to the same pattern, names of the save slots are not preserved.
+/// Patterns not created by `parse` may not have a textual representation in which case formatting fails.
+///
+/// # Examples
+///
+/// ```
+/// use pelite::pattern::{parse, display};
+///
+/// let pat = parse("4c8b41'? 4c2bc2 ???? 8b15${'} 85c9").unwrap();
+/// assert_eq!(display(&pat).to_string(), "4C8B41'? 4C2BC2 ???? 8B15${'} 85C9");
+///
+/// let pat = parse("48 8D 0D $ \"hello\" 00").unwrap();
+/// assert_eq!(display(&pat).to_string(), "488D0D$ \"hello\" 00");
+/// ```
+pub fn display(pat: &[Atom]) -> Display {
+	Display(pat)
+}
+
+/// Displays a pattern in its canonical syntax.
+///
+/// Created by [`display`](fn.display.html).
+#[derive(Copy, Clone, Debug)]
+pub struct Display<'a>(&'a [Atom]);
+impl<'a> fmt::Display for Display<'a> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		// The start of the match is saved implicitly
+		let pat = match self.0.first() {
+			Some(&Atom::Save(0)) => &self.0[1..],
+			_ => self.0,
+		};
+		let mut writer = Writer { f, word: false, depth: 0 };
+		writer.atoms(pat)?;
+		// Trailing pops were removed by the parser
+		for _ in 0..writer.depth {
+			writer.token(false, true, "}")?;
+		}
+		Ok(())
+	}
+}
+
+struct Writer<'a, 'b: 'a> {
+	f: &'a mut fmt::Formatter<'b>,
+	// Previous token must be separated from a following word
+	word: bool,
+	// Number of unpopped pushes
+	depth: usize,
+}
+impl<'a, 'b> Writer<'a, 'b> {
+	// Writes a token, words are separated by a space
+	fn token(&mut self, before: bool, after: bool, token: &str) -> fmt::Result {
+		if before && self.word {
+			self.f.write_str(" ")?;
+		}
+		self.word = after;
+		self.f.write_str(token)
+	}
+	// Writes the byte matched under the mask
+	fn byte(&mut self, mask: u8, byte: u8, repeat: Option<u8>) -> fmt::Result {
+		match mask {
+			0xFF => write!(self.f, "{:02X}", byte)?,
+			0xF0 => write!(self.f, "{:X}?", byte >> 4)?,
+			0x0F => write!(self.f, "?{:X}", byte & 0xF)?,
+			_ => write!(self.f, "{:02X}&{:02X}", byte, mask)?,
+		}
+		if let Some(limit) = repeat {
+			write!(self.f, "[0-{}]", limit)?;
+		}
+		Ok(())
+	}
+	// Writes a run of bytes quoting the printable parts
+	fn bytes(&mut self, bytes: &[u8]) -> fmt::Result {
+		let mut i = 0;
+		while i < bytes.len() {
+			let printable = bytes[i..].iter().take_while(|&&byte| byte >= 0x20 && byte < 0x7F && byte != b'"').count();
+			if printable >= 4 {
+				let text = str::from_utf8(&bytes[i..i + printable]).unwrap();
+				self.token(true, true, "\"")?;
+				self.f.write_str(text)?;
+				self.f.write_str("\"")?;
+				i += printable;
+				continue;
+			}
+			let start = i;
+			i += 1;
+			while i < bytes.len() && bytes[i..].iter().take_while(|&&byte| byte >= 0x20 && byte < 0x7F && byte != b'"').count() < 4 {
+				i += 1;
+			}
+			self.token(true, true, "")?;
+			for &byte in &bytes[start..i] {
+				write!(self.f, "{:02X}", byte)?;
+			}
+		}
+		Ok(())
+	}
+	fn atoms(&mut self, pat: &[Atom]) -> fmt::Result {
+		let mut i = 0;
+		while let Some(&atom) = pat.get(i) {
+			i += 1;
+			match atom {
+				Atom::Byte(_) => {
+					let start = i - 1;
+					while let Some(&Atom::Byte(_)) = pat.get(i) {
+						i += 1;
+					}
+					let bytes: Vec<u8> = pat[start..i].iter().map(|atom| match *atom { Atom::Byte(byte) => byte, _ => 0 }).collect();
+					self.bytes(&bytes)?;
+				},
+				Atom::Fuzzy(mask) => {
+					let byte = match pat.get(i) {
+						Some(&Atom::Byte(byte)) => byte,
+						_ => return Err(fmt::Error),
+					};
+					i += 1;
+					self.token(true, true, "")?;
+					self.byte(mask, byte, None)?;
+				},
+				Atom::Many(limit) => {
+					let (mask, byte) = match (pat.get(i), pat.get(i + 1)) {
+						(Some(&Atom::Fuzzy(mask)), Some(&Atom::Byte(byte))) => (mask, byte),
+						(Some(&Atom::Byte(byte)), _) => (0xFF, byte),
+						_ => return Err(fmt::Error),
+					};
+					i += if mask == 0xFF { 1 } else { 2 };
+					self.token(true, true, "")?;
+					self.byte(mask, byte, Some(limit))?;
+				},
+				Atom::Save(_) => self.token(false, false, "'")?,
+				Atom::Push(skip) => {
+					let jump = match (skip, pat.get(i)) {
+						(1, Some(&Atom::Jump1)) => "%{",
+						(4, Some(&Atom::Jump4)) => "${",
+						(PTR_SKIP, Some(&Atom::Ptr)) => "*{",
+						_ => return Err(fmt::Error),
+					};
+					i += 1;
+					self.depth += 1;
+					self.token(false, false, jump)?;
+				},
+				Atom::Pop => {
+					if self.depth == 0 {
+						return Err(fmt::Error);
+					}
+					self.depth -= 1;
+					self.token(false, true, "}")?;
+				},
+				Atom::Skip(skip) => {
+					if skip <= 0 {
+						return Err(fmt::Error);
+					}
+					// Merge the skips split by the parser
+					let mut len = skip as usize;
+					while let Some(&Atom::Skip(skip)) = pat.get(i) {
+						if skip <= 0 || len % 127 != 0 {
+							break;
+						}
+						len += skip as usize;
+						i += 1;
+					}
+					self.token(true, true, "")?;
+					for _ in 0..len {
+						self.f.write_str("?")?;
+					}
+				},
+				Atom::Jump1 => self.token(false, true, "%")?,
+				Atom::Jump4 => self.token(false, true, "$")?,
+				Atom::Ptr => self.token(false, true, "*")?,
+				Atom::Case(_) => {
+					i = i - 1 + self.group(&pat[i - 1..])?;
+				},
+				Atom::Break(_) => return Err(fmt::Error),
+				Atom::ReadU8(_) => self.token(true, true, "u1")?,
+				Atom::ReadI8(_) => self.token(true, true, "i1")?,
+				Atom::ReadU16(_) => self.token(true, true, "u2")?,
+				Atom::ReadI16(_) => self.token(true, true, "i2")?,
+				Atom::ReadU32(_) => self.token(true, true, "u4")?,
+				Atom::ReadI32(_) => self.token(true, true, "i4")?,
+				Atom::ReadU64(_) => self.token(true, true, "u8")?,
+				Atom::Check(slot) => {
+					self.token(true, true, "=")?;
+					write!(self.f, "{}", slot)?;
+				},
+				Atom::Aligned(align) => {
+					self.token(true, true, "@")?;
+					write!(self.f, "{}", align)?;
+				},
+			}
+		}
+		Ok(())
+	}
+	// Writes the alternation starting at the Case atom, returns its length
+	fn group(&mut self, pat: &[Atom]) -> Result<usize, fmt::Error> {
+		let end = alternation_end(pat, 0).ok_or(fmt::Error)?;
+		self.token(true, false, "(")?;
+		let mut i = 0;
+		loop {
+			let next = match pat[i] {
+				Atom::Case(next) => next as usize,
+				_ => return Err(fmt::Error),
+			};
+			self.atoms(&pat[i + 1..i + next - 1])?;
+			self.token(false, false, "|")?;
+			i += next;
+			// Nested alternations ending with this one are indistinguishable from more alternatives
+			if alternation_end(pat, i) != Some(end) {
+				break;
+			}
+		}
+		self.atoms(&pat[i..end])?;
+		self.token(false, true, ")")?;
+		Ok(end)
+	}
+}
+
+// Finds the end of the alternation starting at the Case atom
+fn alternation_end(pat: &[Atom], case: usize) -> Option<usize> {
+	let next = match pat.get(case) {
+		Some(&Atom::Case(next)) if next >= 2 => next as usize,
+		_ => return None,
+	};
+	let index = case + next - 1;
+	let end = match pat.get(index) {
+		Some(&Atom::Break(skip)) => index + 1 + skip as usize,
+		_ => return None,
+	};
+	if end > pat.len() { None } else { Some(end) }
+}
+
+//----------------------------------------------------------------
+
 /// Max saved cursors.
 pub(crate) const MAX_SAVE: usize = 7;
 
@@ -360,6 +970,8 @@ pub(crate) const MAX_SAVE: usize = 7;
 /// The scanner populates the result with `Atom::Save(i)` where the cursor is saved at the tuple index `i`.
 ///
 /// Each backtick in a pattern writes to the next slot where the first element is the start of the pattern match.
+///
+/// Slots beyond the size of the tuple are dropped, the scanner methods ending in `_into` save to a caller provided buffer of any size instead.
 #[derive(Copy, Clone, Default, Eq, PartialEq, Debug)]
 pub struct Match(pub u32, pub u32, pub u32, pub u32, pub u32, pub u32, pub u32);
 impl AsRef<[u32; MAX_SAVE]> for Match {
@@ -430,18 +1042,223 @@ mod tests {
 		]));
 	}
 
+	#[test]
+	fn fuzzy() {
+		use self::Atom::*;
+
+		assert_eq!(parse("4? ?B 8B&C7"), Ok(vec![
+			Save(0), Fuzzy(0xF0), Byte(0x40), Fuzzy(0x0F), Byte(0x0B), Fuzzy(0xC7), Byte(0x83)
+		]));
+		assert_eq!(parse("4?&3F"), Ok(vec![Save(0), Fuzzy(0x30), Byte(0x00)]));
+		// Full masks are dropped to format the same as the plain byte
+		assert_eq!(parse("AB&FF"), parse("AB"));
+		assert_eq!(parse("4?&FF"), parse("4?"));
+
+		// A question mark followed by a whole byte is still a wildcard
+		assert_eq!(parse("?B8 ??8B"), Ok(vec![Save(0), Skip(1), Byte(0xB8), Skip(2), Byte(0x8B)]));
+
+		assert_eq!(parse("(74|75|0F8?)"), Ok(vec![
+			Save(0), Case(3), Byte(0x74), Break(6), Case(3), Byte(0x75), Break(3), Byte(0x0F), Fuzzy(0xF0), Byte(0x80)
+		]));
+		assert_eq!(parse("((12|34)|56) ?"), Ok(vec![
+			Save(0), Case(6), Case(3), Byte(0x12), Break(1), Byte(0x34), Break(1), Byte(0x56)
+		]));
+		// The breaks skip over the trailing wildcard of the last alternative
+		assert_eq!(parse("(90|?)"), Ok(vec![Save(0), Case(3), Byte(0x90), Break(1), Skip(1)]));
+		// Wildcards after a group are not merged into the group
+		assert_eq!(parse("((90|?))?AB"), Ok(vec![Save(0), Case(3), Byte(0x90), Break(1), Skip(1), Skip(1), Byte(0xAB)]));
+
+		assert_eq!(parse("90[0-2] CC[2] ?C[1-2]"), Ok(vec![
+			Save(0), Many(2), Byte(0x90), Byte(0xCC), Byte(0xCC), Fuzzy(0x0F), Byte(0x0C), Many(1), Fuzzy(0x0F), Byte(0x0C)
+		]));
+	}
+
+	#[test]
+	fn values() {
+		use self::Atom::*;
+
+		assert_eq!(parse("8B81 u4 'i1 u8 @4 =2"), Ok(vec![
+			Save(0), Byte(0x8B), Byte(0x81), ReadU32(1), Save(2), ReadI8(3), ReadU64(4), Aligned(4), Check(2)
+		]));
+	}
+
+	#[test]
+	fn names() {
+		use self::Atom::*;
+
+		let (pat, names) = parse_named("'<start> 8B15 i4<disp> ' u8<qword> '<end>").unwrap();
+		assert_eq!(pat, [Save(0), Save(1), Byte(0x8B), Byte(0x15), ReadI32(2), Save(3), ReadU64(4), Save(6)]);
+		let names: Vec<_> = names.iter().map(|&(ref name, slot)| (&name[..], slot)).collect();
+		assert_eq!(names, [("start", 1), ("disp", 2), ("qword", 4), ("end", 6)]);
+
+		// Well beyond the size of Match
+		let pat = parse(&"'?".repeat(MAX_SLOTS - 1)).unwrap();
+		assert_eq!(pat[pat.len() - 1], Save((MAX_SLOTS - 1) as u8));
+	}
+
+	#[test]
+	fn display() {
+		let canonical = |pat: &str| super::display(&parse(pat).unwrap()).to_string();
+
+		assert_eq!(canonical("12 34 56 ? ?"), "123456");
+		assert_eq!(canonical("B9'?? 68???? E8${'} 8B"), "B9'?? 68 ???? E8${'} 8B");
+		assert_eq!(canonical("${%{${%{}}}}"), "${%{${%{}}}}");
+		assert_eq!(canonical("\"string\" 00 \"ab\""), "\"string\" 006162");
+		assert_eq!(canonical("*{FF D8 42} C3"), "*{FFD842} C3");
+		assert_eq!(canonical("4? ?5 8B&C7 9?&3F 90[2-4] ?0[0-3]"), "4? ?5 83&C7 10&30 9090 90[0-2] ?0[0-3]");
+		assert_eq!(canonical("(74|75)% (EB|(90|91) C3|) (E8${'}|E9)"), "(74|75)% (EB|(90|91) C3|) (E8${'}|E9)");
+		assert_eq!(canonical("(90|(91|92))"), "(90|91|92)");
+		assert_eq!(canonical("(90|??)"), "(90|??)");
+		assert_eq!(canonical("8B81 u4<off> i1 ' u8 =1 @4 $ % *"), "8B81 u4 i1'u8 =1 @4$%*");
+		assert_eq!(canonical(&format!("90{}90", "?".repeat(300))), format!("90 {} 90", "?".repeat(300)));
+	}
+
+	// Xorshift for reproducible random patterns
+	fn next(seed: &mut u32, n: u32) -> u32 {
+		*seed ^= *seed << 13;
+		*seed ^= *seed >> 17;
+		*seed ^= *seed << 5;
+		*seed % n
+	}
+
+	// Generates random tokens, balanced groups and recursions are generated as a whole to make more of them valid
+	fn random_tokens(seed: &mut u32, depth: usize, tokens: &mut Vec<&'static str>) {
+		const TOKENS: [&str; 32] = [
+			"8B", "C3", "E8", "00", "4?", "?F", "??", "?", "&F0", "&3C", "&FF", "4?&FF", "[2]", "[0-3]", "\"text\"", "\"a\"",
+			"'", "${", "%{", "*{", "}", "$", "%", "(", "|", ")", "u1", "i4", "u8", "=1", "@2", " ",
+		];
+		for _ in 0..next(seed, 8) {
+			match next(seed, 8) {
+				0 if depth < 4 => {
+					tokens.push("(");
+					random_tokens(seed, depth + 1, tokens);
+					for _ in 0..next(seed, 3) {
+						tokens.push("|");
+						random_tokens(seed, depth + 1, tokens);
+					}
+					tokens.push(")");
+				},
+				1 if depth < 4 => {
+					tokens.push(["${", "%{", "*{"][next(seed, 3) as usize]);
+					random_tokens(seed, depth + 1, tokens);
+					tokens.push("}");
+				},
+				2 => tokens.push("?"),
+				_ => tokens.push(TOKENS[next(seed, TOKENS.len() as u32) as usize]),
+			}
+		}
+	}
+
+	// Generates random pattern strings for the round trip property
+	// Also returns an equivalent pattern whose wildcards cannot be merged, the alignment check always succeeds
+	fn random_pattern(seed: &mut u32) -> (String, String) {
+		let mut tokens = Vec::new();
+		random_tokens(seed, 0, &mut tokens);
+		let mut pat = String::new();
+		let mut unmerged = String::new();
+		for (i, &token) in tokens.iter().enumerate() {
+			pat.push_str(token);
+			// A wildcard followed by a nibble is a nibble itself
+			match (token, tokens.get(i + 1)) {
+				(_, Some(&"4?")) => unmerged.push_str(token),
+				("?", _) => unmerged.push_str("?@0 "),
+				("??", _) => unmerged.push_str("?@0 ?@0 "),
+				_ => unmerged.push_str(token),
+			}
+		}
+		(pat, unmerged)
+	}
+
+	// Generates bytes likely to be matched by the pattern taking random alternatives
+	fn random_bytes(pat: &[Atom], seed: &mut u32) -> Vec<u8> {
+		let mut bytes = Vec::new();
+		let mut mask = 0xFF;
+		let mut pc = 0;
+		while let Some(&atom) = pat.get(pc) {
+			pc += 1;
+			match atom {
+				Atom::Byte(byte) => {
+					bytes.push(byte | next(seed, 256) as u8 & !mask);
+					mask = 0xFF;
+				},
+				Atom::Fuzzy(fuzzy) => mask = fuzzy,
+				Atom::Skip(skip) => bytes.extend((0..skip).map(|_| next(seed, 256) as u8)),
+				// Jump to the next byte
+				Atom::Jump1 => bytes.push(0),
+				Atom::Jump4 => bytes.extend_from_slice(&[0; 4]),
+				Atom::Case(next_case) if next(seed, 2) == 0 => pc += next_case as usize - 1,
+				Atom::Break(skip) => pc += skip as usize,
+				Atom::ReadU8(_) | Atom::ReadI8(_) => bytes.push(next(seed, 256) as u8),
+				Atom::ReadU16(_) | Atom::ReadI16(_) => bytes.extend((0..2).map(|_| next(seed, 256) as u8)),
+				Atom::ReadU32(_) | Atom::ReadI32(_) => bytes.extend((0..4).map(|_| next(seed, 256) as u8)),
+				Atom::ReadU64(_) | Atom::Ptr => bytes.extend((0..8).map(|_| next(seed, 256) as u8)),
+				_ => (),
+			}
+		}
+		bytes.extend((0..4).map(|_| next(seed, 256) as u8));
+		bytes
+	}
+
+	#[test]
+	fn roundtrip() {
+		use pe64::scanner::{Scanner, Slice};
+		// Wildcards following nested groups are not merged into their alternatives
+		let bytes = [0x90, 0xCC, 0xAB];
+		let scanner = Scanner::new(Slice::new(&bytes, 0x1000));
+		assert!(scanner.exec(&parse("((90|?))?AB").unwrap(), 0).is_some());
+
+		let mut seed = 0x1234_5678;
+		let mut valid = 0;
+		for _ in 0..100000 {
+			let (text, unmerged) = random_pattern(&mut seed);
+			if let Ok(pat) = parse(&text) {
+				valid += 1;
+				let canonical = super::display(&pat).to_string();
+				assert_eq!(parse(&canonical), Ok(pat.clone()), "{:?} => {:?}", text, canonical);
+				assert_eq!(super::display(&parse(&canonical).unwrap()).to_string(), canonical);
+				// Merging the wildcards must not change what the pattern matches
+				// Digits following a check may split the next byte in a nibble swallowing the wildcard, then the reference is invalid
+				if let Ok(reference) = parse(&unmerged) {
+					for &atoms in &[&pat, &reference] {
+						let bytes = random_bytes(atoms, &mut seed);
+						let scanner = Scanner::new(Slice::new(&bytes, 0x1000));
+						assert_eq!(scanner.exec(&pat, 0), scanner.exec(&reference, 0), "{:?} in {:02X?}", text, bytes);
+					}
+				}
+			}
+		}
+		assert!(valid > 1000, "only {} valid patterns", valid);
+	}
+
 	#[test]
 	fn errors() {
 		use self::PatError::*;
-		assert_eq!(Err(ParsePatError(StackOverflow)), parse("${%{${%{${%{"));
+		assert_eq!(Err(ParsePatError(StackOverflow)), parse(&"${".repeat(STACK_SIZE + 1)));
 		assert_eq!(Err(ParsePatError(StackError)), parse("${"));
 		assert_eq!(Err(ParsePatError(StackError)), parse("}}"));
 		assert_eq!(Err(ParsePatError(SyntaxError)), parse("AB {}"));
 		assert_eq!(Err(ParsePatError(UnpairedHexDigit)), parse("123"));
 		assert_eq!(Err(ParsePatError(UnpairedHexDigit)), parse("EE BZ"));
-		assert_eq!(Err(ParsePatError(UnpairedHexDigit)), parse("A?"));
-		assert_eq!(Err(ParsePatError(SaveOverflow)), parse("'?'?'?'?'?'?'?'?"));
-		assert_eq!(Err(ParsePatError(UnknownChar)), parse("@"));
+		assert_eq!(Err(ParsePatError(UnpairedHexDigit)), parse("8B&C"));
+		assert_eq!(Err(ParsePatError(SyntaxError)), parse("&FF"));
+		assert_eq!(Err(ParsePatError(SyntaxError)), parse("12|34"));
+		assert_eq!(Err(ParsePatError(StackError)), parse("(12|34"));
+		assert_eq!(Err(ParsePatError(StackError)), parse("(E8${|34})"));
+		assert_eq!(Err(ParsePatError(BadRepeat)), parse("90[8-0]"));
+		assert_eq!(Err(ParsePatError(BadRepeat)), parse("?[1-2]"));
+		assert_eq!(Err(ParsePatError(BadRepeat)), parse("(90)[2]"));
+		assert_eq!(Err(ParsePatError(BadRepeat)), parse("[2]"));
+		assert_eq!(Err(ParsePatError(SyntaxError)), parse("u3"));
+		assert_eq!(Err(ParsePatError(SyntaxError)), parse("i8"));
+		assert_eq!(Err(ParsePatError(SyntaxError)), parse("@"));
+		assert_eq!(Err(ParsePatError(SaveOverflow)), parse("=1"));
+		assert_eq!(Err(ParsePatError(SaveOverflow)), parse(&format!("{}u8", "'".repeat(MAX_SLOTS - 2))));
+		assert_eq!(Err(ParsePatError(SaveOverflow)), parse(&"'?".repeat(MAX_SLOTS)));
+		assert_eq!(Err(ParsePatError(SyntaxError)), parse("'<name"));
+		assert_eq!(Err(ParsePatError(SyntaxError)), parse("'<>"));
+		assert_eq!(Err(ParsePatError(SyntaxError)), parse("'<a b>"));
+		assert_eq!(Err(ParsePatError(DuplicateName)), parse("'<a> u4<a>"));
+		assert_eq!(Err(ParsePatError(UnknownChar)), parse("~"));
 		assert_eq!(Err(ParsePatError(UnclosedQuote)), parse("\"unbalanced"));
 	}
 }
@@ -0,0 +1,383 @@
+/*!
+DBI Stream.
+*/
+
+use std::borrow::Cow;
+use std::mem;
+use std::ops::Range;
+
+use error::{Error, Result};
+use util::{CStr, Pod};
+
+use super::{read, NIL_STREAM_INDEX};
+
+//----------------------------------------------------------------
+
+/// Signature of the DBI stream header.
+pub const DBI_SIGNATURE: i32 = -1;
+/// Section contribution substream version 6.0.
+pub const SC_VERSION_60: u32 = 0xF12EBA2D;
+/// Section contribution substream version 2, entries have an additional `isect_coff` field.
+pub const SC_VERSION_2: u32 = 0xF13151E4;
+
+/// Optional debug header index of the FPO data stream.
+pub const DBG_FPO: usize = 0;
+/// Optional debug header index of the exception data stream.
+pub const DBG_EXCEPTION: usize = 1;
+/// Optional debug header index of the fixup data stream.
+pub const DBG_FIXUP: usize = 2;
+/// Optional debug header index of the OMAP to source stream.
+pub const DBG_OMAP_TO_SRC: usize = 3;
+/// Optional debug header index of the OMAP from source stream.
+pub const DBG_OMAP_FROM_SRC: usize = 4;
+/// Optional debug header index of the section headers stream.
+pub const DBG_SECTION_HDR: usize = 5;
+/// Optional debug header index of the token to RID map stream.
+pub const DBG_TOKEN_RID_MAP: usize = 6;
+/// Optional debug header index of the xdata stream.
+pub const DBG_XDATA: usize = 7;
+/// Optional debug header index of the pdata stream.
+pub const DBG_PDATA: usize = 8;
+/// Optional debug header index of the new FPO data stream.
+pub const DBG_NEW_FPO: usize = 9;
+/// Optional debug header index of the original section headers stream.
+pub const DBG_SECTION_HDR_ORIG: usize = 10;
+
+/// Header of the DBI stream.
+#[derive(Copy, Clone, Debug)]
+#[repr(C)]
+pub struct DbiStreamHeader {
+	/// Always `-1`.
+	pub version_signature: i32,
+	/// Version, `19990903` for VC70 and newer.
+	pub version_header: u32,
+	/// Number of times the PDB was written, matches the age of the CodeView RSDS record.
+	pub age: u32,
+	/// Stream index of the global symbols hash.
+	pub global_stream_index: u16,
+	pub build_number: u16,
+	/// Stream index of the public symbols hash.
+	pub public_stream_index: u16,
+	pub pdb_dll_version: u16,
+	/// Stream index of the symbol records.
+	pub sym_record_stream: u16,
+	pub pdb_dll_rbld: u16,
+	pub mod_info_size: i32,
+	pub section_contribution_size: i32,
+	pub section_map_size: i32,
+	pub source_info_size: i32,
+	pub type_server_map_size: i32,
+	pub mfc_type_server_index: u32,
+	pub optional_dbg_header_size: i32,
+	pub ec_substream_size: i32,
+	pub flags: u16,
+	/// Machine type, see `IMAGE_FILE_MACHINE_*`.
+	pub machine: u16,
+	pub padding: u32,
+}
+
+/// Contribution of a module to a section of the image.
+#[derive(Copy, Clone, Debug)]
+#[repr(C)]
+pub struct SectionContrib {
+	/// One based section index.
+	pub section: u16,
+	pub padding1: u16,
+	/// Offset in the section.
+	pub offset: i32,
+	/// Size of the contribution in bytes.
+	pub size: i32,
+	/// Section characteristics, see `IMAGE_SCN_*`.
+	pub characteristics: u32,
+	/// Index of the contributing module.
+	pub module_index: u16,
+	pub padding2: u16,
+	pub data_crc: u32,
+	pub reloc_crc: u32,
+}
+
+/// Module information header, followed by the module and object file names.
+#[derive(Copy, Clone, Debug)]
+#[repr(C)]
+pub struct ModuleInfo {
+	pub unused1: u32,
+	/// First section contribution of this module.
+	pub section_contr: SectionContrib,
+	pub flags: u16,
+	/// Stream index of the module's symbols and line info.
+	pub module_sym_stream: u16,
+	/// Size of the symbol records in the module stream, including the leading signature.
+	pub sym_byte_size: u32,
+	pub c11_byte_size: u32,
+	pub c13_byte_size: u32,
+	pub source_file_count: u16,
+	pub padding: u16,
+	pub unused2: u32,
+	pub source_file_name_index: u32,
+	pub pdb_file_path_name_index: u32,
+}
+
+unsafe impl Pod for DbiStreamHeader {}
+unsafe impl Pod for SectionContrib {}
+unsafe impl Pod for ModuleInfo {}
+
+//----------------------------------------------------------------
+
+/// DBI stream.
+pub struct Dbi<'a> {
+	stream: Cow<'a, [u8]>,
+	header: DbiStreamHeader,
+	mod_info: Range<usize>,
+	section_contribs: Range<usize>,
+	optional_dbg_header: Range<usize>,
+}
+
+impl<'a> Dbi<'a> {
+	pub(crate) fn new(stream: Cow<'a, [u8]>) -> Result<Dbi<'a>> {
+		let header: DbiStreamHeader = read(&stream, 0)?;
+		if header.version_signature != DBI_SIGNATURE {
+			return Err(Error::BadMagic);
+		}
+		// The substreams follow the header in this order
+		let sizes = [
+			header.mod_info_size,
+			header.section_contribution_size,
+			header.section_map_size,
+			header.source_info_size,
+			header.type_server_map_size,
+			header.ec_substream_size,
+			header.optional_dbg_header_size,
+		];
+		let mut ranges = [0..0, 0..0, 0..0, 0..0, 0..0, 0..0, 0..0];
+		let mut offset = mem::size_of::<DbiStreamHeader>();
+		for (range, &size) in ranges.iter_mut().zip(sizes.iter()) {
+			if size < 0 {
+				return Err(Error::Corrupt);
+			}
+			let end = usize::checked_add(offset, size as usize).ok_or(Error::Overflow)?;
+			if end > stream.len() {
+				return Err(Error::OOB);
+			}
+			*range = offset..end;
+			offset = end;
+		}
+		Ok(Dbi {
+			stream,
+			header,
+			mod_info: ranges[0].clone(),
+			section_contribs: ranges[1].clone(),
+			optional_dbg_header: ranges[6].clone(),
+		})
+	}
+	/// Returns the DBI stream header.
+	pub fn header(&self) -> &DbiStreamHeader {
+		&self.header
+	}
+	/// Returns the stream bytes.
+	pub fn stream(&self) -> &[u8] {
+		&self.stream
+	}
+	/// Returns an iterator over the modules.
+	pub fn modules(&self) -> Modules {
+		Modules { bytes: &self.stream[self.mod_info.clone()], offset: 0, index: 0 }
+	}
+	/// Returns an iterator over the section contributions.
+	pub fn section_contribs(&self) -> Result<SectionContribs> {
+		let bytes = &self.stream[self.section_contribs.clone()];
+		let stride = match read::<u32>(bytes, 0)? {
+			SC_VERSION_60 => mem::size_of::<SectionContrib>(),
+			SC_VERSION_2 => mem::size_of::<SectionContrib>() + 4,
+			_ => return Err(Error::BadMagic),
+		};
+		Ok(SectionContribs { bytes: &bytes[4..], stride })
+	}
+	/// Gets a stream index from the optional debug header.
+	///
+	/// Returns [`Err(Null)`](../enum.Error.html#variant.Null) if the stream is absent.
+	pub fn debug_stream(&self, index: usize) -> Result<u16> {
+		let offset = usize::checked_mul(index, 2).ok_or(Error::Overflow)?;
+		let stream_index = read::<u16>(&self.stream[self.optional_dbg_header.clone()], offset)?;
+		if stream_index == NIL_STREAM_INDEX {
+			return Err(Error::Null);
+		}
+		Ok(stream_index)
+	}
+}
+
+//----------------------------------------------------------------
+
+/// Module in the DBI stream.
+#[derive(Copy, Clone, Debug)]
+pub struct Module<'s> {
+	/// Index of the module.
+	pub index: usize,
+	/// Module information header.
+	pub info: ModuleInfo,
+	/// Name of the module, the object file path or the import library name for imports.
+	pub module_name: &'s CStr,
+	/// Name of the object file or library the module came from.
+	pub obj_file_name: &'s CStr,
+}
+
+impl<'s> Module<'s> {
+	/// Returns the stream index of the module's symbols.
+	///
+	/// Returns `None` if the module has no symbols.
+	pub fn symbols_stream(&self) -> Option<usize> {
+		if self.info.module_sym_stream == NIL_STREAM_INDEX { None }
+		else { Some(self.info.module_sym_stream as usize) }
+	}
+}
+
+/// Iterator over the modules.
+#[derive(Clone)]
+pub struct Modules<'s> {
+	bytes: &'s [u8],
+	offset: usize,
+	index: usize,
+}
+impl<'s> Modules<'s> {
+	fn parse(&mut self) -> Result<Module<'s>> {
+		let info: ModuleInfo = read(self.bytes, self.offset)?;
+		let names = &self.bytes[self.offset + mem::size_of::<ModuleInfo>()..];
+		let module_name = CStr::from_bytes(names)?;
+		let obj_file_name = CStr::from_bytes(&names[module_name.c_str().len()..])?;
+		let size = mem::size_of::<ModuleInfo>() + module_name.c_str().len() + obj_file_name.c_str().len();
+		// Module infos are aligned to 4 bytes
+		self.offset += (size + 3) & !3;
+		let index = self.index;
+		self.index += 1;
+		Ok(Module { index, info, module_name, obj_file_name })
+	}
+}
+impl<'s> Iterator for Modules<'s> {
+	type Item = Module<'s>;
+	fn next(&mut self) -> Option<Module<'s>> {
+		if self.offset >= self.bytes.len() {
+			return None;
+		}
+		match self.parse() {
+			Ok(module) => Some(module),
+			Err(_) => {
+				// Stop iterating on corrupt module infos
+				self.offset = self.bytes.len();
+				None
+			},
+		}
+	}
+}
+
+//----------------------------------------------------------------
+
+/// Iterator over the section contributions.
+#[derive(Clone)]
+pub struct SectionContribs<'s> {
+	bytes: &'s [u8],
+	stride: usize,
+}
+impl<'s> Iterator for SectionContribs<'s> {
+	type Item = SectionContrib;
+	fn next(&mut self) -> Option<SectionContrib> {
+		if self.bytes.len() < self.stride {
+			return None;
+		}
+		let contrib = read(self.bytes, 0).ok();
+		self.bytes = &self.bytes[self.stride..];
+		contrib
+	}
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		let len = self.bytes.len() / self.stride;
+		(len, Some(len))
+	}
+}
+impl<'s> ExactSizeIterator for SectionContribs<'s> {}
+
+//----------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+	use std::borrow::Cow;
+	use super::*;
+	use super::super::tests::{as_bytes, dbi};
+
+	#[test]
+	fn substreams() {
+		let stream = dbi(6, &[("a.obj", 7, 0x40), ("Import:KERNEL32.dll", NIL_STREAM_INDEX, 0)], &[NIL_STREAM_INDEX, 9, 8, 8, 8, 5]);
+		let dbi = Dbi::new(Cow::Borrowed(&stream)).unwrap();
+		assert_eq!(dbi.header().age, 1);
+		assert_eq!(dbi.header().sym_record_stream, 6);
+
+		let modules: Vec<_> = dbi.modules().collect();
+		assert_eq!(modules.len(), 2);
+		assert_eq!((modules[0].index, modules[0].module_name.to_str(), modules[0].obj_file_name.to_str()), (0, Ok("a.obj"), Ok("a.obj")));
+		assert_eq!((modules[0].symbols_stream(), modules[0].info.sym_byte_size), (Some(7), 0x40));
+		assert_eq!((modules[1].index, modules[1].module_name.to_str()), (1, Ok("Import:KERNEL32.dll")));
+		assert_eq!(modules[1].symbols_stream(), None);
+
+		assert_eq!(dbi.debug_stream(DBG_SECTION_HDR), Ok(5));
+		assert_eq!(dbi.debug_stream(DBG_EXCEPTION), Ok(9));
+		assert_eq!(dbi.debug_stream(DBG_FPO), Err(Error::Null));
+		assert_eq!(dbi.debug_stream(DBG_SECTION_HDR_ORIG), Err(Error::OOB));
+	}
+
+	#[test]
+	fn section_contribs() {
+		let stream = dbi(6, &[("a.obj", 7, 0x40), ("b.obj", 8, 0x40)], &[]);
+		let dbi = Dbi::new(Cow::Borrowed(&stream)).unwrap();
+		let contribs = dbi.section_contribs().unwrap();
+		assert_eq!(contribs.len(), 2);
+		let contribs: Vec<_> = contribs.map(|sc| (sc.section, sc.offset, sc.size, sc.module_index)).collect();
+		assert_eq!(contribs, [(1, 0, 0x10, 0), (1, 0x10, 0x10, 1)]);
+
+		// Version 2 entries are followed by the COFF section index
+		let mut header: DbiStreamHeader = read(&stream, 0).unwrap();
+		let mut contrib: SectionContrib = read(&stream, mem::size_of::<DbiStreamHeader>() + header.mod_info_size as usize + 4).unwrap();
+		contrib.section = 3;
+		let mut substream = as_bytes(&SC_VERSION_2).to_vec();
+		for _ in 0..2 {
+			substream.extend_from_slice(as_bytes(&contrib));
+			substream.extend_from_slice(&[1, 0, 0, 0]);
+		}
+		header.mod_info_size = 0;
+		header.section_contribution_size = substream.len() as i32;
+		let mut stream = as_bytes(&header).to_vec();
+		stream.extend_from_slice(&substream);
+		let dbi = Dbi::new(Cow::Borrowed(&stream)).unwrap();
+		let sections: Vec<_> = dbi.section_contribs().unwrap().map(|sc| sc.section).collect();
+		assert_eq!(sections, [3, 3]);
+
+		// Unknown versions
+		stream[mem::size_of::<DbiStreamHeader>()] = 0;
+		let dbi = Dbi::new(Cow::Borrowed(&stream)).unwrap();
+		assert_eq!(dbi.section_contribs().err(), Some(Error::BadMagic));
+	}
+
+	#[test]
+	fn errors() {
+		let stream = dbi(6, &[("a.obj", 7, 0x40), ("b.obj", 8, 0x40)], &[]);
+		let mut header: DbiStreamHeader = read(&stream, 0).unwrap();
+		let body = &stream[mem::size_of::<DbiStreamHeader>()..];
+		let with_header = |header: &DbiStreamHeader| {
+			let mut stream = as_bytes(header).to_vec();
+			stream.extend_from_slice(body);
+			stream
+		};
+
+		// The unterminated names of the second module end the modules
+		let mut corrupt = stream.clone();
+		let names = mem::size_of::<DbiStreamHeader>() + mem::size_of::<ModuleInfo>() + header.mod_info_size as usize / 2;
+		for byte in &mut corrupt[names..names + 12] {
+			*byte = b'x';
+		}
+		assert_eq!(Dbi::new(Cow::Borrowed(&corrupt)).unwrap().modules().count(), 1);
+
+		header.section_map_size = 1000;
+		assert_eq!(Dbi::new(Cow::Owned(with_header(&header))).err(), Some(Error::OOB));
+		header.section_map_size = -1;
+		assert_eq!(Dbi::new(Cow::Owned(with_header(&header))).err(), Some(Error::Corrupt));
+		header.section_map_size = 0;
+		header.version_signature = 0;
+		assert_eq!(Dbi::new(Cow::Owned(with_header(&header))).err(), Some(Error::BadMagic));
+		assert_eq!(Dbi::new(Cow::Borrowed(&stream[..60])).err(), Some(Error::OOB));
+	}
+}
@@ -0,0 +1,457 @@
+/*!
+Program Database.
+
+The debug directory of an image identifies its PDB with a [CodeView RSDS record](../pe64/debug/enum.Info.html).
+This module reads the PDB itself: the MSF 7.0 container, the PDB info stream, the DBI stream and the symbol records.
+
+References:
+
+[1]: [The PDB File Format](https://llvm.org/docs/PDB/index.html)
+[2]: [microsoft-pdb](https://github.com/Microsoft/microsoft-pdb)
+
+# Multi-Stream Format
+
+A PDB is a small filesystem of streams stored in fixed size blocks.
+Streams whose blocks happen to be consecutive are borrowed from the underlying bytes,
+the others are reassembled into an owned buffer. This is the only place where this module allocates.
+
+# Examples
+
+```
+# #![allow(unused_variables)]
+use pelite::pe64::{Pe, PeFile, debug};
+use pelite::pdb::Pdb;
+
+# #[allow(dead_code)]
+fn example(file: PeFile, pdb_bytes: &[u8], rva: u32) -> pelite::Result<()> {
+	let pdb = Pdb::from_bytes(pdb_bytes)?;
+
+	// Check the PDB belongs to the image
+	for dir in file.debug()? {
+		if let debug::Info::CvRSDS { image, .. } = dir.info()? {
+			if !pdb.matches(&image.Signature, image.Age)? {
+				return Err(pelite::Error::BadMagic);
+			}
+		}
+	}
+
+	// Find the symbol containing the rva
+	let symbols = pdb.symbols()?;
+	if let Some(sym) = symbols.symbolize(rva) {
+		println!("{}+{:#x}", sym.name, sym.offset);
+	}
+
+	Ok(())
+}
+```
+*/
+
+use std::borrow::Cow;
+use std::{fmt, mem, ptr};
+
+use error::{Error, Result};
+use image::{GUID, IMAGE_SECTION_HEADER};
+use util::Pod;
+
+mod dbi;
+mod symbols;
+
+pub use self::dbi::*;
+pub use self::symbols::*;
+
+//----------------------------------------------------------------
+
+/// Magic number at the start of an MSF 7.0 file.
+pub const MSF_MAGIC: [u8; 32] = *b"Microsoft C/C++ MSF 7.00\r\n\x1ADS\0\0\0";
+
+/// Stream index of the PDB info stream.
+pub const PDB_STREAM: usize = 1;
+/// Stream index of the TPI stream.
+pub const TPI_STREAM: usize = 2;
+/// Stream index of the DBI stream.
+pub const DBI_STREAM: usize = 3;
+/// Stream index of the IPI stream.
+pub const IPI_STREAM: usize = 4;
+
+/// Stream size marking a nil stream.
+pub const NIL_STREAM_SIZE: u32 = 0xFFFFFFFF;
+
+/// Stream index marking the absence of a stream.
+pub const NIL_STREAM_INDEX: u16 = 0xFFFF;
+
+/// The MSF superblock.
+#[derive(Copy, Clone, Debug)]
+#[repr(C)]
+pub struct SuperBlock {
+	/// Must equal [`MSF_MAGIC`](constant.MSF_MAGIC.html).
+	pub magic: [u8; 32],
+	/// Size of the blocks in bytes, a power of two between 512 and 4096.
+	pub block_size: u32,
+	/// Index of the active free block map.
+	pub free_block_map_block: u32,
+	/// Total number of blocks in the file.
+	pub num_blocks: u32,
+	/// Size of the stream directory in bytes.
+	pub num_directory_bytes: u32,
+	pub unknown: u32,
+	/// Index of the block listing the blocks of the stream directory.
+	pub block_map_addr: u32,
+}
+
+/// Header of the PDB info stream.
+#[derive(Clone, Debug)]
+#[repr(C)]
+pub struct PdbStreamHeader {
+	/// Version, `20000404` for VC70 and newer.
+	pub version: u32,
+	/// Timestamp when the PDB was created.
+	pub signature: u32,
+	/// Number of times the PDB was written.
+	pub age: u32,
+	/// Unique identifier matched against the CodeView RSDS record.
+	pub guid: GUID,
+}
+
+unsafe impl Pod for SuperBlock {}
+unsafe impl Pod for PdbStreamHeader {}
+
+//----------------------------------------------------------------
+
+/// Reads an unaligned pod `T` from the bytes at the given offset.
+pub(crate) fn read<T: Pod>(bytes: &[u8], offset: usize) -> Result<T> {
+	let end = usize::checked_add(offset, mem::size_of::<T>()).ok_or(Error::Overflow)?;
+	if end > bytes.len() {
+		return Err(Error::OOB);
+	}
+	// Safe because the bounds are checked above and T is Pod
+	Ok(unsafe { ptr::read_unaligned(bytes.as_ptr().offset(offset as isize) as *const T) })
+}
+
+/// Multi-stream format directory entry.
+#[derive(Copy, Clone, Debug)]
+struct StreamEntry {
+	size: u32,
+	// Offset of the stream's block indices in the directory
+	blocks: usize,
+}
+
+//----------------------------------------------------------------
+
+/// Program Database.
+pub struct Pdb<'a> {
+	bytes: &'a [u8],
+	super_block: SuperBlock,
+	directory: Cow<'a, [u8]>,
+	streams: Vec<StreamEntry>,
+}
+
+impl<'a> Pdb<'a> {
+	/// Interprets the bytes as an MSF 7.0 program database.
+	///
+	/// Reads the superblock and the stream directory, the streams themselves are read on demand.
+	pub fn from_bytes(bytes: &'a [u8]) -> Result<Pdb<'a>> {
+		let super_block: SuperBlock = read(bytes, 0)?;
+		if super_block.magic != MSF_MAGIC {
+			return Err(Error::BadMagic);
+		}
+		match super_block.block_size {
+			512 | 1024 | 2048 | 4096 => (),
+			_ => return Err(Error::Insanity),
+		};
+		if (super_block.num_blocks as u64) * (super_block.block_size as u64) > bytes.len() as u64 {
+			return Err(Error::OOB);
+		}
+		let mut pdb = Pdb {
+			bytes,
+			super_block,
+			directory: Cow::Borrowed(&[]),
+			streams: Vec::new(),
+		};
+		// The block map is a single block listing the blocks of the directory
+		let block_size = super_block.block_size as usize;
+		let num_dir_blocks = (super_block.num_directory_bytes as usize + block_size - 1) / block_size;
+		if num_dir_blocks > block_size / 4 {
+			return Err(Error::Insanity);
+		}
+		let block_map = pdb.block(super_block.block_map_addr)?;
+		pdb.directory = pdb.assemble(&block_map[..num_dir_blocks * 4], super_block.num_directory_bytes)?;
+		// Parse the stream directory
+		let num_streams = read::<u32>(&pdb.directory, 0)? as usize;
+		let mut blocks = usize::checked_mul(num_streams, 4).and_then(|size| size.checked_add(4)).ok_or(Error::Overflow)?;
+		if blocks > pdb.directory.len() {
+			return Err(Error::OOB);
+		}
+		let mut streams = Vec::with_capacity(num_streams);
+		for index in 0..num_streams {
+			let size = read::<u32>(&pdb.directory, 4 + index * 4)?;
+			streams.push(StreamEntry { size, blocks });
+			if size != NIL_STREAM_SIZE {
+				blocks += (size as usize + block_size - 1) / block_size * 4;
+			}
+		}
+		if blocks > pdb.directory.len() {
+			return Err(Error::OOB);
+		}
+		pdb.streams = streams;
+		Ok(pdb)
+	}
+	/// Returns the underlying bytes.
+	pub fn bytes(&self) -> &'a [u8] {
+		self.bytes
+	}
+	/// Returns the superblock.
+	pub fn super_block(&self) -> &SuperBlock {
+		&self.super_block
+	}
+	/// Returns the number of streams.
+	pub fn num_streams(&self) -> usize {
+		self.streams.len()
+	}
+	/// Returns the size of the stream in bytes.
+	///
+	/// Returns `None` if the stream does not exist or is nil.
+	pub fn stream_size(&self, index: usize) -> Option<u32> {
+		self.streams.get(index).map(|entry| entry.size).and_then(|size| if size == NIL_STREAM_SIZE { None } else { Some(size) })
+	}
+	/// Reads a stream by its index.
+	///
+	/// Returns [`Err(Null)`](../enum.Error.html#variant.Null) if the stream is nil.
+	pub fn stream(&self, index: usize) -> Result<Cow<'a, [u8]>> {
+		let entry = *self.streams.get(index).ok_or(Error::OOB)?;
+		if entry.size == NIL_STREAM_SIZE {
+			return Err(Error::Null);
+		}
+		let block_size = self.super_block.block_size as usize;
+		let num_blocks = (entry.size as usize + block_size - 1) / block_size;
+		// Validated by the constructor
+		let indices = &self.directory[entry.blocks..entry.blocks + num_blocks * 4];
+		self.assemble(indices, entry.size)
+	}
+	/// Reads the PDB info stream header.
+	pub fn info(&self) -> Result<PdbStreamHeader> {
+		let stream = self.stream(PDB_STREAM)?;
+		read(&stream, 0)
+	}
+	/// Reads the DBI stream.
+	pub fn dbi(&self) -> Result<Dbi<'a>> {
+		Dbi::new(self.stream(DBI_STREAM)?)
+	}
+	/// Returns if this PDB matches the GUID and age of a CodeView RSDS record.
+	///
+	/// The GUID is compared against the PDB info stream and the age against the DBI stream.
+	/// The age of the PDB info stream is used instead if the DBI stream is absent.
+	pub fn matches(&self, guid: &GUID, age: u32) -> Result<bool> {
+		let info = self.info()?;
+		let pdb_age = match self.dbi() {
+			Ok(dbi) => dbi.header().age,
+			Err(Error::Null) => info.age,
+			Err(err) => return Err(err),
+		};
+		Ok(info.guid == *guid && pdb_age == age)
+	}
+	/// Reads the original section headers of the image.
+	///
+	/// These are used to translate the `segment:offset` addresses found in the symbols to rvas.
+	pub fn section_headers(&self, dbi: &Dbi) -> Result<Vec<IMAGE_SECTION_HEADER>> {
+		let index = dbi.debug_stream(DBG_SECTION_HDR)?;
+		let stream = self.stream(index as usize)?;
+		let size_of = mem::size_of::<IMAGE_SECTION_HEADER>();
+		if stream.len() % size_of != 0 {
+			return Err(Error::Corrupt);
+		}
+		(0..stream.len() / size_of).map(|i| read(&stream, i * size_of)).collect()
+	}
+	/// Builds the symbol table for symbolizing rvas.
+	///
+	/// Collects the procedures of every module and the public symbols, modules with a corrupt symbol stream are skipped.
+	pub fn symbols(&self) -> Result<SymbolTable<'a>> {
+		SymbolTable::new(self)
+	}
+
+	// Gets the bytes of a block by its index.
+	fn block(&self, index: u32) -> Result<&'a [u8]> {
+		if index >= self.super_block.num_blocks {
+			return Err(Error::OOB);
+		}
+		let block_size = self.super_block.block_size as usize;
+		let start = index as usize * block_size;
+		self.bytes.get(start..start + block_size).ok_or(Error::OOB)
+	}
+	// Reassembles the data of a list of block indices.
+	// Consecutive blocks are borrowed without copying.
+	fn assemble(&self, indices: &[u8], size: u32) -> Result<Cow<'a, [u8]>> {
+		let size = size as usize;
+		let num_blocks = indices.len() / 4;
+		if num_blocks == 0 {
+			return Ok(Cow::Borrowed(&[]));
+		}
+		let first = read::<u32>(indices, 0)?;
+		let consecutive = (1..num_blocks).all(|i| read::<u32>(indices, i * 4).ok() == first.checked_add(i as u32));
+		if consecutive {
+			let last = first.checked_add(num_blocks as u32 - 1).ok_or(Error::Overflow)?;
+			if last >= self.super_block.num_blocks {
+				return Err(Error::OOB);
+			}
+			let start = first as usize * self.super_block.block_size as usize;
+			self.bytes.get(start..start + size).map(Cow::Borrowed).ok_or(Error::OOB)
+		}
+		else {
+			let mut data = Vec::with_capacity(num_blocks * self.super_block.block_size as usize);
+			for i in 0..num_blocks {
+				data.extend_from_slice(self.block(read(indices, i * 4)?)?);
+			}
+			data.truncate(size);
+			Ok(Cow::Owned(data))
+		}
+	}
+}
+
+//----------------------------------------------------------------
+// Formatting
+
+impl<'a> fmt::Debug for Pdb<'a> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "Pdb {{ block_size: {}, num_blocks: {}, num_streams: {} }}",
+			self.super_block.block_size, self.super_block.num_blocks, self.streams.len())
+	}
+}
+
+//----------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+	use std::slice;
+	use super::*;
+
+	fn put(buf: &mut [u8], offset: usize, value: u32) {
+		buf[offset..offset + 4].copy_from_slice(&[value as u8, (value >> 8) as u8, (value >> 16) as u8, (value >> 24) as u8]);
+	}
+
+	/// Gets the bytes of a pod value.
+	pub fn as_bytes<T: Pod>(value: &T) -> &[u8] {
+		unsafe { slice::from_raw_parts(value as *const T as *const u8, mem::size_of::<T>()) }
+	}
+
+	/// Builds an MSF file storing the streams in consecutive blocks after the directory.
+	pub fn msf(streams: &[&[u8]]) -> Vec<u8> {
+		const BLOCK_SIZE: usize = 512;
+		let num_blocks = |size: usize| (size + BLOCK_SIZE - 1) / BLOCK_SIZE;
+		let mut directory = vec![streams.len() as u32];
+		directory.extend(streams.iter().map(|stream| stream.len() as u32));
+		let dir_size = (directory.len() + streams.iter().map(|stream| num_blocks(stream.len())).sum::<usize>()) * 4;
+		let dir_blocks = num_blocks(dir_size);
+		let mut next = 2 + dir_blocks;
+		for stream in streams {
+			for _ in 0..num_blocks(stream.len()) {
+				directory.push(next as u32);
+				next += 1;
+			}
+		}
+
+		let mut bytes = vec![0u8; next * BLOCK_SIZE];
+		bytes[..32].copy_from_slice(&MSF_MAGIC);
+		put(&mut bytes, 32, BLOCK_SIZE as u32);
+		put(&mut bytes, 40, next as u32);
+		put(&mut bytes, 44, dir_size as u32);
+		put(&mut bytes, 52, 1);
+		for i in 0..dir_blocks {
+			put(&mut bytes, BLOCK_SIZE + i * 4, (2 + i) as u32);
+		}
+		for (i, &word) in directory.iter().enumerate() {
+			put(&mut bytes, 2 * BLOCK_SIZE + i * 4, word);
+		}
+		let mut offset = (2 + dir_blocks) * BLOCK_SIZE;
+		for stream in streams {
+			bytes[offset..offset + stream.len()].copy_from_slice(stream);
+			offset += num_blocks(stream.len()) * BLOCK_SIZE;
+		}
+		bytes
+	}
+
+	/// Builds a DBI stream.
+	///
+	/// The modules are given by their name, symbol stream and symbol byte size.
+	/// Every module contributes 0x10 bytes to the first section in a version 6.0 section contribution substream.
+	pub fn dbi(sym_record_stream: u16, modules: &[(&str, u16, u32)], dbg_streams: &[u16]) -> Vec<u8> {
+		let mut mod_info = Vec::new();
+		let mut contribs = as_bytes(&SC_VERSION_60).to_vec();
+		for (index, &(name, module_sym_stream, sym_byte_size)) in modules.iter().enumerate() {
+			let mut info: ModuleInfo = unsafe { mem::zeroed() };
+			info.section_contr.section = 1;
+			info.section_contr.offset = index as i32 * 0x10;
+			info.section_contr.size = 0x10;
+			info.section_contr.module_index = index as u16;
+			info.module_sym_stream = module_sym_stream;
+			info.sym_byte_size = sym_byte_size;
+			mod_info.extend_from_slice(as_bytes(&info));
+			// The module name followed by the object file name
+			for _ in 0..2 {
+				mod_info.extend_from_slice(name.as_bytes());
+				mod_info.push(0);
+			}
+			while mod_info.len() % 4 != 0 {
+				mod_info.push(0);
+			}
+			contribs.extend_from_slice(as_bytes(&info.section_contr));
+		}
+		let dbg_header: Vec<u8> = dbg_streams.iter().flat_map(|index| as_bytes(index).to_vec()).collect();
+
+		let mut header: DbiStreamHeader = unsafe { mem::zeroed() };
+		header.version_signature = DBI_SIGNATURE;
+		header.version_header = 19990903;
+		header.age = 1;
+		header.sym_record_stream = sym_record_stream;
+		header.mod_info_size = mod_info.len() as i32;
+		header.section_contribution_size = contribs.len() as i32;
+		header.optional_dbg_header_size = dbg_header.len() as i32;
+		let mut stream = as_bytes(&header).to_vec();
+		stream.extend_from_slice(&mod_info);
+		stream.extend_from_slice(&contribs);
+		stream.extend_from_slice(&dbg_header);
+		stream
+	}
+
+	#[test]
+	fn streams() {
+		// Superblock, block map, directory, stream 0 split over blocks 5 and 3, stream 1 in block 4
+		let mut bytes = vec![0u8; 512 * 6];
+		bytes[..32].copy_from_slice(&MSF_MAGIC);
+		put(&mut bytes, 32, 512);
+		put(&mut bytes, 40, 6);
+		put(&mut bytes, 44, 28);
+		put(&mut bytes, 52, 1);
+		put(&mut bytes, 512, 2);
+		let dir = 1024;
+		for (i, &value) in [3, 600, 4, NIL_STREAM_SIZE, 5, 3, 4].iter().enumerate() {
+			put(&mut bytes, dir + i * 4, value);
+		}
+		bytes[5 * 512] = 0xAA;
+		bytes[3 * 512 + 87] = 0xBB;
+		bytes[4 * 512] = 0xCC;
+
+		let pdb = Pdb::from_bytes(&bytes).unwrap();
+		assert_eq!(pdb.num_streams(), 3);
+		assert_eq!(pdb.stream_size(0), Some(600));
+		assert_eq!(pdb.stream_size(2), None);
+
+		let stream = pdb.stream(0).unwrap();
+		assert_eq!(stream.len(), 600);
+		assert_eq!((stream[0], stream[599]), (0xAA, 0xBB));
+		assert_eq!(pdb.stream(1).unwrap()[0], 0xCC);
+		assert_eq!(pdb.stream(2), Err(Error::Null));
+		assert_eq!(pdb.stream(3), Err(Error::OOB));
+	}
+
+	#[test]
+	fn fixtures() {
+		// Enough streams to spill the directory over multiple blocks
+		let data = [0x5A; 1000];
+		let streams: Vec<&[u8]> = (0..200).map(|i| &data[..i * 5]).collect();
+		let bytes = msf(&streams);
+		let pdb = Pdb::from_bytes(&bytes).unwrap();
+		assert_eq!(pdb.num_streams(), 200);
+		for (i, stream) in streams.iter().enumerate() {
+			assert_eq!(pdb.stream(i).unwrap(), *stream);
+		}
+	}
+}
@@ -0,0 +1,434 @@
+/*!
+Symbol records.
+*/
+
+use std::borrow::Cow;
+use std::{fmt, slice};
+
+use error::{Error, Result};
+use util::CStr;
+
+use super::{read, Pdb, Module};
+
+//----------------------------------------------------------------
+
+/// Public symbol.
+pub const S_PUB32: u16 = 0x110E;
+/// Local data symbol.
+pub const S_LDATA32: u16 = 0x110C;
+/// Global data symbol.
+pub const S_GDATA32: u16 = 0x110D;
+/// Local procedure symbol.
+pub const S_LPROC32: u16 = 0x110F;
+/// Global procedure symbol.
+pub const S_GPROC32: u16 = 0x1110;
+/// Local procedure symbol with an IPI function id.
+pub const S_LPROC32_ID: u16 = 0x1146;
+/// Global procedure symbol with an IPI function id.
+pub const S_GPROC32_ID: u16 = 0x1147;
+
+/// Signature of the module symbol streams with C13 line info.
+pub const CV_SIGNATURE_C13: u32 = 4;
+
+//----------------------------------------------------------------
+
+/// Symbol record.
+#[derive(Copy, Clone, Debug)]
+pub struct Record<'s> {
+	/// Record kind, see the `S_*` constants.
+	pub kind: u16,
+	/// Record data following the kind.
+	pub data: &'s [u8],
+}
+
+/// Interpreted symbol record.
+#[derive(Copy, Clone, Debug)]
+pub enum Symbol<'s> {
+	Unknown,
+	/// `S_PUB32` record.
+	Public { flags: u32, segment: u16, offset: u32, name: &'s CStr },
+	/// `S_GDATA32` and `S_LDATA32` records.
+	Data { global: bool, type_index: u32, segment: u16, offset: u32, name: &'s CStr },
+	/// `S_GPROC32` and `S_LPROC32` records including their `_ID` variants.
+	Procedure { global: bool, segment: u16, offset: u32, code_size: u32, type_index: u32, name: &'s CStr },
+}
+
+impl<'s> Record<'s> {
+	/// Interprets the symbol record.
+	pub fn symbol(&self) -> Result<Symbol<'s>> {
+		let data = self.data;
+		match self.kind {
+			S_PUB32 => {
+				let flags = read(data, 0)?;
+				let offset = read(data, 4)?;
+				let segment = read(data, 8)?;
+				let name = CStr::from_bytes(data.get(10..).ok_or(Error::OOB)?)?;
+				Ok(Symbol::Public { flags, segment, offset, name })
+			},
+			S_GDATA32 | S_LDATA32 => {
+				let type_index = read(data, 0)?;
+				let offset = read(data, 4)?;
+				let segment = read(data, 8)?;
+				let name = CStr::from_bytes(data.get(10..).ok_or(Error::OOB)?)?;
+				Ok(Symbol::Data { global: self.kind == S_GDATA32, type_index, segment, offset, name })
+			},
+			S_GPROC32 | S_LPROC32 | S_GPROC32_ID | S_LPROC32_ID => {
+				let code_size = read(data, 12)?;
+				let type_index = read(data, 24)?;
+				let offset = read(data, 28)?;
+				let segment = read(data, 32)?;
+				let name = CStr::from_bytes(data.get(35..).ok_or(Error::OOB)?)?;
+				let global = self.kind == S_GPROC32 || self.kind == S_GPROC32_ID;
+				Ok(Symbol::Procedure { global, segment, offset, code_size, type_index, name })
+			},
+			_ => Ok(Symbol::Unknown),
+		}
+	}
+}
+
+//----------------------------------------------------------------
+
+/// Iterator over symbol records.
+///
+/// Stops at the first malformed record.
+#[derive(Clone)]
+pub struct SymbolRecords<'s> {
+	bytes: &'s [u8],
+}
+impl<'s> SymbolRecords<'s> {
+	/// Iterates over the records in the symbol record stream.
+	pub fn new(bytes: &'s [u8]) -> SymbolRecords<'s> {
+		SymbolRecords { bytes }
+	}
+	/// Iterates over the records in a module symbol stream.
+	///
+	/// Skips the leading signature and stops before the line info.
+	pub fn module(bytes: &'s [u8], module: &Module) -> Result<SymbolRecords<'s>> {
+		if read::<u32>(bytes, 0)? != CV_SIGNATURE_C13 {
+			return Err(Error::BadMagic);
+		}
+		let bytes = bytes.get(4..module.info.sym_byte_size as usize).ok_or(Error::OOB)?;
+		Ok(SymbolRecords { bytes })
+	}
+}
+impl<'s> Iterator for SymbolRecords<'s> {
+	type Item = Record<'s>;
+	fn next(&mut self) -> Option<Record<'s>> {
+		let len = match read::<u16>(self.bytes, 0) {
+			Ok(len) if len >= 2 && len as usize + 2 <= self.bytes.len() => len as usize,
+			_ => {
+				self.bytes = &[];
+				return None;
+			},
+		};
+		let kind = read::<u16>(self.bytes, 2).unwrap_or(0);
+		let data = &self.bytes[4..len + 2];
+		self.bytes = &self.bytes[len + 2..];
+		Some(Record { kind, data })
+	}
+}
+
+//----------------------------------------------------------------
+
+#[derive(Copy, Clone, Debug)]
+struct Entry {
+	rva: u32,
+	size: u32,
+	// Index of the stream and offset of the name in it
+	stream: usize,
+	name: usize,
+}
+
+/// Symbol found by [`SymbolTable::symbolize`](struct.SymbolTable.html#method.symbolize).
+#[derive(Copy, Clone, Debug)]
+pub struct Symbolized<'s> {
+	/// Name of the symbol.
+	pub name: &'s CStr,
+	/// Rva of the start of the symbol.
+	pub rva: u32,
+	/// Size of the symbol, zero if unknown.
+	pub size: u32,
+	/// Offset of the symbolized rva from the start of the symbol.
+	pub offset: u32,
+}
+
+/// Symbol table for symbolizing rvas.
+///
+/// Contains the procedures of every module and the public symbols sorted by their rva.
+pub struct SymbolTable<'a> {
+	streams: Vec<Cow<'a, [u8]>>,
+	entries: Vec<Entry>,
+}
+
+impl<'a> SymbolTable<'a> {
+	pub(crate) fn new(pdb: &Pdb<'a>) -> Result<SymbolTable<'a>> {
+		let dbi = pdb.dbi()?;
+		let sections = pdb.section_headers(&dbi)?;
+		let mut table = SymbolTable { streams: Vec::new(), entries: Vec::new() };
+
+		// Translates segment:offset addresses to rvas
+		let to_rva = |segment: u16, offset: u32| {
+			if segment == 0 { None }
+			else { sections.get(segment as usize - 1).map(|section| section.VirtualAddress.wrapping_add(offset)) }
+		};
+
+		// Procedures from the module symbol streams
+		for module in dbi.modules() {
+			let index = match module.symbols_stream() {
+				Some(index) => index,
+				None => continue,
+			};
+			// Skip the modules whose symbol stream is corrupt like the modules iterator does
+			let stream = match pdb.stream(index) {
+				Ok(stream) => stream,
+				Err(_) => continue,
+			};
+			let records = match SymbolRecords::module(&stream, &module) {
+				Ok(records) => records,
+				Err(_) => continue,
+			};
+			for record in records {
+				if let Ok(Symbol::Procedure { segment, offset, code_size, name, .. }) = record.symbol() {
+					if let Some(rva) = to_rva(segment, offset) {
+						let name = name.c_str().as_ptr() as usize - stream.as_ptr() as usize;
+						table.entries.push(Entry { rva, size: code_size, stream: table.streams.len(), name });
+					}
+				}
+			}
+			table.streams.push(stream);
+		}
+
+		// Public symbols from the symbol record stream
+		let stream = pdb.stream(dbi.header().sym_record_stream as usize)?;
+		for record in SymbolRecords::new(&stream) {
+			if let Ok(Symbol::Public { segment, offset, name, .. }) = record.symbol() {
+				if let Some(rva) = to_rva(segment, offset) {
+					let name = name.c_str().as_ptr() as usize - stream.as_ptr() as usize;
+					table.entries.push(Entry { rva, size: 0, stream: table.streams.len(), name });
+				}
+			}
+		}
+		table.streams.push(stream);
+
+		// Sort by rva, prefer the procedures with a known size over the publics at the same address
+		table.entries.sort_by(|lhs, rhs| lhs.rva.cmp(&rhs.rva).then(rhs.size.cmp(&lhs.size)));
+		table.entries.dedup_by_key(|entry| entry.rva);
+		Ok(table)
+	}
+	/// Returns the number of symbols.
+	pub fn len(&self) -> usize {
+		self.entries.len()
+	}
+	/// Returns if there are no symbols.
+	pub fn is_empty(&self) -> bool {
+		self.entries.is_empty()
+	}
+	/// Returns an iterator over the symbols sorted by rva.
+	pub fn iter(&self) -> Iter {
+		Iter { table: self, iter: self.entries.iter() }
+	}
+	/// Finds the symbol containing the rva.
+	///
+	/// Symbols without a known size are assumed to extend to the next symbol.
+	pub fn symbolize(&self, rva: u32) -> Option<Symbolized> {
+		let index = match self.entries.binary_search_by(|entry| entry.rva.cmp(&rva)) {
+			Ok(index) => index,
+			Err(0) => return None,
+			Err(index) => index - 1,
+		};
+		let entry = &self.entries[index];
+		if entry.size != 0 && rva - entry.rva >= entry.size {
+			return None;
+		}
+		Some(self.get(entry, rva))
+	}
+	fn get(&self, entry: &Entry, rva: u32) -> Symbolized {
+		// The name offsets were taken from valid C strings
+		let name = CStr::from_bytes(&self.streams[entry.stream][entry.name..]).unwrap();
+		Symbolized { name, rva: entry.rva, size: entry.size, offset: rva - entry.rva }
+	}
+}
+
+/// Iterator over the symbols of a symbol table.
+#[derive(Clone)]
+pub struct Iter<'s> {
+	table: &'s SymbolTable<'s>,
+	iter: slice::Iter<'s, Entry>,
+}
+impl<'s> Iterator for Iter<'s> {
+	type Item = Symbolized<'s>;
+	fn next(&mut self) -> Option<Symbolized<'s>> {
+		let table = self.table;
+		self.iter.next().map(|entry| table.get(entry, entry.rva))
+	}
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		self.iter.size_hint()
+	}
+}
+impl<'s> ExactSizeIterator for Iter<'s> {}
+
+//----------------------------------------------------------------
+// Formatting
+
+impl<'s> fmt::Display for Symbolized<'s> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self.offset {
+			0 => write!(f, "{}", self.name),
+			offset => write!(f, "{}+{:#x}", self.name, offset),
+		}
+	}
+}
+
+impl<'a> fmt::Debug for SymbolTable<'a> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		for sym in self.iter() {
+			write!(f, "{:08X} {:>6X} {}\n", sym.rva, sym.size, sym.name)?;
+		}
+		Ok(())
+	}
+}
+
+//----------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+	use std::mem;
+	use image::IMAGE_SECTION_HEADER;
+	use super::*;
+	use super::super::tests::{as_bytes, dbi, msf};
+	use super::super::{ModuleInfo, NIL_STREAM_INDEX};
+
+	fn record(kind: u16, data: &[u8]) -> Vec<u8> {
+		let mut bytes = as_bytes(&(data.len() as u16 + 2)).to_vec();
+		bytes.extend_from_slice(as_bytes(&kind));
+		bytes.extend_from_slice(data);
+		bytes
+	}
+	fn public(segment: u16, offset: u32, name: &str) -> Vec<u8> {
+		let mut data = as_bytes(&0u32).to_vec();
+		data.extend_from_slice(as_bytes(&offset));
+		data.extend_from_slice(as_bytes(&segment));
+		data.extend_from_slice(name.as_bytes());
+		data.push(0);
+		record(S_PUB32, &data)
+	}
+	fn procedure(kind: u16, segment: u16, offset: u32, code_size: u32, name: &str) -> Vec<u8> {
+		let mut data = vec![0; 12];
+		data.extend_from_slice(as_bytes(&code_size));
+		data.extend_from_slice(&[0; 8]);
+		data.extend_from_slice(as_bytes(&0x1001u32));
+		data.extend_from_slice(as_bytes(&offset));
+		data.extend_from_slice(as_bytes(&segment));
+		data.push(0);
+		data.extend_from_slice(name.as_bytes());
+		data.push(0);
+		record(kind, &data)
+	}
+	fn symbol<'s>(bytes: &'s [u8]) -> Result<Symbol<'s>> {
+		SymbolRecords::new(bytes).next().ok_or(Error::Null)?.symbol()
+	}
+
+	#[test]
+	fn records() {
+		match symbol(&public(1, 0x10, "pub")) {
+			Ok(Symbol::Public { flags: 0, segment: 1, offset: 0x10, name }) => assert_eq!(name.to_str(), Ok("pub")),
+			_ => panic!(),
+		}
+		match symbol(&procedure(S_LPROC32_ID, 2, 0x20, 0x30, "proc")) {
+			Ok(Symbol::Procedure { global: false, segment: 2, offset: 0x20, code_size: 0x30, type_index: 0x1001, name }) => assert_eq!(name.to_str(), Ok("proc")),
+			_ => panic!(),
+		}
+		let mut data = as_bytes(&0x74u32).to_vec();
+		data.extend_from_slice(&[0x40, 0, 0, 0, 3, 0, b'g', 0]);
+		match symbol(&record(S_GDATA32, &data)) {
+			Ok(Symbol::Data { global: true, type_index: 0x74, segment: 3, offset: 0x40, name }) => assert_eq!(name.to_str(), Ok("g")),
+			_ => panic!(),
+		}
+		match symbol(&record(0x1111, &[1, 2, 3])) {
+			Ok(Symbol::Unknown) => (),
+			_ => panic!(),
+		}
+
+		// Truncated records and unterminated names
+		assert_eq!(symbol(&record(S_PUB32, &[0; 9])).err(), Some(Error::OOB));
+		assert_eq!(symbol(&record(S_GPROC32, &[0; 34])).err(), Some(Error::OOB));
+		assert_eq!(symbol(&record(S_PUB32, b"\0\0\0\0\0\0\0\0\0\0pub")).err(), Some(Error::CStr));
+	}
+
+	#[test]
+	fn record_iter() {
+		let mut bytes = public(1, 0x10, "a");
+		bytes.extend_from_slice(&record(0x1111, &[]));
+		bytes.extend_from_slice(&public(1, 0x20, "b"));
+		assert_eq!(SymbolRecords::new(&bytes).map(|record| record.kind).collect::<Vec<_>>(), [S_PUB32, 0x1111, S_PUB32]);
+
+		// Stops at records with a bad length
+		let mut truncated = bytes.clone();
+		truncated.extend_from_slice(&[0x10, 0, 0x0E, 0x11, 0]);
+		assert_eq!(SymbolRecords::new(&truncated).count(), 3);
+		let mut short = public(1, 0x10, "a");
+		short.extend_from_slice(&[1, 0, 0x0E, 0x11]);
+		short.extend_from_slice(&public(1, 0x20, "b"));
+		assert_eq!(SymbolRecords::new(&short).count(), 1);
+
+		// Module streams start with a signature and end before the line info
+		let mut info: ModuleInfo = unsafe { mem::zeroed() };
+		info.sym_byte_size = 4 + bytes.len() as u32;
+		let name = CStr::from_bytes(b"a.obj\0").unwrap();
+		let module = Module { index: 0, info, module_name: name, obj_file_name: name };
+		let mut stream = as_bytes(&CV_SIGNATURE_C13).to_vec();
+		stream.extend_from_slice(&bytes);
+		stream.extend_from_slice(&[0xF4, 0, 0, 0, 0x18, 0, 0, 0]);
+		assert_eq!(SymbolRecords::module(&stream, &module).unwrap().count(), 3);
+		assert_eq!(SymbolRecords::module(&stream[..8], &module).err(), Some(Error::OOB));
+		stream[0] = 1;
+		assert_eq!(SymbolRecords::module(&stream, &module).err(), Some(Error::BadMagic));
+	}
+
+	#[test]
+	fn symbolize() {
+		let mut sections: [IMAGE_SECTION_HEADER; 2] = unsafe { mem::zeroed() };
+		sections[0].VirtualAddress = 0x1000;
+		sections[1].VirtualAddress = 0x3000;
+		let mut section_headers = as_bytes(&sections[0]).to_vec();
+		section_headers.extend_from_slice(as_bytes(&sections[1]));
+
+		// The public of a procedure is merged with it, publics without section are ignored
+		let mut publics = public(1, 0x10, "pub_main");
+		publics.extend_from_slice(&public(2, 0x8, "pub_data"));
+		publics.extend_from_slice(&public(0, 0x4, "pub_abs"));
+
+		let mut procs = procedure(S_GPROC32, 1, 0x40, 0x8, "helper");
+		procs.extend_from_slice(&procedure(S_LPROC32, 1, 0x10, 0x20, "main"));
+		let mut module = as_bytes(&CV_SIGNATURE_C13).to_vec();
+		module.extend_from_slice(&procs);
+		let bad_magic = vec![0; 16];
+
+		// Modules with a corrupt symbol stream are skipped
+		let dbi = dbi(6, &[
+			("a.obj", 7, module.len() as u32),
+			("bad_magic.obj", 8, 16),
+			("too_small.obj", 7, module.len() as u32 + 1),
+			("missing.obj", 9, 16),
+			("import.obj", NIL_STREAM_INDEX, 0),
+		], &[NIL_STREAM_INDEX, NIL_STREAM_INDEX, NIL_STREAM_INDEX, NIL_STREAM_INDEX, NIL_STREAM_INDEX, 5]);
+		let bytes = msf(&[&[], &[], &[], &dbi, &[], &section_headers, &publics, &module, &bad_magic]);
+		let pdb = Pdb::from_bytes(&bytes).unwrap();
+		let table = pdb.symbols().unwrap();
+
+		assert_eq!(table.len(), 3);
+		assert!(!table.is_empty());
+		let symbols: Vec<_> = table.iter().map(|sym| (sym.rva, sym.size, sym.name.to_str().unwrap())).collect();
+		assert_eq!(symbols, [(0x1010, 0x20, "main"), (0x1040, 0x8, "helper"), (0x3008, 0, "pub_data")]);
+
+		let name = |rva| table.symbolize(rva).map(|sym| sym.to_string());
+		assert_eq!(name(0x1000), None);
+		assert_eq!(name(0x1010), Some(String::from("main")));
+		assert_eq!(name(0x102F), Some(String::from("main+0x1f")));
+		assert_eq!(name(0x1030), None);
+		assert_eq!(name(0x1047), Some(String::from("helper+0x7")));
+		assert_eq!(name(0x1048), None);
+		// Symbols without a size extend to the next symbol
+		assert_eq!(name(0x4000), Some(String::from("pub_data+0xff8")));
+	}
+}
@@ -171,6 +171,8 @@ pub mod debug;
 mod ptr;
 #[path = "../pe64/scanner.rs"]
 pub mod scanner;
+#[path = "../pe64/signature.rs"]
+pub mod signature;
 
 pub mod msvc;
 
@@ -33,9 +33,10 @@ fn example(file: PeFile) -> pelite::Result<()> {
 ```
 */
 
-use std::{fmt, mem, slice};
+use std::{cmp, fmt, mem, slice};
 
 use error::{Error, Result};
+use symstore::Key;
 use util::CStr;
 
 use super::image::*;
@@ -110,7 +111,9 @@ impl<'a, P: Pe<'a> + Copy> Dir<'a, P> {
 	}
 	/// Gets the referenced debug info.
 	pub fn info(&self) -> Result<Info<'a>> {
-		let bytes = self.pe.slice(self.image.AddressOfRawData, self.image.SizeOfData as usize, 4)?;
+		let size = self.image.SizeOfData as usize;
+		// Some debug info such as REPRO may have no data at all
+		let bytes = if size == 0 { &[][..] } else { self.pe.slice(self.image.AddressOfRawData, size, 4)? };
 		match self.image.Type {
 			IMAGE_DEBUG_TYPE_CODEVIEW => {
 				if bytes.len() >= 4 {
@@ -154,6 +157,72 @@ impl<'a, P: Pe<'a> + Copy> Dir<'a, P> {
 					Err(Error::Corrupt)
 				}
 			},
+			IMAGE_DEBUG_TYPE_FPO => {
+				if size % mem::size_of::<FPO_DATA>() != 0 {
+					return Err(Error::Corrupt);
+				}
+				let image = if size == 0 { &[][..] } else { self.pe.derva_slice(self.image.AddressOfRawData, size / mem::size_of::<FPO_DATA>())? };
+				Ok(Info::Fpo { image })
+			},
+			IMAGE_DEBUG_TYPE_VC_FEATURE => {
+				if size >= mem::size_of::<IMAGE_DEBUG_VC_FEATURE>() {
+					let image = unsafe { &*(bytes.as_ptr() as *const IMAGE_DEBUG_VC_FEATURE) };
+					Ok(Info::VcFeature { image })
+				}
+				else {
+					Err(Error::Corrupt)
+				}
+			},
+			IMAGE_DEBUG_TYPE_POGO => {
+				if size >= 4 {
+					let signature = unsafe { *(bytes.as_ptr() as *const u32) };
+					let entries = PogoEntries { bytes: &bytes[4..size] };
+					Ok(Info::Pogo { signature, entries })
+				}
+				else {
+					Err(Error::Corrupt)
+				}
+			},
+			IMAGE_DEBUG_TYPE_REPRO => {
+				// Without data the TimeDateStamp of the debug directory is the hash
+				if size == 0 {
+					Ok(Info::Repro { hash: &[] })
+				}
+				else if size >= 4 {
+					let len = unsafe { *(bytes.as_ptr() as *const u32) } as usize;
+					let hash = bytes[4..size].get(..len).ok_or(Error::Corrupt)?;
+					Ok(Info::Repro { hash })
+				}
+				else {
+					Err(Error::Corrupt)
+				}
+			},
+			IMAGE_DEBUG_TYPE_EX_DLLCHARACTERISTICS => {
+				if size >= 4 {
+					let characteristics = unsafe { *(bytes.as_ptr() as *const u32) };
+					Ok(Info::ExDllCharacteristics { characteristics })
+				}
+				else {
+					Err(Error::Corrupt)
+				}
+			},
+			IMAGE_DEBUG_TYPE_EMBEDDED_PORTABLE_PDB => {
+				if size >= 8 {
+					let header = unsafe { &*(bytes.as_ptr() as *const [u32; 2]) };
+					if header[0] != IMAGE_DEBUG_EMBEDDED_PORTABLE_PDB_SIGNATURE {
+						return Err(Error::BadMagic);
+					}
+					Ok(Info::EmbeddedPortablePdb { uncompressed_size: header[1], compressed: &bytes[8..size] })
+				}
+				else {
+					Err(Error::Corrupt)
+				}
+			},
+			IMAGE_DEBUG_TYPE_PDBCHECKSUM => {
+				let algorithm = CStr::from_bytes(&bytes[..size])?;
+				let checksum = &bytes[algorithm.c_str().len()..size];
+				Ok(Info::PdbChecksum { algorithm, checksum })
+			},
 			_ => Ok(Info::Unknown),
 		}
 	}
@@ -161,17 +230,101 @@ impl<'a, P: Pe<'a> + Copy> Dir<'a, P> {
 
 //----------------------------------------------------------------
 
+/// POGO debug info entry.
+///
+/// Describes a subsection of the image as laid out by profile guided optimization.
+#[derive(Copy, Clone, Debug)]
+pub struct PogoEntry<'a> {
+	pub rva: Rva,
+	pub size: u32,
+	pub name: &'a CStr,
+}
+
+/// Iterator over the POGO debug info entries.
+#[derive(Copy, Clone)]
+pub struct PogoEntries<'a> {
+	bytes: &'a [u8],
+}
+impl<'a> PogoEntries<'a> {
+	/// Returns the underlying bytes.
+	pub fn as_slice(&self) -> &'a [u8] {
+		self.bytes
+	}
+}
+impl<'a> Iterator for PogoEntries<'a> {
+	type Item = PogoEntry<'a>;
+	fn next(&mut self) -> Option<PogoEntry<'a>> {
+		if self.bytes.len() < 8 {
+			return None;
+		}
+		// Entries are aligned to 4 bytes
+		let header = unsafe { &*(self.bytes.as_ptr() as *const [u32; 2]) };
+		let name = match CStr::from_bytes(&self.bytes[8..]) {
+			Ok(name) => name,
+			Err(_) => {
+				self.bytes = &[];
+				return None;
+			},
+		};
+		let len = cmp::min((8 + name.c_str().len() + 3) & !3, self.bytes.len());
+		self.bytes = &self.bytes[len..];
+		Some(PogoEntry { rva: header[0], size: header[1], name })
+	}
+}
+
+//----------------------------------------------------------------
+
 #[derive(Copy, Clone)]
 pub enum Info<'a> {
 	Unknown,
 	CvNB10 { image: &'a IMAGE_DEBUG_CV_INFO_PDB20, pdb_file_name: &'a CStr },
 	CvRSDS { image: &'a IMAGE_DEBUG_CV_INFO_PDB70, pdb_file_name: &'a CStr },
 	Dbg { image: &'a IMAGE_DEBUG_MISC },
+	/// Frame pointer omission info.
+	Fpo { image: &'a [FPO_DATA] },
+	/// Number of object files compiled with each security feature.
+	VcFeature { image: &'a IMAGE_DEBUG_VC_FEATURE },
+	/// Profile guided optimization section layout.
+	Pogo { signature: u32, entries: PogoEntries<'a> },
+	/// Hash of a deterministic build.
+	///
+	/// The hash is empty if the TimeDateStamp of the debug directory holds the hash instead.
+	Repro { hash: &'a [u8] },
+	/// Extended DLL characteristics, see `IMAGE_DLLCHARACTERISTICS_EX_*`.
+	ExDllCharacteristics { characteristics: u32 },
+	/// Deflate compressed portable PDB.
+	EmbeddedPortablePdb { uncompressed_size: u32, compressed: &'a [u8] },
+	/// Checksum of the PDB using the named hash algorithm.
+	PdbChecksum { algorithm: &'a CStr, checksum: &'a [u8] },
+}
+
+impl<'a> Info<'a> {
+	/// Gets the symbol store key of the referenced PDB.
+	///
+	/// Returns `None` if this isn't CodeView debug info or the PDB file name isn't valid UTF-8.
+	pub fn symstore_key(&self) -> Option<Key> {
+		match *self {
+			Info::CvNB10 { image, pdb_file_name } => {
+				pdb_file_name.to_str().ok().map(|name| Key::pdb20(name, image.TimeDateStamp, image.Age))
+			},
+			Info::CvRSDS { image, pdb_file_name } => {
+				pdb_file_name.to_str().ok().map(|name| Key::pdb70(name, &image.Signature, image.Age))
+			},
+			_ => None,
+		}
+	}
 }
 
 //----------------------------------------------------------------
 // Formatting
 
+use strings::Fmt;
+
+static IMAGE_DLLCHARACTERISTICS_EX_STRINGS: [&str; 8] = [
+	"CET_COMPAT", "CET_COMPAT_STRICT_MODE", "CET_SET_CONTEXT_IP_VALIDATION_RELAXED_MODE", "CET_DYNAMIC_APIS_ALLOW_IN_PROC",
+	"CET_RESERVED_1", "CET_RESERVED_2", "FORWARD_CFI_COMPAT", "HOTPATCH_COMPATIBLE",
+];
+
 impl<'a, P: Pe<'a> + Copy> fmt::Debug for Debug<'a, P> {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
 		for dir in *self {
@@ -205,6 +358,196 @@ impl<'a> fmt::Debug for Info<'a> {
 			Info::Dbg { image } => {
 				write!(f, "{:?}", image)
 			},
+			Info::Fpo { image } => {
+				for fpo in image {
+					write!(f, "{:?}", fpo)?;
+				}
+				Ok(())
+			},
+			Info::VcFeature { image } => {
+				write!(f, "{:?}", image)
+			},
+			Info::Pogo { signature, entries } => {
+				write!(f, "POGO {}\n", Fmt(|f| match signature {
+					IMAGE_DEBUG_POGO_SIGNATURE_LTCG => f.write_str("LTCG"),
+					IMAGE_DEBUG_POGO_SIGNATURE_PGI => f.write_str("PGI"),
+					IMAGE_DEBUG_POGO_SIGNATURE_PGU => f.write_str("PGU"),
+					signature => write!(f, "{:08X}", signature),
+				}))?;
+				for entry in entries {
+					write!(f, "  {:·>8X} {:·>8X} {}\n", entry.rva, entry.size, entry.name)?;
+				}
+				Ok(())
+			},
+			Info::Repro { hash } => {
+				write!(f, "Repro\n  Hash:             {}\n", Fmt(|f| hash.iter().map(|byte| write!(f, "{:02X}", byte)).collect()))
+			},
+			Info::ExDllCharacteristics { characteristics } => {
+				write!(f, "ExDllCharacteristics\n  Characteristics:  {:·>8X}{}\n", characteristics, Fmt(|f| {
+					for (i, name) in IMAGE_DLLCHARACTERISTICS_EX_STRINGS.iter().enumerate() {
+						if characteristics & (1 << i) != 0 {
+							write!(f, " {}", name)?;
+						}
+					}
+					Ok(())
+				}))
+			},
+			Info::EmbeddedPortablePdb { uncompressed_size, compressed } => {
+				write!(f, "Embedded Portable PDB\n  UncompressedSize: {:·>8X}\n  CompressedSize:   {:·>8X}\n", uncompressed_size, compressed.len())
+			},
+			Info::PdbChecksum { algorithm, checksum } => {
+				write!(f, "PDB Checksum\n  Algorithm:        {}\n  Checksum:         {}\n", algorithm, Fmt(|f| checksum.iter().map(|byte| write!(f, "{:02X}", byte)).collect()))
+			},
+		}
+	}
+}
+
+//----------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+	use std::{ptr, slice};
+	use super::*;
+	use super::super::PeView;
+
+	// Handcrafted image with a single debug directory whose data follows the headers
+	fn image(ty: u32, data: &[u8], size_of_data: u32) -> Vec<u64> {
+		let size_of_image = 0x220 + ((data.len() + 7) & !7);
+		let mut image = vec![0u64; size_of_image / 8];
+		unsafe {
+			let base = image.as_mut_ptr() as *mut u8;
+			let dos = &mut *(base as *mut IMAGE_DOS_HEADER);
+			dos.e_magic = IMAGE_DOS_SIGNATURE;
+			dos.e_lfanew = 0x40;
+			let nt = &mut *(base.offset(0x40) as *mut IMAGE_NT_HEADERS);
+			nt.Signature = IMAGE_NT_HEADERS_SIGNATURE;
+			nt.FileHeader.SizeOfOptionalHeader = mem::size_of::<IMAGE_OPTIONAL_HEADER>() as u16;
+			nt.OptionalHeader.Magic = IMAGE_NT_OPTIONAL_HDR_MAGIC;
+			nt.OptionalHeader.SizeOfImage = size_of_image as u32;
+			nt.OptionalHeader.SizeOfHeaders = 0x200;
+			nt.OptionalHeader.NumberOfRvaAndSizes = IMAGE_NUMBEROF_DIRECTORY_ENTRIES as u32;
+			nt.OptionalHeader.DataDirectory[IMAGE_DIRECTORY_ENTRY_DEBUG].VirtualAddress = 0x200;
+			nt.OptionalHeader.DataDirectory[IMAGE_DIRECTORY_ENTRY_DEBUG].Size = mem::size_of::<IMAGE_DEBUG_DIRECTORY>() as u32;
+			let dir = &mut *(base.offset(0x200) as *mut IMAGE_DEBUG_DIRECTORY);
+			dir.Type = ty;
+			dir.SizeOfData = size_of_data;
+			dir.AddressOfRawData = if size_of_data != 0 { 0x220 } else { 0 };
+			ptr::copy_nonoverlapping(data.as_ptr(), base.offset(0x220), data.len());
+		}
+		image
+	}
+	fn info<'a>(image: &'a [u64]) -> Result<Info<'a>> {
+		let bytes = unsafe { slice::from_raw_parts(image.as_ptr() as *const u8, image.len() * 8) };
+		let view = PeView::from_bytes(bytes)?;
+		let dir = view.debug()?.into_iter().next().ok_or(Error::Null)?;
+		dir.info()
+	}
+	fn data(dwords: &[u32], tail: &[u8]) -> Vec<u8> {
+		let mut bytes: Vec<u8> = dwords.iter().flat_map(|&dword| (0..4).map(move |i| (dword >> (i * 8)) as u8)).collect();
+		bytes.extend_from_slice(tail);
+		bytes
+	}
+
+	#[test]
+	fn pogo() {
+		let mut bytes = data(&[IMAGE_DEBUG_POGO_SIGNATURE_LTCG, 0x1000, 0x20], b".text$mn\0\0\0\0");
+		bytes.extend_from_slice(&data(&[0x1020, 0x8], b".rdata\0\0"));
+		let pogo = image(IMAGE_DEBUG_TYPE_POGO, &bytes, bytes.len() as u32);
+		match info(&pogo) {
+			Ok(Info::Pogo { signature, entries }) => {
+				assert_eq!(signature, IMAGE_DEBUG_POGO_SIGNATURE_LTCG);
+				let entries: Vec<_> = entries.map(|entry| (entry.rva, entry.size, entry.name.to_str().unwrap())).collect();
+				assert_eq!(entries, [(0x1000, 0x20, ".text$mn"), (0x1020, 0x8, ".rdata")]);
+			},
+			_ => panic!(),
+		}
+		// Unterminated names end the entries
+		let bytes = data(&[IMAGE_DEBUG_POGO_SIGNATURE_LTCG, 0x1000, 0x20], b".text");
+		let unterminated = image(IMAGE_DEBUG_TYPE_POGO, &bytes, bytes.len() as u32);
+		match info(&unterminated) {
+			Ok(Info::Pogo { entries, .. }) => assert_eq!(entries.count(), 0),
+			_ => panic!(),
+		}
+		assert_eq!(info(&image(IMAGE_DEBUG_TYPE_POGO, b"LTC", 3)).err(), Some(Error::Corrupt));
+		assert_eq!(info(&image(IMAGE_DEBUG_TYPE_POGO, &[], 0)).err(), Some(Error::Corrupt));
+	}
+
+	#[test]
+	fn repro() {
+		let bytes = data(&[8, 0x44332211, 0x88776655], &[]);
+		match info(&image(IMAGE_DEBUG_TYPE_REPRO, &bytes, 12)) {
+			Ok(Info::Repro { hash }) => assert_eq!(hash, &[0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88]),
+			_ => panic!(),
+		}
+		match info(&image(IMAGE_DEBUG_TYPE_REPRO, &[], 0)) {
+			Ok(Info::Repro { hash }) => assert!(hash.is_empty()),
+			_ => panic!(),
+		}
+		// The hash length exceeds the data
+		assert_eq!(info(&image(IMAGE_DEBUG_TYPE_REPRO, &bytes, 8)).err(), Some(Error::Corrupt));
+		assert_eq!(info(&image(IMAGE_DEBUG_TYPE_REPRO, &bytes, 2)).err(), Some(Error::Corrupt));
+	}
+
+	#[test]
+	fn fpo() {
+		let bytes = data(&[0x1000, 0x40, 2, 0x0001_0003, 0x1040, 0x10, 0, 0], &[]);
+		match info(&image(IMAGE_DEBUG_TYPE_FPO, &bytes, 32)) {
+			Ok(Info::Fpo { image }) => {
+				assert_eq!(image.len(), 2);
+				assert_eq!(({ image[0].ulOffStart }, { image[0].cbProcSize }, { image[0].cdwLocals }), (0x1000, 0x40, 2));
+				assert_eq!(({ image[0].cdwParams }, { image[0].Attributes }), (3, 1));
+				assert_eq!({ image[1].ulOffStart }, 0x1040);
+			},
+			_ => panic!(),
+		}
+		match info(&image(IMAGE_DEBUG_TYPE_FPO, &[], 0)) {
+			Ok(Info::Fpo { image }) => assert!(image.is_empty()),
+			_ => panic!(),
+		}
+		assert_eq!(info(&image(IMAGE_DEBUG_TYPE_FPO, &bytes, 20)).err(), Some(Error::Corrupt));
+		// The data extends past the end of the image
+		assert_eq!(info(&image(IMAGE_DEBUG_TYPE_FPO, &bytes, 48)).err(), Some(Error::OOB));
+	}
+
+	#[test]
+	fn ex_dll_characteristics() {
+		let bytes = data(&[IMAGE_DLLCHARACTERISTICS_EX_CET_COMPAT], &[]);
+		match info(&image(IMAGE_DEBUG_TYPE_EX_DLLCHARACTERISTICS, &bytes, 4)) {
+			Ok(Info::ExDllCharacteristics { characteristics }) => assert_eq!(characteristics, IMAGE_DLLCHARACTERISTICS_EX_CET_COMPAT),
+			_ => panic!(),
+		}
+		assert_eq!(info(&image(IMAGE_DEBUG_TYPE_EX_DLLCHARACTERISTICS, &bytes, 2)).err(), Some(Error::Corrupt));
+		assert_eq!(info(&image(IMAGE_DEBUG_TYPE_EX_DLLCHARACTERISTICS, &[], 0)).err(), Some(Error::Corrupt));
+	}
+
+	#[test]
+	fn embedded_portable_pdb() {
+		let bytes = data(&[IMAGE_DEBUG_EMBEDDED_PORTABLE_PDB_SIGNATURE, 0x100], &[1, 2, 3]);
+		match info(&image(IMAGE_DEBUG_TYPE_EMBEDDED_PORTABLE_PDB, &bytes, 11)) {
+			Ok(Info::EmbeddedPortablePdb { uncompressed_size, compressed }) => {
+				assert_eq!(uncompressed_size, 0x100);
+				assert_eq!(compressed, &[1, 2, 3]);
+			},
+			_ => panic!(),
+		}
+		let bad = data(&[0x12345678, 0x100], &[]);
+		assert_eq!(info(&image(IMAGE_DEBUG_TYPE_EMBEDDED_PORTABLE_PDB, &bad, 8)).err(), Some(Error::BadMagic));
+		assert_eq!(info(&image(IMAGE_DEBUG_TYPE_EMBEDDED_PORTABLE_PDB, &bytes, 4)).err(), Some(Error::Corrupt));
+		assert_eq!(info(&image(IMAGE_DEBUG_TYPE_EMBEDDED_PORTABLE_PDB, &[], 0)).err(), Some(Error::Corrupt));
+	}
+
+	#[test]
+	fn pdb_checksum() {
+		let bytes = b"SHA256\0\x01\x02\x03\x04";
+		match info(&image(IMAGE_DEBUG_TYPE_PDBCHECKSUM, bytes, 11)) {
+			Ok(Info::PdbChecksum { algorithm, checksum }) => {
+				assert_eq!(algorithm.to_str(), Ok("SHA256"));
+				assert_eq!(checksum, &[1, 2, 3, 4]);
+			},
+			_ => panic!(),
 		}
+		// The algorithm name isn't terminated within the data
+		assert_eq!(info(&image(IMAGE_DEBUG_TYPE_PDBCHECKSUM, bytes, 6)).err(), Some(Error::CStr));
+		assert_eq!(info(&image(IMAGE_DEBUG_TYPE_PDBCHECKSUM, &[], 0)).err(), Some(Error::CStr));
 	}
 }
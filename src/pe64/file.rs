@@ -22,16 +22,16 @@ impl<'a> PeFile<'a> {
 	}
 	fn section_get(&self, rva: Rva, min_size: usize) -> Result<&'a [u8]> {
 		// Cannot reuse `self.rva_to_file_offset` because it doesn't return the size of the section
-		// FIXME! What to do about all the potential overflows?
+		// The section headers are untrusted, their ends saturate instead of overflowing
 		for it in self.section_headers() {
 			#[allow(non_snake_case)]
-			let VirtualEnd = it.VirtualAddress + it.VirtualSize;
+			let VirtualEnd = it.VirtualAddress.saturating_add(it.VirtualSize);
 			// Rva is contained within the virtual space of a section
 			if rva >= it.VirtualAddress && rva < VirtualEnd {
 				// Rva is contained in the physical space of the section
-				if rva < it.VirtualAddress + it.SizeOfRawData {
+				if rva < it.VirtualAddress.saturating_add(it.SizeOfRawData) {
 					let start = (rva - it.VirtualAddress + it.PointerToRawData) as FileOffset;
-					let end = (it.PointerToRawData + it.SizeOfRawData) as FileOffset;
+					let end = it.PointerToRawData.saturating_add(it.SizeOfRawData) as FileOffset;
 					return match self.image.get(start..end) {
 						Some(bytes) if bytes.len() >= min_size => Ok(bytes),
 						_ => {
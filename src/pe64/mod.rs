@@ -158,6 +158,7 @@ pub mod tls;
 pub mod debug;
 mod ptr;
 pub mod scanner;
+pub mod signature;
 
 pub use self::image::{Va, Rva};
 pub use self::pe::{Pe};
@@ -25,15 +25,45 @@ fn example(file: PeFile, pat: &[Atom]) -> Option<Match> {
 		println!("found: {}", m);
 	}
 
+	// Find a singular code match saving to a larger buffer
+	let mut save = [0; 16];
+	if scanner.find_code_into(pat, &mut save) {
+		println!("found: {:08X}", save[0]);
+	}
+
+	// Find the code matches for many patterns at once
+	for (index, m) in scanner.matches_many_code(&[pat, pat]) {
+		println!("found {}: {}", index, m);
+	}
+
 	None
 }
 ```
+
+The scanner is not limited to PE images, any memory implementing [`Memory`](trait.Memory.html) can be scanned.
+A byte slice such as a memory dump or shellcode is scanned with [`Slice`](struct.Slice.html) given the address it is loaded at:
+
+```
+use pelite::pe64::scanner::{Scanner, Slice};
+use pelite::pattern;
+
+let bytes = [0x90, 0x48, 0x8B, 0x05, 0x08, 0x00, 0x00, 0x00, 0xC3, 0xCC];
+let scanner = Scanner::new(Slice::new(&bytes, 0x140001000));
+
+let pat = pattern::parse("488B05${'} C3").unwrap();
+let m = scanner.find(&pat, 0..bytes.len() as u32).unwrap();
+
+// The addresses are relative to the start of the slice
+assert_eq!((m.0, m.1), (1, 0x10));
+```
 */
 
-use std::mem;
+use std::{cmp, mem, vec};
 use std::ops::Range;
 
 use pattern as pat;
+use util::aho_corasick::{self, AhoCorasick};
+use util::Finder;
 
 use super::{Rva, Pe};
 use super::image::*;
@@ -43,22 +73,225 @@ const QS_BUF_LEN: usize = 16;
 
 //----------------------------------------------------------------
 
+/// Memory access for the scanner.
+///
+/// Addresses are rvas relative to the start of the memory, pointers are translated back to rvas.
+pub trait Memory<'a> {
+	/// Returns the bytes at the address until the end of the contiguous memory containing it.
+	///
+	/// Returns an empty slice if the address isn't mapped.
+	fn bytes(&self, address: Rva) -> &'a [u8];
+	/// Returns the first contiguous chunk of memory overlapping the range and its address.
+	///
+	/// The chunk is clipped to the range, returns `None` if no memory is mapped in the range.
+	fn chunk(&self, range: Range<Rva>) -> Option<(Rva, &'a [u8])>;
+	/// Translates a pointer read from the memory to its address.
+	fn translate(&self, ptr: Va) -> Option<Rva>;
+}
+
+/// The PE image is scanned by its sections.
+impl<'a, P: Pe<'a> + Copy> Memory<'a> for P {
+	fn bytes(&self, address: Rva) -> &'a [u8] {
+		self.slice_bytes(address).unwrap_or(&[])
+	}
+	fn chunk(&self, range: Range<Rva>) -> Option<(Rva, &'a [u8])> {
+		self.section_headers().iter().filter_map(|it| {
+			// Mapped images include the zero filled tail, files only have the raw data which `slice_bytes` clips to
+			let size = cmp::max(it.VirtualSize, it.SizeOfRawData);
+			let start = cmp::max(range.start, it.VirtualAddress);
+			let end = cmp::min(range.end, it.VirtualAddress.saturating_add(size));
+			if start >= end {
+				return None;
+			}
+			let bytes = self.slice_bytes(start).ok()?;
+			Some((start, &bytes[..cmp::min(bytes.len(), (end - start) as usize)]))
+		}).min_by_key(|&(start, _)| start)
+	}
+	fn translate(&self, ptr: Va) -> Option<Rva> {
+		self.va_to_rva(ptr).ok()
+	}
+}
+
+/// Memory of a byte slice loaded at an address.
+#[derive(Copy, Clone, Debug)]
+pub struct Slice<'a> {
+	bytes: &'a [u8],
+	base: Va,
+}
+impl<'a> Slice<'a> {
+	/// Creates memory for the bytes loaded at the base address.
+	///
+	/// Pointers are translated relative to the base address, the bytes past 4 GiB are not accessible.
+	pub fn new(bytes: &'a [u8], base: Va) -> Slice<'a> {
+		Slice { bytes: &bytes[..cmp::min(bytes.len(), Rva::max_value() as usize)], base }
+	}
+	/// Gets the bytes.
+	pub fn as_bytes(&self) -> &'a [u8] {
+		self.bytes
+	}
+	/// Gets the base address.
+	pub fn base(&self) -> Va {
+		self.base
+	}
+}
+impl<'a> Memory<'a> for Slice<'a> {
+	fn bytes(&self, address: Rva) -> &'a [u8] {
+		self.bytes.get(address as usize..).unwrap_or(&[])
+	}
+	fn chunk(&self, range: Range<Rva>) -> Option<(Rva, &'a [u8])> {
+		let end = cmp::min(range.end as usize, self.bytes.len());
+		if (range.start as usize) < end {
+			Some((range.start, &self.bytes[range.start as usize..end]))
+		}
+		else {
+			None
+		}
+	}
+	fn translate(&self, ptr: Va) -> Option<Rva> {
+		if ptr >= self.base && ptr - self.base < self.bytes.len() as Va {
+			Some((ptr - self.base) as Rva)
+		}
+		else {
+			None
+		}
+	}
+}
+
+//----------------------------------------------------------------
+
+/// Ranges to scan.
+///
+/// A sorted set of disjoint ranges of rvas, overlapping and adjacent ranges are merged.
+/// The scanner methods accept a single range, a vector or a slice of ranges.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Ranges(Vec<Range<Rva>>);
+impl Ranges {
+	/// Creates an empty set of ranges.
+	pub fn new() -> Ranges {
+		Ranges(Vec::new())
+	}
+	/// Adds the range to the set.
+	pub fn push(&mut self, mut range: Range<Rva>) {
+		if range.start >= range.end {
+			return;
+		}
+		let i = self.0.iter().position(|it| it.end >= range.start).unwrap_or(self.0.len());
+		let mut j = i;
+		while j < self.0.len() && self.0[j].start <= range.end {
+			range.start = cmp::min(range.start, self.0[j].start);
+			range.end = cmp::max(range.end, self.0[j].end);
+			j += 1;
+		}
+		self.0.drain(i..j);
+		self.0.insert(i, range);
+	}
+	/// Returns the ranges in ascending order.
+	pub fn as_slice(&self) -> &[Range<Rva>] {
+		&self.0
+	}
+}
+impl From<Range<Rva>> for Ranges {
+	fn from(range: Range<Rva>) -> Ranges {
+		let mut ranges = Ranges::new();
+		ranges.push(range);
+		ranges
+	}
+}
+impl<'a> From<&'a [Range<Rva>]> for Ranges {
+	fn from(slice: &'a [Range<Rva>]) -> Ranges {
+		let mut ranges = Ranges::new();
+		for range in slice {
+			ranges.push(range.clone());
+		}
+		ranges
+	}
+}
+impl From<Vec<Range<Rva>>> for Ranges {
+	fn from(vec: Vec<Range<Rva>>) -> Ranges {
+		Ranges::from(&vec[..])
+	}
+}
+
+//----------------------------------------------------------------
+
 /// Pattern scanner.
 #[derive(Copy, Clone)]
-pub struct Scanner<P> {
-	pe: P,
+pub struct Scanner<M> {
+	memory: M,
 }
 impl<'a, P: Pe<'a> + Copy> Scanner<P> {
-	pub(crate) fn new(pe: P) -> Scanner<P> {
-		Scanner { pe }
+	/// Gets the PE instance.
+	pub fn pe(&self) -> P {
+		self.memory
+	}
+	/// Finds the unique code match for the pattern.
+	///
+	/// Restricts the ranges to the executable sections. See [`find`](#find) for more information.
+	pub fn find_code(self, pat: &[self::pat::Atom]) -> Option<pat::Match> {
+		self.find(pat, self.code_ranges())
+	}
+	/// Finds the unique code match for the pattern and saves to the buffer.
+	///
+	/// Restricts the ranges to the executable sections. See [`find_into`](#method.find_into) for more information.
+	pub fn find_code_into(self, pat: &[pat::Atom], save: &mut [Rva]) -> bool {
+		self.find_into(pat, self.code_ranges(), save)
+	}
+	/// Returns an iterator over the code matches of a pattern.
+	///
+	/// Restricts the ranges to the executable sections. See [`matches`](#matches) for more information.
+	pub fn matches_code(self, pat: &[self::pat::Atom]) -> Matches<P> {
+		self.matches(pat, self.code_ranges())
+	}
+	/// Returns an iterator over the code matches of many patterns.
+	///
+	/// Restricts the ranges to the executable sections. See [`matches_many`](#matches_many) for more information.
+	pub fn matches_many_code<'u>(self, pats: &'u [&'u [pat::Atom]]) -> MatchesMany<'u, P> {
+		self.matches_many(pats, self.code_ranges())
+	}
+	/// Returns the ranges of the sections selected by the predicate.
+	pub fn section_ranges<F>(&self, mut f: F) -> Ranges where F: FnMut(&IMAGE_SECTION_HEADER) -> bool {
+		let mut ranges = Ranges::new();
+		for it in self.memory.section_headers() {
+			if f(it) {
+				// The virtual size is zero in images from some linkers
+				let size = cmp::max(it.VirtualSize, it.SizeOfRawData);
+				ranges.push(it.VirtualAddress..it.VirtualAddress.saturating_add(size));
+			}
+		}
+		ranges
+	}
+	/// Returns the ranges of the executable sections.
+	///
+	/// The `BaseOfCode` and `SizeOfCode` fields of the optional header don't cover images with several code sections.
+	pub fn code_ranges(&self) -> Ranges {
+		self.section_ranges(|it| it.Characteristics & IMAGE_SCN_MEM_EXECUTE != 0)
+	}
+	/// Returns the ranges of the sections with the name.
+	pub fn named_ranges(&self, name: &str) -> Ranges {
+		self.section_ranges(|it| {
+			let len = it.Name.iter().position(|&byte| byte == 0).unwrap_or(it.Name.len());
+			&it.Name[..len] == name.as_bytes()
+		})
+	}
+}
+impl<'a, M: Memory<'a> + Copy> Scanner<M> {
+	/// Creates a scanner for the memory.
+	///
+	/// PE images have a [`scanner`](../trait.Pe.html#method.scanner) method to create their scanner.
+	pub fn new(memory: M) -> Scanner<M> {
+		Scanner { memory }
+	}
+	/// Gets the memory.
+	pub fn memory(&self) -> M {
+		self.memory
 	}
-	/// Finds the unique match for the pattern in the given range.
+	/// Finds the unique match for the pattern in the given ranges.
 	///
 	/// Returns `None` if multiple matches are found to prevent subtle bugs where a pattern goes stale by not being unique any more.
 	///
-	/// Use `matches(pat, range).next()` if just the first match is desired.
-	pub fn find(self, pat: &[self::pat::Atom], range: Range<Rva>) -> Option<pat::Match> {
-		let mut matches = self.matches(pat, range);
+	/// Use `matches(pat, ranges).next()` if just the first match is desired.
+	pub fn find<R: Into<Ranges>>(self, pat: &[self::pat::Atom], ranges: R) -> Option<pat::Match> {
+		let mut matches = self.matches(pat, ranges);
 		if let Some(found) = matches.next() {
 			// Disallow more than one match as it indicates the signature isn't unique enough
 			match matches.next() {
@@ -70,44 +303,87 @@ impl<'a, P: Pe<'a> + Copy> Scanner<P> {
 			None
 		}
 	}
-	/// Finds the unique code match for the pattern.
+	/// Finds the unique match for the pattern in the given ranges and saves to the buffer.
 	///
-	/// Restricts the range to the code section. See [`find`](#find) for more information.
-	pub fn find_code(self, pat: &[self::pat::Atom]) -> Option<pat::Match> {
-		let optional_header = self.pe.optional_header();
-		let range = optional_header.BaseOfCode..optional_header.BaseOfCode + optional_header.SizeOfCode;
-		self.find(pat, range)
+	/// Same as [`find`](#method.find) but the save slots are written to a buffer of any size instead of a [`Match`](../../pattern/struct.Match.html).
+	/// Returns `false` if there's no unique match, the contents of the buffer are unspecified in that case.
+	pub fn find_into<R: Into<Ranges>>(self, pat: &[pat::Atom], ranges: R, save: &mut [Rva]) -> bool {
+		let mut matches = self.matches(pat, ranges);
+		match matches.next_cursor(save) {
+			// Rerun the first match as looking for another clobbers the buffer
+			Some(cursor) => matches.next_cursor(save).is_none() && self.exec_into(pat, cursor, save),
+			None => false,
+		}
 	}
-	/// Returns an iterator over the matches of a pattern within the given range.
-	pub fn matches(self, pat: &[self::pat::Atom], range: Range<Rva>) -> Matches<P> {
-		Matches { scanner: self, pat, range, hits: 0 }
+	/// Returns an iterator over the matches of a pattern within the given ranges.
+	///
+	/// The matches start in the ranges but may extend past their end.
+	pub fn matches<R: Into<Ranges>>(self, pat: &[self::pat::Atom], ranges: R) -> Matches<M> {
+		let mut ranges = ranges.into().0.into_iter();
+		let range = ranges.next().unwrap_or(0..0);
+		Matches { scanner: self, pat, range, ranges, hits: 0 }
 	}
-	/// Returns an iterator over the code matches of a pattern.
+	/// Returns an iterator over the matches of many patterns within the given ranges.
 	///
-	/// Restricts the range to the code section. See [`matches`](#matches) for more information.
-	pub fn matches_code(self, pat: &[self::pat::Atom]) -> Matches<P> {
-		let optional_header = self.pe.optional_header();
-		let range = optional_header.BaseOfCode..optional_header.BaseOfCode + optional_header.SizeOfCode;
-		self.matches(pat, range)
+	/// The ranges are scanned only once for the literal prefixes of all the patterns,
+	/// the matches are tagged with the index of their pattern and are yielded in the order their prefix is found.
+	pub fn matches_many<'u, R: Into<Ranges>>(self, pats: &'u [&'u [pat::Atom]], ranges: R) -> MatchesMany<'u, M> {
+		let mut prefix_lens = Vec::with_capacity(pats.len());
+		let mut unanchored = Vec::new();
+		let mut prefixes = Vec::with_capacity(pats.len());
+		for (index, pat) in pats.iter().enumerate() {
+			let mut qsbuf = [0u8; QS_BUF_LEN];
+			let len = prefix(pat, &mut qsbuf).len();
+			// Patterns without prefix are tried at every address
+			if len == 0 {
+				unanchored.push(index);
+			}
+			prefix_lens.push(len);
+			prefixes.push((index, qsbuf));
+		}
+		let ac = AhoCorasick::new(prefixes.iter().map(|&(index, ref qsbuf)| (index, &qsbuf[..prefix_lens[index]])));
+		let max_prefix = prefix_lens.iter().cloned().max().unwrap_or(0);
+		let ranges = ranges.into().0.into_iter();
+		let mut matches = MatchesMany { scanner: self, pats, range: 0..0, end: 0, ranges, max_prefix, ac, prefix_lens, unanchored, state: aho_corasick::ROOT, pending: !0, hits: 0 };
+		matches.next_range();
+		matches
 	}
 	/// Returns if the pattern matches the binary image at the given rva.
-	pub fn exec(self, pat: &[self::pat::Atom], mut cursor: Rva) -> Option<pat::Match> {
+	pub fn exec(self, pat: &[self::pat::Atom], cursor: Rva) -> Option<pat::Match> {
+		let mut result = pat::Match::default();
+		if self.exec_into(pat, cursor, result.as_mut()) { Some(result) } else { None }
+	}
+	/// Returns if the pattern matches the binary image at the given rva and saves to the buffer.
+	///
+	/// Save slots out of range of the buffer are ignored and checks against them fail.
+	pub fn exec_into(self, pat: &[pat::Atom], mut cursor: Rva, save: &mut [Rva]) -> bool {
 		let ptr_skip = mem::size_of::<Va>() as i8;
 		let mut stack = [0u32; pat::STACK_SIZE];
 		let mut sp = 0;
-		let mut result = pat::Match::default();
-		for &atom in pat {
-			match atom {
+		// Where to continue when an alternative fails
+		let mut cases = [(0usize, 0u32, 0usize); pat::STACK_SIZE];
+		let mut cp = 0;
+		let mut mask = 0xFF;
+		let mut pc = 0;
+		while let Some(&atom) = pat.get(pc) {
+			pc += 1;
+			let matched = match atom {
 				pat::Atom::Byte(byte) => {
-					if Ok(byte) != self.pe.derva_copy(cursor) {
-						return None;
+					let matched = self.read(cursor, 1).map(|value| value as u8 & mask == byte).unwrap_or(false);
+					mask = 0xFF;
+					// Failed reads may be at the end of the address space
+					if matched {
+						cursor += 1;
 					}
-					cursor += 1;
+					matched
+				},
+				pat::Atom::Fuzzy(fuzzy) => {
+					mask = fuzzy;
+					true
 				},
 				pat::Atom::Save(slot) => {
-					if slot < pat::MAX_SAVE as u8 {
-						result.as_mut()[slot as usize] = cursor;
-					}
+					store(save, slot, cursor);
+					true
 				},
 				pat::Atom::Push(skip) => {
 					if sp < pat::STACK_SIZE {
@@ -115,113 +391,192 @@ impl<'a, P: Pe<'a> + Copy> Scanner<P> {
 						stack[sp] = cursor.wrapping_add(skip as Rva);
 						sp += 1;
 					}
+					true
 				},
 				pat::Atom::Pop => {
 					if sp > 0 {
 						sp -= 1;
 						cursor = stack[sp];
 					}
+					true
 				},
 				pat::Atom::Skip(skip) => {
 					let skip = if skip == pat::PTR_SKIP { ptr_skip } else { skip };
 					cursor = cursor.wrapping_add(skip as Rva);
+					true
 				},
 				pat::Atom::Jump1 => {
-					if let Ok(sbyte) = self.pe.derva_copy::<i8>(cursor) {
-						cursor = cursor.wrapping_add(sbyte as Rva).wrapping_add(1);
+					if let Some(sbyte) = self.read(cursor, 1) {
+						cursor = cursor.wrapping_add(sbyte as i8 as Rva).wrapping_add(1);
+						true
 					}
 					else {
-						return None;
+						false
 					}
 				},
 				pat::Atom::Jump4 => {
-					if let Ok(sdword) = self.pe.derva_copy::<i32>(cursor) {
-						cursor = cursor.wrapping_add(sdword as Rva).wrapping_add(4);
+					if let Some(sdword) = self.read(cursor, 4) {
+						cursor = cursor.wrapping_add(sdword as i32 as Rva).wrapping_add(4);
+						true
 					}
 					else {
-						return None;
+						false
 					}
 				},
 				pat::Atom::Ptr => {
-					cursor = match self.pe.derva_copy(cursor).and_then(|va| self.pe.va_to_rva(va)) {
-						Ok(cursor) => cursor,
-						Err(_) => return None,
+					match self.read(cursor, mem::size_of::<Va>()).and_then(|va| self.memory.translate(va as Va)) {
+						Some(ptr) => {
+							cursor = ptr;
+							true
+						},
+						None => false,
+					}
+				},
+				pat::Atom::Case(next) => {
+					if cp < pat::STACK_SIZE {
+						cases[cp] = (pc - 1 + next as usize, cursor, sp);
+						cp += 1;
+					}
+					true
+				},
+				pat::Atom::Break(skip) => {
+					if cp > 0 {
+						cp -= 1;
+					}
+					pc += skip as usize;
+					true
+				},
+				pat::Atom::Many(limit) => {
+					// Take the mask and byte to repeat
+					let (fuzzy, byte, len) = match (pat.get(pc), pat.get(pc + 1)) {
+						(Some(&pat::Atom::Fuzzy(fuzzy)), Some(&pat::Atom::Byte(byte))) => (fuzzy, byte, 2),
+						(Some(&pat::Atom::Byte(byte)), _) => (0xFF, byte, 1),
+						_ => continue,
 					};
+					pc += len;
+					for _ in 0..limit {
+						match self.read(cursor, 1) {
+							Some(value) if value as u8 & fuzzy == byte => cursor += 1,
+							_ => break,
+						}
+					}
+					true
 				},
+				pat::Atom::ReadU8(slot) => self.read(cursor, 1).map(|value| { store(save, slot, value as u8 as u32); cursor += 1; }).is_some(),
+				pat::Atom::ReadI8(slot) => self.read(cursor, 1).map(|value| { store(save, slot, value as i8 as u32); cursor += 1; }).is_some(),
+				pat::Atom::ReadU16(slot) => self.read(cursor, 2).map(|value| { store(save, slot, value as u16 as u32); cursor += 2; }).is_some(),
+				pat::Atom::ReadI16(slot) => self.read(cursor, 2).map(|value| { store(save, slot, value as i16 as u32); cursor += 2; }).is_some(),
+				pat::Atom::ReadU32(slot) => self.read(cursor, 4).map(|value| { store(save, slot, value as u32); cursor += 4; }).is_some(),
+				pat::Atom::ReadI32(slot) => self.read(cursor, 4).map(|value| { store(save, slot, value as u32); cursor += 4; }).is_some(),
+				pat::Atom::ReadU64(slot) => {
+					self.read(cursor, 8).map(|value| {
+						store(save, slot, value as u32);
+						store(save, slot.wrapping_add(1), (value >> 32) as u32);
+						cursor += 8;
+					}).is_some()
+				},
+				pat::Atom::Check(slot) => save.get(slot as usize) == Some(&cursor),
+				pat::Atom::Aligned(align) => align < 32 && cursor & ((1 << align) - 1) == 0,
+			};
+			if !matched {
+				// Try the next alternative
+				if cp == 0 {
+					return false;
+				}
+				cp -= 1;
+				let (next, saved_cursor, saved_sp) = cases[cp];
+				pc = next;
+				cursor = saved_cursor;
+				sp = saved_sp;
+				mask = 0xFF;
 			}
 		}
 		// Pattern matches
-		Some(result)
-	}
-	// Invokes the callback for all sections overlapping the range.
-	// TODO! Specialze me for PeView?
-	fn sections<F>(&self, range: Range<Rva>, mut f: F) -> Option<pat::Match> where F: FnMut(Rva, &'a [u8]) -> Option<pat::Match> {
-		let image = self.pe.image();
-		for it in self.pe.section_headers() {
-			if range.start < (it.VirtualAddress + it.SizeOfRawData) && range.end >= it.VirtualAddress {
-				use std::cmp::{min, max};
-				let start = max(range.start, it.VirtualAddress);
-				let end = min(range.end, it.VirtualAddress + it.SizeOfRawData);
-				if let Some(slice) = image.get((start - it.VirtualAddress + it.PointerToRawData) as FileOffset..(end - it.VirtualAddress + it.PointerToRawData) as FileOffset) {
-					let m = f(start, slice);
-					if m.is_some() {
-						return m;
-					}
-				}
+		true
+	}
+	// Reads a little endian integer of the size at the address
+	fn read(self, address: Rva, size: usize) -> Option<u64> {
+		let bytes = self.memory.bytes(address);
+		if bytes.len() < size {
+			return None;
+		}
+		Some(bytes[..size].iter().rev().fold(0, |acc, &byte| acc << 8 | byte as u64))
+	}
+	// Invokes the callback for all the chunks of memory overlapping the range.
+	fn chunks<T, F>(&self, mut range: Range<Rva>, mut f: F) -> Option<T> where F: FnMut(Rva, &'a [u8]) -> Option<T> {
+		while let Some((start, slice)) = self.memory.chunk(range.clone()) {
+			if slice.len() == 0 {
+				break;
+			}
+			let m = f(start, slice);
+			if m.is_some() {
+				return m;
 			}
+			range.start = start + slice.len() as Rva;
 		}
 		None
 	}
 }
 
+// Saves the value in the slot, out of range slots are ignored
+fn store(save: &mut [Rva], slot: u8, value: u32) {
+	if let Some(dest) = save.get_mut(slot as usize) {
+		*dest = value;
+	}
+}
+
 //----------------------------------------------------------------
 
 /// An iterator over the matches of a pattern.
 ///
 /// Created with the method [`matches`](struct.Scanner.html#method.matches).
 #[derive(Clone)]
-pub struct Matches<'u, P> {
-	scanner: Scanner<P>,
+pub struct Matches<'u, M> {
+	scanner: Scanner<M>,
 	pat: &'u [pat::Atom],
 	range: Range<Rva>,
+	ranges: vec::IntoIter<Range<Rva>>,
 	/// Performance.
 	///
 	/// Number of times the slow [`exec`](struct.Scanner.html#method.exec) was invoked.
 	pub hits: u32,
 }
 
-impl<'a, 'u, P: Pe<'a> + Copy> Matches<'u, P> {
-	// Extract the prefix of bytes for optimizing the search
-	fn setup<'b>(&self, qsbuf: &'b mut [u8; QS_BUF_LEN]) -> &'b [u8] {
-		let mut qslen = 0usize;
-		for unit in self.pat {
-			match *unit {
-				pat::Atom::Byte(byte) => {
-					if qslen >= QS_BUF_LEN {
-						break;
-					}
-					qsbuf[qslen] = byte;
-					qslen += 1;
-				},
-				pat::Atom::Save(_) => {},
-				_ => break,
-			}
+// Extract the prefix of bytes for optimizing the search
+fn prefix<'b>(pat: &[pat::Atom], qsbuf: &'b mut [u8; QS_BUF_LEN]) -> &'b [u8] {
+	let mut qslen = 0usize;
+	for unit in pat {
+		match *unit {
+			pat::Atom::Byte(byte) => {
+				if qslen >= QS_BUF_LEN {
+					break;
+				}
+				qsbuf[qslen] = byte;
+				qslen += 1;
+			},
+			pat::Atom::Save(_) => {},
+			_ => break,
 		}
-		&qsbuf[..qslen]
+	}
+	&qsbuf[..qslen]
+}
+
+impl<'a, 'u, M: Memory<'a> + Copy> Matches<'u, M> {
+	fn setup<'b>(&self, qsbuf: &'b mut [u8; QS_BUF_LEN]) -> &'b [u8] {
+		prefix(self.pat, qsbuf)
 	}
 	// Strategy:
 	//  Cannot optimize the search, just brute-force it.
 	//  Note that this is (relatively) slow...
-	fn strategy0(&mut self, _qsbuf: &[u8]) -> Option<pat::Match> {
+	fn strategy0(&mut self, _qsbuf: &[u8], save: &mut [Rva]) -> Option<Rva> {
 		let scanner = self.scanner;
-		scanner.sections(self.range.clone(), |mut it, slice| {
+		scanner.chunks(self.range.clone(), |mut it, slice| {
 			let end = it + slice.len() as Rva;
 			while it < end {
 				self.hits += 1;
-				let m = scanner.exec(self.pat, it);
-				if m.is_some() {
+				if scanner.exec_into(self.pat, it, save) {
 					self.range.start = it + 1;
-					return m;
+					return Some(it);
 				}
 				it += 1;
 			}
@@ -230,82 +585,175 @@ impl<'a, 'u, P: Pe<'a> + Copy> Matches<'u, P> {
 		})
 	}
 	// Strategy:
-	//  Prefix is too small for full blown quicksearch.
-	//  Memchr for the first byte and only eval pattern on potential matches.
-	fn strategy1(&mut self, qsbuf: &[u8]) -> Option<pat::Match> {
-		let byte = qsbuf[0];
+	//  Vectorized search for the rarest pair of bytes in the prefix.
+	//  Only eval pattern on the places where the whole prefix matches.
+	fn strategy1(&mut self, qsbuf: &[u8], save: &mut [Rva]) -> Option<Rva> {
+		let finder = Finder::new(qsbuf);
 		let scanner = self.scanner;
-		scanner.sections(self.range.clone(), |it, slice| {
-			// Find all places with matching byte
-			// TODO! Replace with actual memchr
-			for cursor in slice.iter().enumerate().filter_map(|(i, &a)| if a == byte { Some(it + i as Rva) } else { None }) {
+		scanner.chunks(self.range.clone(), |it, slice| {
+			let mut i = 0;
+			while let Some(pos) = finder.find(&slice[i..]) {
 				self.hits += 1;
-				let m = scanner.exec(self.pat, cursor);
-				if m.is_some() {
+				let cursor = it + (i + pos) as Rva;
+				if scanner.exec_into(self.pat, cursor, save) {
 					self.range.start = cursor + 1;
-					return m;
+					return Some(cursor);
 				}
+				i += pos + 1;
 			}
-			self.range.start = it + slice.len() as Rva;
-			None
-		})
-	}
-	// Strategy:
-	//  Full blown quicksearch for the prefix.
-	//  Most likely completely unnecessary but oh well... it was fun to write!
-	fn strategy2(&mut self, qsbuf: &[u8]) -> Option<pat::Match> {
-		// Initialize jump table for quicksearch
-		let qslen = qsbuf.len();
-		let mut jumps = [qslen as u8; 256];
-		for i in 0..qslen - 1 {
-			jumps[qsbuf[i] as usize] = qslen as u8 - i as u8 - 1;
-		}
-		let jumps = jumps;
-		let scanner = self.scanner;
-		scanner.sections(self.range.clone(), |it, slice| {
-			// Quicksearch baby!
-			let mut i = 0;
-			while i + qslen <= slice.len() {
-				let tbuf = &slice[i..i + qslen];
-				let last = tbuf[qslen - 1];
-				let jump = jumps[last as usize] as Rva;
-				if qsbuf[qslen - 1] == last && tbuf == qsbuf {
-					self.hits += 1;
-					let cursor = it + i as Rva;
-					let m = scanner.exec(self.pat, cursor);
-					if m.is_some() {
-						self.range.start = cursor + jump;
-						return m;
-					}
+			// The prefix of the matches starting in the last few bytes extends past the end of the range
+			for i in cmp::max(i, (slice.len() + 1).saturating_sub(qsbuf.len()))..slice.len() {
+				self.hits += 1;
+				let cursor = it + i as Rva;
+				if scanner.exec_into(self.pat, cursor, save) {
+					self.range.start = cursor + 1;
+					return Some(cursor);
 				}
-				i += jump as usize;
 			}
-			// FIXME! Quicksearch stops too soon!
-			// It assumes there can't be another match in the last `qsbuf.len()` bytes
-			// Even though there clearly can since the scan range can be artificially limited
-			// For now let's ignore this edge case...
 			self.range.start = it + slice.len() as Rva;
 			None
 		})
 	}
+	// Finds the next match and returns where it starts
+	fn next_cursor(&mut self, save: &mut [Rva]) -> Option<Rva> {
+		// Build the prefix buffer
+		let mut qsbuf = [0u8; QS_BUF_LEN];
+		let qsbuf = self.setup(&mut qsbuf);
+
+		loop {
+			// Select search strategy
+			let cursor = if qsbuf.len() == 0 {
+				self.strategy0(qsbuf, save)
+			}
+			else {
+				self.strategy1(qsbuf, save)
+			};
+			if cursor.is_some() {
+				return cursor;
+			}
+			// Continue with the next range
+			self.range = self.ranges.next()?;
+		}
+	}
+	/// Finds the next match and saves to the buffer.
+	///
+	/// Same as [`next`](#method.next) but the save slots are written to a buffer of any size instead of a [`Match`](../../pattern/struct.Match.html).
+	pub fn next_into(&mut self, save: &mut [Rva]) -> bool {
+		self.next_cursor(save).is_some()
+	}
 }
-impl<'a, 'u, P: Pe<'a> + Copy> Iterator for Matches<'u, P> {
+impl<'a, 'u, M: Memory<'a> + Copy> Iterator for Matches<'u, M> {
 	type Item = pat::Match;
 	fn next(&mut self) -> Option<pat::Match> {
-		// Build the quicksearch buffer
-		let mut qsbuf = [0u8; QS_BUF_LEN];
-		let qsbuf = self.setup(&mut qsbuf);
+		let mut result = pat::Match::default();
+		self.next_cursor(result.as_mut()).map(|_| result)
+	}
+}
+
+//----------------------------------------------------------------
+
+/// An iterator over the matches of many patterns.
+///
+/// Created with the method [`matches_many`](struct.Scanner.html#method.matches_many).
+pub struct MatchesMany<'u, M> {
+	scanner: Scanner<M>,
+	pats: &'u [&'u [pat::Atom]],
+	// The current range extended by the longest prefix
+	range: Range<Rva>,
+	// End of the current range, the matches start before it
+	end: Rva,
+	ranges: vec::IntoIter<Range<Rva>>,
+	max_prefix: usize,
+	ac: AhoCorasick,
+	prefix_lens: Vec<usize>,
+	unanchored: Vec<usize>,
+	// Automaton state after the bytes before `range.start`
+	state: u32,
+	// Next candidate to try for the byte before `range.start`
+	pending: usize,
+	/// Performance.
+	///
+	/// Number of times the slow [`exec`](struct.Scanner.html#method.exec) was invoked.
+	pub hits: u32,
+}
 
-		// Select search strategy
-		// FIXME! Profile the performance!
-		if qsbuf.len() == 0 {
-			self.strategy0(qsbuf)
+impl<'a, 'u, M: Memory<'a> + Copy> MatchesMany<'u, M> {
+	// Moves to the next range, the bytes past its end are scanned for the prefixes of matches starting in it
+	fn next_range(&mut self) -> bool {
+		match self.ranges.next() {
+			Some(range) => {
+				self.end = range.end;
+				self.range = range.start..range.end.saturating_add(self.max_prefix.saturating_sub(1) as Rva);
+				self.state = aho_corasick::ROOT;
+				self.pending = !0;
+				true
+			},
+			None => false,
 		}
-		else if qsbuf.len() < 4 {
-			self.strategy1(qsbuf)
+	}
+	// Tries the patterns whose prefix ends at the byte before `range.start` and those without prefix
+	fn candidates(&mut self, save: &mut [Rva]) -> Option<usize> {
+		let outputs = self.ac.outputs(self.state);
+		while self.pending < outputs.len() + self.unanchored.len() {
+			let (index, cursor) = if self.pending < outputs.len() {
+				let index = outputs[self.pending];
+				(index, self.range.start - self.prefix_lens[index] as Rva)
+			}
+			else {
+				(self.unanchored[self.pending - outputs.len()], self.range.start - 1)
+			};
+			self.pending += 1;
+			if cursor >= self.end {
+				continue;
+			}
+			self.hits += 1;
+			if self.scanner.exec_into(self.pats[index], cursor, save) {
+				return Some(index);
+			}
 		}
-		else {
-			self.strategy2(qsbuf)
+		None
+	}
+	/// Finds the next match and saves to the buffer.
+	///
+	/// Same as [`next`](#method.next) but the save slots are written to a buffer of any size instead of a [`Match`](../../pattern/struct.Match.html).
+	/// Returns the index of the matching pattern.
+	pub fn next_into(&mut self, save: &mut [Rva]) -> Option<usize> {
+		loop {
+			// Finish the candidates of the last byte scanned
+			if let Some(index) = self.candidates(save) {
+				return Some(index);
+			}
+			let scanner = self.scanner;
+			let found = scanner.chunks(self.range.clone(), |it, slice| {
+				// Only resume the automaton where it left off, prefixes don't span gaps between chunks
+				if it != self.range.start {
+					self.range.start = it;
+					self.state = aho_corasick::ROOT;
+					self.pending = !0;
+				}
+				for (i, &byte) in slice.iter().enumerate() {
+					self.state = self.ac.next(self.state, byte);
+					self.pending = 0;
+					self.range.start = it + i as Rva + 1;
+					if let Some(index) = self.candidates(save) {
+						return Some(index);
+					}
+				}
+				None
+			});
+			if found.is_some() {
+				return found;
+			}
+			if !self.next_range() {
+				return None;
+			}
 		}
 	}
 }
+impl<'a, 'u, M: Memory<'a> + Copy> Iterator for MatchesMany<'u, M> {
+	type Item = (usize, pat::Match);
+	fn next(&mut self) -> Option<(usize, pat::Match)> {
+		let mut result = pat::Match::default();
+		self.next_into(result.as_mut()).map(|index| (index, result))
+	}
+}
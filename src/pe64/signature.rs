@@ -0,0 +1,244 @@
+/*!
+Signature Generator.
+
+Creates patterns which uniquely identify an address, see the [`pattern`](../../pattern/index.html) module for more information about patterns.
+
+The generator grows a pattern from the bytes at the address until the [scanner](../scanner/index.html) finds a unique match.
+Bytes which change when the program is rebuilt or relocated are replaced by wildcards:
+bytes fixed up by the [base relocations](../base_relocs/index.html) and the 4 byte relative offsets of calls, jumps and RIP-relative operands.
+
+Relative offsets are found without disassembling, a few extra bytes may be wildcards but the pattern still matches the address.
+
+# Examples
+
+```
+# #![allow(unused_variables)]
+use pelite::pe64::{Pe, PeFile};
+use pelite::pe64::signature::Generator;
+use pelite::pattern;
+
+# #[allow(dead_code)]
+fn example(file: PeFile, rva: u32) {
+	let generator = Generator::new(file.scanner());
+
+	// Signature for the code at the address
+	if let Some(pat) = generator.generate(rva) {
+		println!("{}", pattern::display(&pat));
+	}
+
+	// Signature for a unique reference to the address, the address is saved in `Match.1`
+	if let Some(pat) = generator.generate_xref(rva) {
+		println!("{}", pattern::display(&pat));
+	}
+}
+```
+*/
+
+use std::{cmp, mem};
+
+use pattern as pat;
+
+use super::{Rva, Pe};
+use super::image::*;
+use super::scanner::{Memory, Ranges, Scanner};
+
+/// Bytes before the address inspected for relative offsets overlapping it.
+const LOOKBEHIND: Rva = 6;
+/// Bytes before a relative offset tried as the start of a reference.
+const MAX_OPCODE: Rva = 3;
+
+//----------------------------------------------------------------
+
+/// Signature generator.
+#[derive(Clone)]
+pub struct Generator<P> {
+	scanner: Scanner<P>,
+	ranges: Ranges,
+	max_len: usize,
+	// Relocated rva and size sorted by rva
+	relocs: Vec<(Rva, Rva)>,
+}
+impl<'a, P: Pe<'a> + Copy> Generator<P> {
+	/// Creates a generator searching the executable sections for signatures of up to 64 bytes.
+	pub fn new(scanner: Scanner<P>) -> Generator<P> {
+		let pe = scanner.pe();
+		let mut relocs = Vec::new();
+		if let Ok(base_relocs) = pe.base_relocs() {
+			for block in base_relocs {
+				for tyoff in block.words() {
+					let size = match block.type_of(tyoff) {
+						IMAGE_REL_BASED_HIGH | IMAGE_REL_BASED_LOW => 2,
+						IMAGE_REL_BASED_HIGHLOW => 4,
+						IMAGE_REL_BASED_DIR64 => 8,
+						_ => continue,
+					};
+					relocs.push((block.rva_of(tyoff), size));
+				}
+			}
+		}
+		relocs.sort();
+		let ranges = scanner.code_ranges();
+		Generator { scanner, ranges, max_len: 64, relocs }
+	}
+	/// Sets the ranges in which the signatures must be unique.
+	pub fn ranges<R: Into<Ranges>>(mut self, ranges: R) -> Generator<P> {
+		self.ranges = ranges.into();
+		self
+	}
+	/// Sets the max number of bytes following the address to match.
+	pub fn max_len(mut self, max_len: usize) -> Generator<P> {
+		self.max_len = max_len;
+		self
+	}
+	/// Generates the shortest pattern starting at the rva which uniquely matches it.
+	///
+	/// Returns `None` if the rva isn't in the ranges or no unique pattern within the max length exists.
+	pub fn generate(&self, rva: Rva) -> Option<pat::Pattern> {
+		let tail = self.bytes(rva, self.max_len);
+		self.grow(vec![pat::Atom::Save(0)], &tail, |m| m.0 == rva)
+	}
+	/// Generates the shortest pattern uniquely matching a reference to the rva.
+	///
+	/// References are 4 byte relative offsets to the rva such as calls, jumps and RIP-relative operands.
+	/// The pattern follows the reference with `${'}` saving the rva in `Match.1`.
+	///
+	/// Returns `None` if there are no references in the ranges or none of them has a unique pattern.
+	pub fn generate_xref(&self, rva: Rva) -> Option<pat::Pattern> {
+		let mut best: Option<pat::Pattern> = None;
+		for site in self.xrefs(rva) {
+			let tail = self.bytes(site + 4, self.max_len);
+			for back in 1..MAX_OPCODE + 1 {
+				if back > site {
+					break;
+				}
+				let start = site - back;
+				let mut head = vec![pat::Atom::Save(0)];
+				push(&mut head, &self.bytes(start, back as usize));
+				// Only whole opcodes make sensible references
+				if head.len() == 1 || head[1] == pat::Atom::Skip(back as i8) {
+					continue;
+				}
+				head.extend_from_slice(&[pat::Atom::Push(4), pat::Atom::Jump4, pat::Atom::Save(1), pat::Atom::Pop]);
+				if let Some(pat) = self.grow(head, &tail, |m| m.0 == start && m.1 == rva) {
+					if best.as_ref().map(|best| pat.len() < best.len()).unwrap_or(true) {
+						best = Some(pat);
+					}
+				}
+			}
+		}
+		best
+	}
+	// Appends the bytes to the head until the pattern uniquely matches
+	fn grow<F: Fn(&pat::Match) -> bool>(&self, head: pat::Pattern, tail: &[Option<u8>], check: F) -> Option<pat::Pattern> {
+		for len in 0..tail.len() + 1 {
+			// Grow one concrete byte at a time
+			if len > 0 && tail[len - 1].is_none() {
+				continue;
+			}
+			let mut pat = head.clone();
+			push(&mut pat, &tail[..len]);
+			while let Some(&pat::Atom::Pop) = pat.last() {
+				pat.pop();
+			}
+			let mut matches = self.scanner.matches(&pat, self.ranges.clone());
+			let m = matches.next()?;
+			if matches.next().is_none() {
+				return if check(&m) { Some(pat) } else { None };
+			}
+		}
+		None
+	}
+	// Finds the relative offsets referencing the rva
+	fn xrefs(&self, rva: Rva) -> Vec<Rva> {
+		let pe = self.scanner.pe();
+		let mut sites = Vec::new();
+		for range in self.ranges.as_slice() {
+			let mut start = range.start;
+			while let Some((it, bytes)) = pe.chunk(start..range.end) {
+				for (i, window) in bytes.windows(4).enumerate() {
+					let offset = window[0] as u32 | (window[1] as u32) << 8 | (window[2] as u32) << 16 | (window[3] as u32) << 24;
+					let site = it + i as Rva;
+					if site.wrapping_add(4).wrapping_add(offset) == rva && !self.relocated(site, 4) {
+						sites.push(site);
+					}
+				}
+				if bytes.is_empty() {
+					break;
+				}
+				start = it + bytes.len() as Rva;
+			}
+		}
+		sites
+	}
+	// Returns if any of the bytes are relocated
+	fn relocated(&self, rva: Rva, len: Rva) -> bool {
+		// Relocations are at most 8 bytes
+		let i = match self.relocs.binary_search(&(rva.saturating_sub(7), 0)) { Ok(i) | Err(i) => i };
+		self.relocs[i..].iter().take_while(|&&(reloc, _)| reloc < rva.saturating_add(len)).any(|&(reloc, size)| reloc.saturating_add(size) > rva)
+	}
+	// Reads the bytes at the rva, the bytes which may change are wildcards
+	fn bytes(&self, rva: Rva, len: usize) -> Vec<Option<u8>> {
+		let pe = self.scanner.pe();
+		// Look behind for relative offsets overlapping the rva but not past the start of its section
+		let section = pe.section_headers().iter().find(|it| rva >= it.VirtualAddress && rva < it.VirtualAddress.saturating_add(cmp::max(it.VirtualSize, it.SizeOfRawData)));
+		let start = match section {
+			Some(section) => cmp::max(rva.saturating_sub(LOOKBEHIND), section.VirtualAddress),
+			None => rva,
+		};
+		let slice = match pe.slice_bytes(start) {
+			Ok(slice) => slice,
+			Err(_) => return Vec::new(),
+		};
+		let offset = (rva - start) as usize;
+		let end = cmp::min(slice.len(), offset + len);
+		if end <= offset {
+			return Vec::new();
+		}
+		let slice = &slice[..end];
+		let mut bytes: Vec<Option<u8>> = slice.iter().map(|&byte| Some(byte)).collect();
+		let x64 = mem::size_of::<Va>() == 8;
+		for i in 0..end {
+			let at = start + i as Rva;
+			if self.relocated(at, 1) {
+				bytes[i] = None;
+			}
+			// Guess the relative offsets: call, jmp, jcc and RIP-relative ModRM
+			let disp = match (slice[i], slice.get(i + 1)) {
+				(0xE8, _) | (0xE9, _) => i + 1,
+				(0x0F, Some(&(0x80...0x8F))) => i + 2,
+				(modrm, _) if x64 && modrm & 0xC7 == 0x05 => i + 1,
+				_ => continue,
+			};
+			if disp + 4 > end {
+				continue;
+			}
+			let window = &slice[disp..disp + 4];
+			let offset = window[0] as u32 | (window[1] as u32) << 8 | (window[2] as u32) << 16 | (window[3] as u32) << 24;
+			let target = (start + disp as Rva + 4).wrapping_add(offset);
+			if pe.derva_copy::<u8>(target).is_ok() {
+				for byte in &mut bytes[disp..disp + 4] {
+					*byte = None;
+				}
+			}
+		}
+		bytes.split_off(offset)
+	}
+}
+
+// Appends the bytes to the pattern with the wildcards as skips
+fn push(pat: &mut pat::Pattern, bytes: &[Option<u8>]) {
+	for &byte in bytes {
+		match byte {
+			Some(byte) => pat.push(pat::Atom::Byte(byte)),
+			None => {
+				if let Some(&mut pat::Atom::Skip(ref mut skip)) = pat.last_mut() {
+					if *skip < 127 {
+						*skip += 1;
+						continue;
+					}
+				}
+				pat.push(pat::Atom::Skip(1));
+			},
+		}
+	}
+}
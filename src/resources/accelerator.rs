@@ -0,0 +1,122 @@
+/*!
+Accelerator Tables.
+
+The `RT_ACCELERATOR` resources contain an array of `ACCELTABLEENTRY`, the last entry has the `0x80` flag set.
+*/
+
+use std::{fmt, mem, slice};
+
+use error::{Error, Result};
+use image::*;
+
+use super::DataEntry;
+
+//----------------------------------------------------------------
+
+/// Accelerator table.
+#[derive(Copy, Clone)]
+pub struct Accelerators<'a> {
+	image: &'a [ACCELTABLEENTRY],
+}
+
+impl<'a> Accelerators<'a> {
+	/// Interprets the bytes of an accelerator table resource.
+	///
+	/// Returns [`Err(Corrupt)`](../../enum.Error.html#variant.Corrupt) if the last entry isn't marked as such.
+	pub fn new(bytes: &'a [u8]) -> Result<Accelerators<'a>> {
+		let entries = unsafe {
+			slice::from_raw_parts(bytes.as_ptr() as *const ACCELTABLEENTRY, bytes.len() / mem::size_of::<ACCELTABLEENTRY>())
+		};
+		let len = entries.iter().position(|entry| entry.fFlags & 0x80 != 0).ok_or(Error::Corrupt)? + 1;
+		Ok(Accelerators { image: &entries[..len] })
+	}
+	/// Gets the underlying accelerator table entries.
+	pub fn image(&self) -> &'a [ACCELTABLEENTRY] {
+		self.image
+	}
+}
+
+impl<'a> DataEntry<'a> {
+	/// Interprets the data as an accelerator table.
+	pub fn accelerators(&self) -> Result<Accelerators<'a>> {
+		Accelerators::new(self.data()?)
+	}
+}
+
+//----------------------------------------------------------------
+// Formatting
+
+impl<'a> fmt::Debug for Accelerators<'a> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.write_str("Accelerators\n")?;
+		for entry in self.image {
+			f.write_str("  ")?;
+			if entry.fFlags & FCONTROL != 0 {
+				f.write_str("Ctrl+")?;
+			}
+			if entry.fFlags & FALT != 0 {
+				f.write_str("Alt+")?;
+			}
+			if entry.fFlags & FSHIFT != 0 {
+				f.write_str("Shift+")?;
+			}
+			let key = entry.wAnsi;
+			if entry.fFlags & FVIRTKEY != 0 {
+				write!(f, "VK_{:02X}", key)?;
+			}
+			else if key >= 0x20 && key < 0x7F {
+				write!(f, "'{}'", key as u8 as char)?;
+			}
+			else {
+				write!(f, "{:#X}", key)?;
+			}
+			write!(f, " -> {}\n", { entry.wId })?;
+		}
+		Ok(())
+	}
+}
+
+//----------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn bytes(words: &[u16]) -> &[u8] {
+		unsafe { ::std::slice::from_raw_parts(words.as_ptr() as *const u8, words.len() * 2) }
+	}
+
+	#[test]
+	fn end_flag() {
+		// Ctrl+'O' (100), Ctrl+Shift+VK_74 (101) is the last entry, followed by padding
+		let words = [
+			FCONTROL, 0x4F, 100, 0,
+			FCONTROL | FSHIFT | FVIRTKEY | 0x80, 0x74, 101, 0,
+			0, 0, 0, 0,
+		];
+		let accelerators = Accelerators::new(bytes(&words)).unwrap();
+		assert_eq!(accelerators.image().len(), 2);
+		assert_eq!({ accelerators.image()[1].wId }, 101);
+	}
+
+	#[test]
+	fn no_end_flag() {
+		let words = [
+			FCONTROL, 0x4F, 100, 0,
+			FALT, 0x0D, 101, 0,
+		];
+		assert_eq!(Accelerators::new(bytes(&words)).err(), Some(Error::Corrupt));
+		assert_eq!(Accelerators::new(&[]).err(), Some(Error::Corrupt));
+	}
+
+	#[test]
+	fn debug() {
+		let words = [
+			FCONTROL, 0x4F, 100, 0,
+			FCONTROL | FSHIFT | FVIRTKEY, 0x74, 101, 0,
+			FALT | 0x80, 0x0D, 102, 0,
+		];
+		let accelerators = Accelerators::new(bytes(&words)).unwrap();
+		assert_eq!(format!("{:?}", accelerators), "Accelerators\n  Ctrl+'O' -> 100\n  Ctrl+Shift+VK_74 -> 101\n  Alt+0xD -> 102\n");
+	}
+}
@@ -0,0 +1,241 @@
+/*!
+Bitmaps.
+
+The `RT_BITMAP` resources contain a device independent bitmap without its `BITMAPFILEHEADER`.
+It starts with a `BITMAPINFOHEADER` or one of its larger variants, followed by the color masks, the palette and the pixel data.
+
+# Examples
+
+```
+# #![allow(unused_variables)]
+use std::fs;
+use pelite::pe64::{Pe, PeFile};
+
+# #[allow(dead_code)]
+fn example(file: PeFile) -> Result<(), pelite::resources::FindError> {
+	let resources = file.resources()?;
+	let bitmap = resources.find_data("/Bitmaps/101/1033")?.bitmap()?;
+
+	// Save it as a .bmp file
+	let mut bmp = Vec::new();
+	bitmap.write(&mut bmp);
+	let _ = fs::write("101.bmp", &bmp);
+
+	Ok(())
+}
+```
+*/
+
+use std::{cmp, fmt, mem, slice};
+
+use error::{Error, Result};
+use image::*;
+
+use super::DataEntry;
+
+//----------------------------------------------------------------
+
+/// Size of the `BITMAPFILEHEADER`.
+pub const BITMAPFILEHEADER_SIZE: usize = 14;
+
+/// Device independent bitmap.
+#[derive(Copy, Clone)]
+pub struct Bitmap<'a> {
+	bytes: &'a [u8],
+	image: &'a BITMAPINFOHEADER,
+	palette: usize,
+	pixels: usize,
+	pixels_end: usize,
+	end: usize,
+}
+
+impl<'a> Bitmap<'a> {
+	/// Interprets the bytes of a bitmap resource.
+	///
+	/// Returns [`Err(BadMagic)`](../../enum.Error.html#variant.BadMagic) for unknown header sizes
+	/// and [`Err(Corrupt)`](../../enum.Error.html#variant.Corrupt) if the header is inconsistent with itself or the size of the resource.
+	pub fn new(bytes: &'a [u8]) -> Result<Bitmap<'a>> {
+		if bytes.len() < mem::size_of::<BITMAPINFOHEADER>() {
+			return Err(Error::OOB);
+		}
+		let image = unsafe { &*(bytes.as_ptr() as *const BITMAPINFOHEADER) };
+		// BITMAPINFOHEADER, BITMAPV2INFOHEADER, BITMAPV3INFOHEADER, BITMAPV4HEADER and BITMAPV5HEADER
+		let header_size = image.biSize as usize;
+		match header_size {
+			40 | 52 | 56 | 108 | 124 => (),
+			_ => return Err(Error::BadMagic),
+		}
+		if header_size > bytes.len() {
+			return Err(Error::Corrupt);
+		}
+		let compression = image.biCompression;
+		let bit_count = image.biBitCount as usize;
+		let compressed = compression == BI_JPEG || compression == BI_PNG;
+		let valid_bit_count = match bit_count {
+			0 => compressed,
+			1 | 4 | 8 | 16 | 24 | 32 => true,
+			_ => false,
+		};
+		if image.biWidth <= 0 || image.biHeight == 0 || image.biPlanes != 1 || !valid_bit_count {
+			return Err(Error::Corrupt);
+		}
+
+		// The masks follow the BITMAPINFOHEADER, the larger headers include them
+		let masks = match compression {
+			BI_BITFIELDS if header_size == 40 => 12,
+			BI_ALPHABITFIELDS if header_size == 40 => 16,
+			_ => 0,
+		};
+		// Palettes are required for up to 256 colors, optional otherwise
+		let colors = match (bit_count, image.biClrUsed as usize) {
+			(1...8, 0) => 1 << bit_count,
+			(1...8, used) if used > 1 << bit_count => return Err(Error::Corrupt),
+			(_, used) => used,
+		};
+		let palette = header_size + masks;
+		let pixels = usize::checked_mul(colors, 4).and_then(|size| size.checked_add(palette)).ok_or(Error::Overflow)?;
+
+		// Uncompressed pixel rows are padded to 32-bit
+		let size_image = match compression {
+			BI_RGB | BI_BITFIELDS | BI_ALPHABITFIELDS => {
+				let stride = (image.biWidth as usize).checked_mul(bit_count).ok_or(Error::Overflow)?.checked_add(31).ok_or(Error::Overflow)? / 32 * 4;
+				let size = stride.checked_mul((image.biHeight as i64).abs() as usize).ok_or(Error::Overflow)?;
+				if image.biSizeImage != 0 && (image.biSizeImage as usize) < size {
+					return Err(Error::Corrupt);
+				}
+				size
+			},
+			BI_RLE8 | BI_RLE4 | BI_JPEG | BI_PNG => {
+				// Compressed bitmaps must be bottom-up and have their size set
+				if image.biHeight < 0 || image.biSizeImage == 0 {
+					return Err(Error::Corrupt);
+				}
+				image.biSizeImage as usize
+			},
+			_ => return Err(Error::Corrupt),
+		};
+		let pixels_end = pixels.checked_add(size_image).ok_or(Error::Overflow)?;
+		let mut end = pixels_end;
+
+		// The embedded color profile may follow the pixel data
+		if header_size >= mem::size_of::<BITMAPV5HEADER>() {
+			let v5 = unsafe { &*(bytes.as_ptr() as *const BITMAPV5HEADER) };
+			if v5.bV5CSType == PROFILE_EMBEDDED {
+				let profile_end = (v5.bV5ProfileData as usize).checked_add(v5.bV5ProfileSize as usize).ok_or(Error::Overflow)?;
+				end = cmp::max(end, profile_end);
+			}
+		}
+		if end > bytes.len() {
+			return Err(Error::Corrupt);
+		}
+		Ok(Bitmap { bytes, image, palette, pixels, pixels_end, end })
+	}
+	/// Gets the underlying bitmap info header image.
+	pub fn image(&self) -> &'a BITMAPINFOHEADER {
+		self.image
+	}
+	/// Gets the underlying bitmap V5 header image if the header is large enough.
+	pub fn v5(&self) -> Option<&'a BITMAPV5HEADER> {
+		if self.image.biSize as usize >= mem::size_of::<BITMAPV5HEADER>() {
+			Some(unsafe { &*(self.bytes.as_ptr() as *const BITMAPV5HEADER) })
+		}
+		else {
+			None
+		}
+	}
+	/// Gets the palette as `RGBQUAD` blue, green, red and reserved bytes.
+	pub fn palette(&self) -> &'a [[u8; 4]] {
+		let bytes = &self.bytes[self.palette..self.pixels];
+		unsafe { slice::from_raw_parts(bytes.as_ptr() as *const [u8; 4], bytes.len() / 4) }
+	}
+	/// Gets the offset of the pixel data from the start of the header.
+	pub fn pixels_offset(&self) -> usize {
+		self.pixels
+	}
+	/// Gets the pixel data.
+	pub fn pixels(&self) -> &'a [u8] {
+		&self.bytes[self.pixels..self.pixels_end]
+	}
+	/// Writes the bitmap as a `.bmp` file.
+	pub fn write(&self, dest: &mut Vec<u8>) {
+		let size = (BITMAPFILEHEADER_SIZE + self.end) as u32;
+		let offset = (BITMAPFILEHEADER_SIZE + self.pixels) as u32;
+		dest.extend_from_slice(b"BM");
+		dest.extend_from_slice(&[size as u8, (size >> 8) as u8, (size >> 16) as u8, (size >> 24) as u8]);
+		dest.extend_from_slice(&[0, 0, 0, 0]);
+		dest.extend_from_slice(&[offset as u8, (offset >> 8) as u8, (offset >> 16) as u8, (offset >> 24) as u8]);
+		dest.extend_from_slice(&self.bytes[..self.end]);
+	}
+}
+
+impl<'a> DataEntry<'a> {
+	/// Interprets the data as a bitmap.
+	pub fn bitmap(&self) -> Result<Bitmap<'a>> {
+		Bitmap::new(self.data()?)
+	}
+}
+
+//----------------------------------------------------------------
+// Formatting
+
+impl<'a> fmt::Debug for Bitmap<'a> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "Bitmap {}x{} {}bpp compression {} palette {} pixels at {:#X}\n",
+			{ self.image.biWidth }, { self.image.biHeight }, { self.image.biBitCount }, { self.image.biCompression },
+			self.palette().len(), self.pixels)
+	}
+}
+
+//----------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn header(bit_count: u16, clr_used: u32, size_image: u32) -> Vec<u8> {
+		let mut bytes = Vec::new();
+		for &dword in &[40u32, 3, 2] {
+			bytes.extend_from_slice(&[dword as u8, (dword >> 8) as u8, (dword >> 16) as u8, (dword >> 24) as u8]);
+		}
+		bytes.extend_from_slice(&[1, 0, bit_count as u8, 0]);
+		for &dword in &[BI_RGB, size_image, 0, 0, clr_used, 0] {
+			bytes.extend_from_slice(&[dword as u8, (dword >> 8) as u8, (dword >> 16) as u8, (dword >> 24) as u8]);
+		}
+		bytes
+	}
+
+	#[test]
+	fn palette() {
+		// 3x2 pixels at 8bpp with a palette of 2 colors, rows are padded to 4 bytes
+		let mut bytes = header(8, 2, 0);
+		bytes.extend_from_slice(&[0, 0, 0, 0, 0xFF, 0xFF, 0xFF, 0]);
+		bytes.extend_from_slice(&[0, 1, 0, 0, 1, 0, 1, 0]);
+		let bitmap = Bitmap::new(&bytes).unwrap();
+		assert_eq!(bitmap.palette().len(), 2);
+		assert_eq!(bitmap.pixels_offset(), 48);
+		assert_eq!(bitmap.pixels(), &[0, 1, 0, 0, 1, 0, 1, 0]);
+
+		let mut bmp = Vec::new();
+		bitmap.write(&mut bmp);
+		assert_eq!(&bmp[..14], &[0x42, 0x4D, 70, 0, 0, 0, 0, 0, 0, 0, 62, 0, 0, 0]);
+		assert_eq!(&bmp[14..], &bytes[..]);
+	}
+
+	#[test]
+	fn inconsistent() {
+		// The pixel data is truncated
+		let mut bytes = header(24, 0, 0);
+		bytes.extend_from_slice(&[0; 12]);
+		assert_eq!(Bitmap::new(&bytes).err(), Some(Error::Corrupt));
+
+		// The image size is smaller than the pixel data
+		let mut bytes = header(24, 0, 12);
+		bytes.extend_from_slice(&[0; 24]);
+		assert_eq!(Bitmap::new(&bytes).err(), Some(Error::Corrupt));
+
+		// Too many colors for the bit count
+		let mut bytes = header(1, 3, 0);
+		bytes.extend_from_slice(&[0; 20]);
+		assert_eq!(Bitmap::new(&bytes).err(), Some(Error::Corrupt));
+	}
+}
@@ -0,0 +1,453 @@
+/*!
+Resource Builder.
+
+An owned resource tree which can be edited and serialized as the contents of a `.rsrc` section.
+
+The serialized layout follows the linker: the directory tables breadth first, the data entries, the string area with the names of the named entries and finally the resource data.
+Named entries are sorted before the id entries as required by the resource lookup.
+
+# Examples
+
+```
+# #![allow(unused_variables)]
+use pelite::pe64::{Pe, PeFile};
+use pelite::resources::build::{DirectoryBuf, DataBuf, NameBuf};
+use pelite::image::RT_MANIFEST;
+
+# #[allow(dead_code)]
+fn example(file: PeFile, manifest: &[u8]) -> pelite::Result<Vec<u8>> {
+	let mut root = DirectoryBuf::from_dir(file.resources()?.root()?)?;
+
+	// Replace the manifest
+	root.insert(NameBuf::Id(RT_MANIFEST as u32), NameBuf::Id(1), 1033, DataBuf::new(manifest.to_vec()))?;
+
+	// Serialize for a section at the same address
+	let rva = file.optional_header().DataDirectory[pelite::image::IMAGE_DIRECTORY_ENTRY_RESOURCE].VirtualAddress;
+	let mut rsrc = Vec::new();
+	root.write(rva, &mut rsrc)?;
+	Ok(rsrc)
+}
+```
+*/
+
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::mem;
+
+use error::{Error, Result};
+use image::*;
+
+use super::{Directory, Entry, Name, Visited, ascii_upper};
+
+// Alignment of the resource data
+const DATA_ALIGN: usize = 8;
+
+//----------------------------------------------------------------
+
+/// Owned resource name.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub enum NameBuf {
+	/// Resource ID.
+	Id(u32),
+	/// UTF-16 named resource, without length prefix.
+	Str(Vec<u16>),
+}
+impl<'a> From<Name<'a>> for NameBuf {
+	fn from(name: Name<'a>) -> NameBuf {
+		match name {
+			Name::Id(id) => NameBuf::Id(id),
+			Name::Str(s) => NameBuf::Str(s.to_vec()),
+		}
+	}
+}
+impl<'a> From<&'a str> for NameBuf {
+	fn from(name: &'a str) -> NameBuf {
+		NameBuf::Str(name.encode_utf16().collect())
+	}
+}
+impl From<u16> for NameBuf {
+	fn from(id: u16) -> NameBuf {
+		NameBuf::Id(id as u32)
+	}
+}
+// Named entries come first sorted ignoring ASCII case, then the id entries sorted by id
+impl Ord for NameBuf {
+	fn cmp(&self, other: &NameBuf) -> Ordering {
+		match (self, other) {
+			(&NameBuf::Str(ref lhs), &NameBuf::Str(ref rhs)) => {
+				let lhs_upper = lhs.iter().map(|&word| ascii_upper(word));
+				let rhs_upper = rhs.iter().map(|&word| ascii_upper(word));
+				lhs_upper.cmp(rhs_upper).then_with(|| lhs.cmp(rhs))
+			},
+			(&NameBuf::Str(_), &NameBuf::Id(_)) => Ordering::Less,
+			(&NameBuf::Id(_), &NameBuf::Str(_)) => Ordering::Greater,
+			(&NameBuf::Id(lhs), &NameBuf::Id(rhs)) => lhs.cmp(&rhs),
+		}
+	}
+}
+impl PartialOrd for NameBuf {
+	fn partial_cmp(&self, other: &NameBuf) -> Option<Ordering> {
+		Some(self.cmp(other))
+	}
+}
+
+//----------------------------------------------------------------
+
+/// Owned resource data.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct DataBuf {
+	/// The code page of the data, usually zero.
+	pub code_page: u32,
+	pub data: Vec<u8>,
+}
+impl DataBuf {
+	/// Creates resource data without code page.
+	pub fn new(data: Vec<u8>) -> DataBuf {
+		DataBuf { code_page: 0, data }
+	}
+}
+
+/// Owned directory or data entry.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum EntryBuf {
+	Directory(DirectoryBuf),
+	DataEntry(DataBuf),
+}
+
+/// Owned resource directory.
+///
+/// The root directory contains the resource types, followed by the resource names and their languages.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct DirectoryBuf {
+	pub characteristics: u32,
+	pub time_date_stamp: u32,
+	pub major_version: u16,
+	pub minor_version: u16,
+	pub entries: BTreeMap<NameBuf, EntryBuf>,
+}
+
+impl DirectoryBuf {
+	/// Creates an empty directory.
+	pub fn new() -> DirectoryBuf {
+		DirectoryBuf::default()
+	}
+	/// Copies the directory and all its children.
+	///
+	/// Returns [`Err(Corrupt)`](../../enum.Error.html#variant.Corrupt) if a directory contains itself or the directories nest too deep.
+	/// Directories shared by several parents are copied for each of them.
+	pub fn from_dir(dir: Directory) -> Result<DirectoryBuf> {
+		let mut visited = Visited::new();
+		visited.enter(dir)?;
+		from_dir(dir, &mut visited)
+	}
+	/// Gets the resource data by its type, name and language.
+	pub fn get(&self, ty: &NameBuf, name: &NameBuf, lang: u16) -> Option<&DataBuf> {
+		match self.subdir(ty)?.subdir(name)?.entries.get(&NameBuf::Id(lang as u32))? {
+			&EntryBuf::DataEntry(ref data) => Some(data),
+			&EntryBuf::Directory(_) => None,
+		}
+	}
+	/// Gets the resource data mutably by its type, name and language.
+	pub fn get_mut(&mut self, ty: &NameBuf, name: &NameBuf, lang: u16) -> Option<&mut DataBuf> {
+		match self.subdir_mut(ty)?.subdir_mut(name)?.entries.get_mut(&NameBuf::Id(lang as u32))? {
+			&mut EntryBuf::DataEntry(ref mut data) => Some(data),
+			&mut EntryBuf::Directory(_) => None,
+		}
+	}
+	/// Adds or replaces the resource data by its type, name and language.
+	///
+	/// Returns the replaced resource data.
+	///
+	/// Returns [`Err(Corrupt)`](../../enum.Error.html#variant.Corrupt) if the type or name is a data entry or the language is a directory, the tree is left unchanged.
+	pub fn insert(&mut self, ty: NameBuf, name: NameBuf, lang: u16, data: DataBuf) -> Result<Option<DataBuf>> {
+		let langs = self.subdir_or_insert(ty)?.subdir_or_insert(name)?;
+		if let Some(&EntryBuf::Directory(_)) = langs.entries.get(&NameBuf::Id(lang as u32)) {
+			return Err(Error::Corrupt);
+		}
+		match langs.entries.insert(NameBuf::Id(lang as u32), EntryBuf::DataEntry(data)) {
+			Some(EntryBuf::DataEntry(data)) => Ok(Some(data)),
+			_ => Ok(None),
+		}
+	}
+	/// Removes the resource data by its type, name and language.
+	///
+	/// Directories left empty are removed as well.
+	pub fn remove(&mut self, ty: &NameBuf, name: &NameBuf, lang: u16) -> Option<DataBuf> {
+		let (data, names_empty) = {
+			let names = self.subdir_mut(ty)?;
+			let (data, langs_empty) = {
+				let langs = names.subdir_mut(name)?;
+				let data = match langs.entries.remove(&NameBuf::Id(lang as u32))? {
+					EntryBuf::DataEntry(data) => data,
+					entry => {
+						// Put back the unexpected directory
+						langs.entries.insert(NameBuf::Id(lang as u32), entry);
+						return None;
+					},
+				};
+				(data, langs.entries.is_empty())
+			};
+			if langs_empty {
+				names.entries.remove(name);
+			}
+			(data, names.entries.is_empty())
+		};
+		if names_empty {
+			self.entries.remove(ty);
+		}
+		Some(data)
+	}
+	fn subdir(&self, name: &NameBuf) -> Option<&DirectoryBuf> {
+		match self.entries.get(name)? {
+			&EntryBuf::Directory(ref dir) => Some(dir),
+			&EntryBuf::DataEntry(_) => None,
+		}
+	}
+	fn subdir_mut(&mut self, name: &NameBuf) -> Option<&mut DirectoryBuf> {
+		match self.entries.get_mut(name)? {
+			&mut EntryBuf::Directory(ref mut dir) => Some(dir),
+			&mut EntryBuf::DataEntry(_) => None,
+		}
+	}
+	fn subdir_or_insert(&mut self, name: NameBuf) -> Result<&mut DirectoryBuf> {
+		match *self.entries.entry(name).or_insert_with(|| EntryBuf::Directory(DirectoryBuf::new())) {
+			EntryBuf::Directory(ref mut dir) => Ok(dir),
+			EntryBuf::DataEntry(_) => Err(Error::Corrupt),
+		}
+	}
+	/// Serializes the resource tree as the contents of a `.rsrc` section.
+	///
+	/// The `OffsetToData` of the data entries are fixed up for the section starting at the given rva.
+	/// The serialized tree starts at the end of `dest` which is assumed to be aligned.
+	///
+	/// Returns [`Err(Overflow)`](../../enum.Error.html#variant.Overflow) if a directory has more than 65535 named or id entries or a name is longer than 65535 words, nothing is written.
+	pub fn write(&self, rva: u32, dest: &mut Vec<u8>) -> Result<()> {
+		// Collect the directories breadth first
+		let mut dirs = vec![self];
+		let mut i = 0;
+		while i < dirs.len() {
+			let dir = dirs[i];
+			dirs.extend(dir.entries.values().filter_map(|entry| match *entry {
+				EntryBuf::Directory(ref dir) => Some(dir),
+				EntryBuf::DataEntry(_) => None,
+			}));
+			i += 1;
+		}
+
+		// Lay out the directories, data entries, strings and data
+		let mut dir_offsets = Vec::with_capacity(dirs.len());
+		let mut offset = 0;
+		let mut data_entries_len = 0;
+		let mut strings_size = 0;
+		for dir in &dirs {
+			dir_offsets.push(offset);
+			let named = named_len(dir);
+			if named > 0xFFFF || dir.entries.len() - named > 0xFFFF {
+				return Err(Error::Overflow);
+			}
+			offset += mem::size_of::<IMAGE_RESOURCE_DIRECTORY>() + dir.entries.len() * mem::size_of::<IMAGE_RESOURCE_DIRECTORY_ENTRY>();
+			for (name, entry) in &dir.entries {
+				if let NameBuf::Str(ref s) = *name {
+					if s.len() > 0xFFFF {
+						return Err(Error::Overflow);
+					}
+					strings_size += 2 + s.len() * 2;
+				}
+				if let EntryBuf::DataEntry(_) = *entry {
+					data_entries_len += 1;
+				}
+			}
+		}
+		let data_entries_offset = offset;
+		let strings_offset = data_entries_offset + data_entries_len * mem::size_of::<IMAGE_RESOURCE_DATA_ENTRY>();
+		let data_offset = align(strings_offset + strings_size, DATA_ALIGN);
+
+		fn put16(dest: &mut Vec<u8>, value: u16) {
+			dest.extend_from_slice(&[value as u8, (value >> 8) as u8]);
+		}
+		fn put32(dest: &mut Vec<u8>, value: u32) {
+			put16(dest, value as u16);
+			put16(dest, (value >> 16) as u16);
+		}
+
+		// The directory tables, children are assigned in the same breadth first order
+		let mut next_dir = 1;
+		let mut datas = Vec::with_capacity(data_entries_len);
+		let mut strings = Vec::new();
+		let mut string_offset = strings_offset;
+		for dir in &dirs {
+			let named = named_len(dir);
+			put32(dest, dir.characteristics);
+			put32(dest, dir.time_date_stamp);
+			put16(dest, dir.major_version);
+			put16(dest, dir.minor_version);
+			put16(dest, named as u16);
+			put16(dest, (dir.entries.len() - named) as u16);
+			for (name, entry) in &dir.entries {
+				match *name {
+					NameBuf::Id(id) => put32(dest, id),
+					NameBuf::Str(ref s) => {
+						put32(dest, 0x80000000 | string_offset as u32);
+						string_offset += 2 + s.len() * 2;
+						strings.push(s);
+					},
+				}
+				match *entry {
+					EntryBuf::Directory(_) => {
+						put32(dest, 0x80000000 | dir_offsets[next_dir] as u32);
+						next_dir += 1;
+					},
+					EntryBuf::DataEntry(ref data) => {
+						put32(dest, (data_entries_offset + datas.len() * mem::size_of::<IMAGE_RESOURCE_DATA_ENTRY>()) as u32);
+						datas.push(data);
+					},
+				}
+			}
+		}
+
+		// The data entries point at the aligned data
+		let mut offset = data_offset;
+		for data in &datas {
+			put32(dest, rva.wrapping_add(offset as u32));
+			put32(dest, data.data.len() as u32);
+			put32(dest, data.code_page);
+			put32(dest, 0);
+			offset = align(offset + data.data.len(), DATA_ALIGN);
+		}
+
+		// The string area with length prefixed names
+		for s in strings {
+			put16(dest, s.len() as u16);
+			for &word in s {
+				put16(dest, word);
+			}
+		}
+
+		// The resource data
+		for data in &datas {
+			pad(dest, DATA_ALIGN);
+			dest.extend_from_slice(&data.data);
+		}
+		pad(dest, DATA_ALIGN);
+		Ok(())
+	}
+}
+
+// The named entries are sorted before the id entries
+fn named_len(dir: &DirectoryBuf) -> usize {
+	dir.entries.keys().take_while(|name| match **name { NameBuf::Str(_) => true, NameBuf::Id(_) => false }).count()
+}
+
+fn from_dir(dir: Directory, visited: &mut Visited) -> Result<DirectoryBuf> {
+	let image = dir.image();
+	let mut entries = BTreeMap::new();
+	for child in dir.entries() {
+		let entry = match child.entry()? {
+			Entry::Directory(dir) => {
+				visited.enter(dir)?;
+				let dir = from_dir(dir, visited)?;
+				visited.leave();
+				EntryBuf::Directory(dir)
+			},
+			Entry::DataEntry(data) => EntryBuf::DataEntry(DataBuf { code_page: data.image().CodePage, data: data.data()?.to_vec() }),
+		};
+		entries.insert(NameBuf::from(child.name()?), entry);
+	}
+	Ok(DirectoryBuf {
+		characteristics: image.Characteristics,
+		time_date_stamp: image.TimeDateStamp,
+		major_version: image.MajorVersion,
+		minor_version: image.MinorVersion,
+		entries,
+	})
+}
+
+fn align(offset: usize, align: usize) -> usize {
+	(offset + (align - 1)) & !(align - 1)
+}
+// Pads relative to the start of the serialized tree, which is assumed to be aligned
+fn pad(dest: &mut Vec<u8>, align: usize) {
+	while dest.len() & (align - 1) != 0 {
+		dest.push(0);
+	}
+}
+
+//----------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use super::super::Resources;
+
+	#[test]
+	fn roundtrip() {
+		let mut root = DirectoryBuf::new();
+		root.insert(NameBuf::from(RT_RCDATA), NameBuf::from(7), 1033, DataBuf::new(b"seven".to_vec())).unwrap();
+		root.insert(NameBuf::from(RT_RCDATA), NameBuf::from("b"), 1033, DataBuf::new(b"bee".to_vec())).unwrap();
+		root.insert(NameBuf::from(RT_RCDATA), NameBuf::from("A"), 2057, DataBuf::new(b"a".to_vec())).unwrap();
+		root.insert(NameBuf::from("CUSTOM"), NameBuf::from(1), 0, DataBuf::new(vec![1, 2, 3])).unwrap();
+		assert_eq!(root.insert(NameBuf::from(RT_RCDATA), NameBuf::from(7), 1033, DataBuf::new(b"7".to_vec())), Ok(Some(DataBuf::new(b"seven".to_vec()))));
+
+		let mut rsrc = Vec::new();
+		root.write(0x3000, &mut rsrc).unwrap();
+		assert_eq!(rsrc.len() % DATA_ALIGN, 0);
+
+		// Named entries come first and are sorted ignoring case
+		let resources = Resources::new(&rsrc, 0x3000);
+		let types = resources.root().unwrap();
+		assert_eq!({ types.image().NumberOfNamedEntries }, 1);
+		assert_eq!({ types.image().NumberOfIdEntries }, 1);
+		let names = resources.find_dir("/Raw Data").unwrap();
+		let names: Vec<_> = names.entries().map(|e| NameBuf::from(e.name().unwrap())).collect();
+		assert_eq!(names, [NameBuf::from("A"), NameBuf::from("b"), NameBuf::from(7)]);
+
+		// The data is aligned and found at the right rva
+		let entry = resources.find_data("/Raw Data/7/1033").unwrap();
+		assert_eq!({ entry.image().OffsetToData } % DATA_ALIGN as u32, 0);
+		assert_eq!(entry.data(), Ok(&b"7"[..]));
+		assert_eq!(resources.find_data("/CUSTOM/1/0").unwrap().data(), Ok(&[1, 2, 3][..]));
+
+		// Parse it back and remove everything
+		let mut copy = DirectoryBuf::from_dir(types).unwrap();
+		assert_eq!(copy, root);
+		assert_eq!(copy.remove(&NameBuf::from("custom"), &NameBuf::from(1), 0), None);
+		assert_eq!(copy.remove(&NameBuf::from("CUSTOM"), &NameBuf::from(1), 0), Some(DataBuf::new(vec![1, 2, 3])));
+		for &(name, lang) in &[("A", 2057), ("b", 1033)] {
+			assert!(copy.remove(&NameBuf::from(RT_RCDATA), &NameBuf::from(name), lang).is_some());
+		}
+		assert!(copy.remove(&NameBuf::from(RT_RCDATA), &NameBuf::from(7), 1033).is_some());
+		assert!(copy.entries.is_empty());
+	}
+
+	#[test]
+	fn errors() {
+		// Data entries aren't replaced by directories and vice versa
+		let mut root = DirectoryBuf::new();
+		root.entries.insert(NameBuf::from(RT_RCDATA), EntryBuf::DataEntry(DataBuf::new(vec![1])));
+		root.insert(NameBuf::from("CUSTOM"), NameBuf::from(1), 0, DataBuf::new(vec![2])).unwrap();
+		let copy = root.clone();
+		assert_eq!(root.insert(NameBuf::from(RT_RCDATA), NameBuf::from(1), 0, DataBuf::new(vec![3])), Err(Error::Corrupt));
+		assert_eq!(root.insert(NameBuf::from("CUSTOM"), NameBuf::from(1), 0, DataBuf::new(vec![3])), Ok(Some(DataBuf::new(vec![2]))));
+		root.subdir_mut(&NameBuf::from("CUSTOM")).unwrap().subdir_mut(&NameBuf::from(1)).unwrap()
+			.entries.insert(NameBuf::from(0), EntryBuf::Directory(DirectoryBuf::new()));
+		assert_eq!(root.insert(NameBuf::from("CUSTOM"), NameBuf::from(1), 0, DataBuf::new(vec![3])), Err(Error::Corrupt));
+		assert_eq!(root.entries[&NameBuf::from(RT_RCDATA)], copy.entries[&NameBuf::from(RT_RCDATA)]);
+
+		// The entry counts are 16-bit
+		let mut root = DirectoryBuf::new();
+		for id in 0..0x10000 {
+			root.entries.insert(NameBuf::Id(id), EntryBuf::DataEntry(DataBuf::default()));
+		}
+		let mut rsrc = Vec::new();
+		assert_eq!(root.write(0, &mut rsrc), Err(Error::Overflow));
+		assert!(rsrc.is_empty());
+		root.entries.remove(&NameBuf::Id(0));
+		root.entries.insert(NameBuf::from("A"), EntryBuf::DataEntry(DataBuf::default()));
+		assert_eq!(root.write(0, &mut rsrc), Ok(()));
+
+		// As are the name lengths
+		let mut root = DirectoryBuf::new();
+		root.entries.insert(NameBuf::Str(vec![0x41; 0x10000]), EntryBuf::DataEntry(DataBuf::default()));
+		assert_eq!(root.write(0, &mut rsrc), Err(Error::Overflow));
+	}
+}
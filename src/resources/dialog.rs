@@ -0,0 +1,258 @@
+/*!
+Dialog Templates.
+
+The `RT_DIALOG` resources contain either a `DLGTEMPLATE` or an extended `DLGTEMPLATEEX` followed by its controls.
+Both are decoded into the same structured values borrowing their strings from the resource data.
+
+# Examples
+
+```
+# #![allow(unused_variables)]
+use pelite::pe64::{Pe, PeFile};
+
+# #[allow(dead_code)]
+fn example(file: PeFile) -> Result<(), pelite::resources::FindError> {
+	let resources = file.resources()?;
+	let dialog = resources.find_data("/Dialogs/101/1033")?.dialog()?;
+
+	println!("{}", dialog.title);
+	for control in &dialog.controls {
+		println!("{:?} {:?} at {},{}", control.class, control.title, control.x, control.y);
+	}
+
+	Ok(())
+}
+```
+*/
+
+use error::Result;
+use image::*;
+use util::WideSlice;
+
+use super::DataEntry;
+use super::template::Words;
+
+//----------------------------------------------------------------
+
+/// Variable length field of a template, either absent, an ordinal or a string.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum SzOrOrd<'a> {
+	None,
+	Ord(u16),
+	Sz(&'a WideSlice),
+}
+
+impl<'a> SzOrOrd<'a> {
+	fn read(words: &mut Words<'a>) -> Result<SzOrOrd<'a>> {
+		match words.u16()? {
+			0x0000 => Ok(SzOrOrd::None),
+			0xFFFF => Ok(SzOrOrd::Ord(words.u16()?)),
+			_ => {
+				// The first word is the first character of the string
+				words.unread();
+				Ok(SzOrOrd::Sz(words.sz()?))
+			},
+		}
+	}
+}
+
+/// Dialog font, present if the `DS_SETFONT` style is set.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Font<'a> {
+	pub point_size: u16,
+	/// Font weight, only in extended templates.
+	pub weight: u16,
+	/// Italic font, only in extended templates.
+	pub italic: bool,
+	/// Character set, only in extended templates.
+	pub charset: u8,
+	pub typeface: &'a WideSlice,
+}
+
+/// Dialog control.
+#[derive(Clone, Debug)]
+pub struct Control<'a> {
+	/// Help context identifier, only in extended templates.
+	pub help_id: u32,
+	pub ex_style: u32,
+	pub style: u32,
+	pub x: i16,
+	pub y: i16,
+	pub cx: i16,
+	pub cy: i16,
+	/// Control identifier, extended templates have 32-bit identifiers.
+	pub id: u32,
+	/// Window class, predefined classes are ordinals eg. `0x0080` for buttons.
+	pub class: SzOrOrd<'a>,
+	/// Title or resource ordinal of the control.
+	pub title: SzOrOrd<'a>,
+	/// Creation data passed to the control.
+	pub extra: &'a [u8],
+}
+
+/// Dialog template.
+#[derive(Clone, Debug)]
+pub struct Dialog<'a> {
+	/// Decoded from a `DLGTEMPLATEEX`.
+	pub extended: bool,
+	/// Help context identifier, only in extended templates.
+	pub help_id: u32,
+	pub ex_style: u32,
+	pub style: u32,
+	pub x: i16,
+	pub y: i16,
+	pub cx: i16,
+	pub cy: i16,
+	pub menu: SzOrOrd<'a>,
+	pub class: SzOrOrd<'a>,
+	pub title: &'a WideSlice,
+	pub font: Option<Font<'a>>,
+	pub controls: Vec<Control<'a>>,
+}
+
+impl<'a> Dialog<'a> {
+	/// Decodes the bytes of a dialog resource.
+	pub fn new(bytes: &'a [u8]) -> Result<Dialog<'a>> {
+		let mut words = Words::new(bytes)?;
+		let dlg_ver = words.u16()?;
+		let signature = words.u16()?;
+		let extended = dlg_ver == 1 && signature == 0xFFFF;
+
+		let (help_id, ex_style, style) = if extended {
+			(words.u32()?, words.u32()?, words.u32()?)
+		}
+		else {
+			// Without signature the template starts with the style
+			let style = dlg_ver as u32 | (signature as u32) << 16;
+			(0, words.u32()?, style)
+		};
+		let count = words.u16()?;
+		let (x, y, cx, cy) = (words.i16()?, words.i16()?, words.i16()?, words.i16()?);
+		let menu = SzOrOrd::read(&mut words)?;
+		let class = SzOrOrd::read(&mut words)?;
+		let title = words.sz()?;
+
+		let font = if style & DS_SETFONT != 0 {
+			let point_size = words.u16()?;
+			let (weight, italic, charset) = if extended {
+				let weight = words.u16()?;
+				let italic_charset = words.u16()?;
+				(weight, italic_charset & 0xff != 0, (italic_charset >> 8) as u8)
+			}
+			else {
+				(0, false, 0)
+			};
+			let typeface = words.sz()?;
+			Some(Font { point_size, weight, italic, charset, typeface })
+		}
+		else {
+			None
+		};
+
+		let mut controls = Vec::new();
+		for _ in 0..count {
+			// Every control is aligned to 32-bit
+			words.align();
+			let (help_id, ex_style, style) = if extended {
+				(words.u32()?, words.u32()?, words.u32()?)
+			}
+			else {
+				let style = words.u32()?;
+				(0, words.u32()?, style)
+			};
+			let (x, y, cx, cy) = (words.i16()?, words.i16()?, words.i16()?, words.i16()?);
+			let id = if extended { words.u32()? } else { words.u16()? as u32 };
+			let class = SzOrOrd::read(&mut words)?;
+			let title = SzOrOrd::read(&mut words)?;
+			// The creation data size of standard templates includes the size word itself
+			let extra_len = match words.u16()? as usize {
+				len if !extended => len.saturating_sub(2),
+				len => len,
+			};
+			let extra = words.bytes(extra_len)?;
+			controls.push(Control { help_id, ex_style, style, x, y, cx, cy, id, class, title, extra });
+		}
+
+		Ok(Dialog { extended, help_id, ex_style, style, x, y, cx, cy, menu, class, title, font, controls })
+	}
+}
+
+impl<'a> DataEntry<'a> {
+	/// Interprets the data as a dialog template.
+	pub fn dialog(&self) -> Result<Dialog<'a>> {
+		Dialog::new(self.data()?)
+	}
+}
+
+//----------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn standard() {
+		let words: [u16; 33] = [
+			0x0040, 0x00C8, 0, 0, 1, 0, 0, 100, 50, 0, 0,
+			0x48, 0x69, 0,
+			8, 0x4D, 0x53, 0,
+			0x0001, 0x5000, 0, 0, 10, 10, 50, 14, 1,
+			0xFFFF, 0x0080,
+			0x4F, 0x4B, 0,
+			0,
+		];
+		let bytes = unsafe { ::std::slice::from_raw_parts(words.as_ptr() as *const u8, words.len() * 2) };
+		let dialog = Dialog::new(bytes).unwrap();
+		assert!(!dialog.extended);
+		assert_eq!(dialog.style, 0x00C80040);
+		assert_eq!((dialog.cx, dialog.cy), (100, 50));
+		assert_eq!(dialog.menu, SzOrOrd::None);
+		assert_eq!(dialog.title, "Hi");
+		assert_eq!(dialog.font.unwrap().typeface, "MS");
+		assert_eq!(dialog.controls.len(), 1);
+		let control = &dialog.controls[0];
+		assert_eq!(control.id, 1);
+		assert_eq!(control.class, SzOrOrd::Ord(0x0080));
+		match control.title {
+			SzOrOrd::Sz(title) => assert_eq!(title, "OK"),
+			_ => panic!(),
+		}
+		assert!(control.extra.is_empty());
+	}
+
+	#[test]
+	fn extended() {
+		let words: [u16; 62] = [
+			1, 0xFFFF, 7, 0, 0, 0, 0x0040, 0x00C8, 2, 0, 0, 200, 100,
+			0xFFFF, 101,
+			0,
+			0x54, 0,
+			9, 700, 0x0101, 0x4D, 0x53, 0,
+			0, 0, 0, 0, 0x0001, 0x5000, 10, 10, 50, 14, 2, 1,
+			0xFFFF, 0x0080,
+			0x4F, 0x4B, 0,
+			2, 0xABCD, 0,
+			0, 0, 0, 0, 0x0001, 0x5000, 10, 30, 50, 14, 3, 0,
+			0xFFFF, 0x0081,
+			0x4F, 0x4B, 0,
+			0,
+		];
+		let bytes = unsafe { ::std::slice::from_raw_parts(words.as_ptr() as *const u8, words.len() * 2) };
+		let dialog = Dialog::new(bytes).unwrap();
+		assert!(dialog.extended);
+		assert_eq!((dialog.help_id, dialog.style), (7, 0x00C80040));
+		assert_eq!(dialog.menu, SzOrOrd::Ord(101));
+		assert_eq!(dialog.class, SzOrOrd::None);
+		assert_eq!(dialog.title, "T");
+		let font = dialog.font.unwrap();
+		assert_eq!((font.point_size, font.weight, font.italic, font.charset), (9, 700, true, 1));
+		assert_eq!(font.typeface, "MS");
+		assert_eq!(dialog.controls.len(), 2);
+		assert_eq!(dialog.controls[0].id, 0x00010002);
+		assert_eq!(dialog.controls[0].extra, &[0xCD, 0xAB]);
+		assert_eq!(dialog.controls[1].id, 3);
+		assert_eq!(dialog.controls[1].class, SzOrOrd::Ord(0x0081));
+		// Equal titles compare equal regardless of the words preceding them
+		assert_eq!(dialog.controls[0].title, dialog.controls[1].title);
+	}
+}
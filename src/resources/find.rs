@@ -1,11 +1,33 @@
 /*!
 Resources Find API.
+
+Resources can be found by their path, eg. `/Manifest/2/1033`, or by their type, name and language with the fallback rules of `FindResourceEx`.
+
+# Examples
+
+```
+# #![allow(unused_variables)]
+use pelite::pe64::{Pe, PeFile};
+use pelite::resources::Name;
+
+# #[allow(dead_code)]
+fn example(file: PeFile) -> Result<(), pelite::resources::FindError> {
+	let resources = file.resources()?;
+
+	// Prefer the British English version info, falls back to any other language
+	let version_info = resources.find_resource(Name::RT_VERSION, Name::Id(1), Some(0x0809))?.version_info()?;
+
+	Ok(())
+}
+```
 */
 
 use std::{error, fmt, str};
 use std::path::Path;
 
-use super::{Resources, Directory, Entry, Name, DataEntry};
+use image::*;
+
+use super::{Resources, Directory, Entry, Name, DataEntry, ascii_upper};
 
 //------------------------------------------------
 
@@ -77,6 +99,20 @@ impl<'a> Resources<'a> {
 	pub fn find_dir<P: AsRef<Path> + ?Sized>(&self, path: &P) -> Result<Directory<'a>, FindError> {
 		self.find(path).and_then(|e| e.dir().ok_or(FindError::UnDataEntry))
 	}
+	/// Finds a resource by its type, name and language.
+	///
+	/// The type is one of the well-known `Name::RT_*` types or a custom type.
+	/// String names are compared ignoring ASCII case as the resource compiler stores them in upper case.
+	///
+	/// When the language isn't found the languages are tried in the order of `FindResourceEx`:
+	/// the primary language with `SUBLANG_NEUTRAL`, `LANG_NEUTRAL`, en-US and then the first available language.
+	/// The user's default language isn't known here, pass it explicitly to prefer it.
+	pub fn find_resource(&self, ty: Name, name: Name, lang: Option<u16>) -> Result<DataEntry<'a>, FindError> {
+		let ty_dir = (*self).root()?.find_name(ty)?.dir().ok_or(FindError::UnDataEntry)?;
+		let name_dir = ty_dir.find_name(name)?.dir().ok_or(FindError::UnDataEntry)?;
+		let entry = name_dir.find_lang(lang)?;
+		entry.data().ok_or(FindError::UnDirectory)
+	}
 	fn find_(&self, path: &Path) -> Result<Entry<'a>, FindError> {
 		let mut iter = path.iter();
 		if let Some(slash) = iter.next() {
@@ -109,6 +145,42 @@ impl<'a> Directory<'a> {
 	pub fn find_dir<P: AsRef<Path> + ?Sized>(&self, path: &P) -> Result<Directory<'a>, FindError> {
 		self.find(path).and_then(|e| e.dir().ok_or(FindError::UnDataEntry))
 	}
+	/// Finds a child entry by its name.
+	///
+	/// String names are compared ignoring ASCII case.
+	pub fn find_name(&self, name: Name) -> Result<Entry<'a>, FindError> {
+		for child in self.entries() {
+			let equal = match (child.name()?, name) {
+				(Name::Id(lhs), Name::Id(rhs)) => lhs == rhs,
+				(Name::Str(lhs), Name::Str(rhs)) => {
+					lhs.len() == rhs.len() && lhs.iter().zip(rhs.iter()).all(|(&lhs, &rhs)| ascii_upper(lhs) == ascii_upper(rhs))
+				},
+				_ => false,
+			};
+			if equal {
+				return Ok(child.entry()?);
+			}
+		}
+		Err(FindError::NotFound)
+	}
+	/// Finds a child entry by its language id with the fallback rules of `FindResourceEx`.
+	///
+	/// See [`Resources::find_resource`](struct.Resources.html#method.find_resource) for the order in which the languages are tried.
+	pub fn find_lang(&self, lang: Option<u16>) -> Result<Entry<'a>, FindError> {
+		let neutral = lang.map(|lang| lang & 0x3FF | SUBLANG_NEUTRAL << 10);
+		let fallbacks = [lang, neutral, Some(LANG_NEUTRAL), Some(LANG_ENGLISH | SUBLANG_ENGLISH_US << 10)];
+		for &lang in fallbacks.iter() {
+			if let Some(lang) = lang {
+				match self.find_name(Name::Id(lang as u32)) {
+					Err(FindError::NotFound) => (),
+					result => return result,
+				}
+			}
+		}
+		// Settle for any language
+		let child = self.entries().next().ok_or(FindError::NotFound)?;
+		Ok(child.entry()?)
+	}
 	fn find_(&self, path: &Path, id_names: &[Option<&str>]) -> Result<Entry<'a>, FindError> {
 		let mut e = Entry::Directory(*self);
 		'parts: for part in path {
@@ -0,0 +1,223 @@
+/*!
+Icon and Cursor Groups.
+
+An `RT_GROUP_ICON` resource describes the images of an icon, each image is a separate `RT_ICON` resource referenced by its id.
+Cursors work the same with `RT_GROUP_CURSOR` and `RT_CURSOR` resources, the cursor images are prefixed by their hotspot.
+
+The group is reassembled into an `.ico` or `.cur` file by rebuilding the icon directory with the offsets of the images in the file.
+PNG compressed images are stored as is, same as in `.ico` files.
+
+# Examples
+
+```
+# #![allow(unused_variables)]
+use std::fs;
+use pelite::pe64::{Pe, PeFile};
+
+# #[allow(dead_code)]
+fn example(file: PeFile) -> pelite::Result<()> {
+	let resources = file.resources()?;
+
+	// Extract every icon as an .ico file
+	for group in resources.group_icons()? {
+		let (name, group) = group?;
+		let mut ico = Vec::new();
+		group.write(&mut ico)?;
+		let _ = fs::write(format!("{:?}.ico", name), &ico);
+	}
+
+	Ok(())
+}
+```
+*/
+
+use std::{fmt, mem, slice};
+
+use error::{Error, Result};
+use image::*;
+
+use super::{Resources, DataEntry, Entries, Name};
+
+//----------------------------------------------------------------
+
+/// Icon or cursor group.
+#[derive(Copy, Clone)]
+pub struct GroupIcon<'a> {
+	resources: Resources<'a>,
+	image: &'a GRPICONDIR,
+	entries: &'a [GRPICONDIRENTRY],
+}
+
+impl<'a> GroupIcon<'a> {
+	/// Interprets the bytes of a group icon or group cursor resource.
+	///
+	/// The images are looked up in the given resources.
+	pub fn new(resources: Resources<'a>, bytes: &'a [u8]) -> Result<GroupIcon<'a>> {
+		if bytes.len() < mem::size_of::<GRPICONDIR>() {
+			return Err(Error::OOB);
+		}
+		let image = unsafe { &*(bytes.as_ptr() as *const GRPICONDIR) };
+		if image.idReserved != 0 || (image.idType != 1 && image.idType != 2) {
+			return Err(Error::BadMagic);
+		}
+		let len = image.idCount as usize;
+		let size = mem::size_of::<GRPICONDIR>() + len * mem::size_of::<GRPICONDIRENTRY>();
+		if bytes.len() < size {
+			return Err(Error::OOB);
+		}
+		let entries = unsafe { slice::from_raw_parts(bytes.as_ptr().offset(mem::size_of::<GRPICONDIR>() as isize) as *const GRPICONDIRENTRY, len) };
+		Ok(GroupIcon { resources, image, entries })
+	}
+	/// Gets the resources.
+	pub fn resources(&self) -> Resources<'a> {
+		self.resources
+	}
+	/// Gets the underlying group icon directory image.
+	pub fn image(&self) -> &'a GRPICONDIR {
+		self.image
+	}
+	/// Gets the group icon directory entries.
+	pub fn entries(&self) -> &'a [GRPICONDIRENTRY] {
+		self.entries
+	}
+	/// Returns if this is a cursor group.
+	pub fn is_cursor(&self) -> bool {
+		self.image.idType == 2
+	}
+	/// Gets the image data referenced by an entry.
+	///
+	/// Takes the first language of the `RT_ICON` or `RT_CURSOR` resource, cursor images start with their hotspot.
+	pub fn image_data(&self, entry: &GRPICONDIRENTRY) -> Result<&'a [u8]> {
+		let ty = if self.is_cursor() { RT_CURSOR } else { RT_ICON };
+		self.resources.first_data(ty, Some(entry.nId))?.data()
+	}
+	/// Writes the group as an `.ico` or `.cur` file.
+	pub fn write(&self, dest: &mut Vec<u8>) -> Result<()> {
+		// Collect the images first, any missing image fails before writing
+		let mut images = Vec::with_capacity(self.entries.len());
+		for entry in self.entries {
+			images.push(self.image_data(entry)?);
+		}
+
+		fn put16(dest: &mut Vec<u8>, value: u16) {
+			dest.extend_from_slice(&[value as u8, (value >> 8) as u8]);
+		}
+		fn put32(dest: &mut Vec<u8>, value: u32) {
+			put16(dest, value as u16);
+			put16(dest, (value >> 16) as u16);
+		}
+
+		// The icon directory
+		put16(dest, 0);
+		put16(dest, self.image.idType);
+		put16(dest, self.image.idCount);
+
+		// The icon directory entries point at the images following them
+		let mut offset = 6 + 16 * self.entries.len();
+		for (entry, image) in self.entries.iter().zip(&images) {
+			if self.is_cursor() {
+				if image.len() < 4 {
+					return Err(Error::Corrupt);
+				}
+				// Sizes of 256 are encoded as zero, the height includes the AND mask
+				let width = entry.bWidth as u16 | (entry.bHeight as u16) << 8;
+				let height = (entry.bColorCount as u16 | (entry.bReserved as u16) << 8) / 2;
+				dest.extend_from_slice(&[width as u8, height as u8, 0, 0]);
+				// The hotspot is moved from the image into the entry
+				dest.extend_from_slice(&image[..4]);
+				put32(dest, image.len() as u32 - 4);
+				put32(dest, offset as u32);
+				offset += image.len() - 4;
+			}
+			else {
+				dest.extend_from_slice(&[entry.bWidth, entry.bHeight, entry.bColorCount, entry.bReserved]);
+				put16(dest, entry.wPlanes);
+				put16(dest, entry.wBitCount);
+				put32(dest, image.len() as u32);
+				put32(dest, offset as u32);
+				offset += image.len();
+			}
+		}
+
+		// The images themselves
+		for image in &images {
+			dest.extend_from_slice(if self.is_cursor() { &image[4..] } else { image });
+		}
+		Ok(())
+	}
+}
+
+/// Returns if the image data is PNG compressed.
+pub fn is_png(image: &[u8]) -> bool {
+	image.starts_with(b"\x89PNG\r\n\x1a\n")
+}
+
+//----------------------------------------------------------------
+
+/// Iterator over the icon or cursor groups.
+///
+/// Takes the first language of every group.
+#[derive(Clone)]
+pub struct GroupIcons<'a> {
+	resources: Resources<'a>,
+	entries: Entries<'a>,
+}
+impl<'a> Iterator for GroupIcons<'a> {
+	type Item = Result<(Name<'a>, GroupIcon<'a>)>;
+	fn next(&mut self) -> Option<Result<(Name<'a>, GroupIcon<'a>)>> {
+		let resources = self.resources;
+		self.entries.next().map(|entry| {
+			let name = entry.name()?;
+			let dir = entry.entry()?.dir().ok_or(Error::Corrupt)?;
+			let lang = dir.entries().next().ok_or(Error::Null)?;
+			let data = lang.entry()?.data().ok_or(Error::Corrupt)?;
+			Ok((name, GroupIcon::new(resources, data.data()?)?))
+		})
+	}
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		self.entries.size_hint()
+	}
+}
+
+impl<'a> DataEntry<'a> {
+	/// Interprets the data as an icon or cursor group.
+	pub fn group_icon(&self) -> Result<GroupIcon<'a>> {
+		GroupIcon::new(self.resources(), self.data()?)
+	}
+}
+
+impl<'a> Resources<'a> {
+	/// Gets the icon groups.
+	///
+	/// Returns [`Err(Null)`](../../enum.Error.html#variant.Null) if there are no icons.
+	pub fn group_icons(&self) -> Result<GroupIcons<'a>> {
+		let entries = self.type_dir(RT_GROUP_ICON)?.entries();
+		Ok(GroupIcons { resources: *self, entries })
+	}
+	/// Gets the cursor groups.
+	///
+	/// Returns [`Err(Null)`](../../enum.Error.html#variant.Null) if there are no cursors.
+	pub fn group_cursors(&self) -> Result<GroupIcons<'a>> {
+		let entries = self.type_dir(RT_GROUP_CURSOR)?.entries();
+		Ok(GroupIcons { resources: *self, entries })
+	}
+}
+
+//----------------------------------------------------------------
+// Formatting
+
+impl<'a> fmt::Debug for GroupIcon<'a> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}\n", if self.is_cursor() { "Group Cursor" } else { "Group Icon" })?;
+		for entry in self.entries {
+			let (width, height) = if self.is_cursor() {
+				(entry.bWidth as u16 | (entry.bHeight as u16) << 8, (entry.bColorCount as u16 | (entry.bReserved as u16) << 8) / 2)
+			}
+			else {
+				(if entry.bWidth == 0 { 256 } else { entry.bWidth as u16 }, if entry.bHeight == 0 { 256 } else { entry.bHeight as u16 })
+			};
+			write!(f, "  {:>5}: {}x{} {}bpp {} bytes\n", { entry.nId }, width, height, { entry.wBitCount }, { entry.dwBytesInRes })?;
+		}
+		Ok(())
+	}
+}
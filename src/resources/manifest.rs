@@ -0,0 +1,365 @@
+/*!
+Application Manifest.
+
+The side-by-side assembly manifest is an XML document embedded as an `RT_MANIFEST` resource.
+Its id tells the loader how to use it:
+
+* `CREATEPROCESS_MANIFEST_RESOURCE_ID` (1) for executables.
+* `ISOLATIONAWARE_MANIFEST_RESOURCE_ID` (2) for DLLs.
+* `ISOLATIONAWARE_NOSTATICIMPORT_MANIFEST_RESOURCE_ID` (3) for DLLs not using static imports.
+
+The commonly needed fields are extracted with a minimal XML scanner.
+Attribute values and element text are returned verbatim, entity references are not decoded.
+
+# Examples
+
+```
+# #![allow(unused_variables)]
+use pelite::pe64::{Pe, PeFile};
+
+# #[allow(dead_code)]
+fn example(file: PeFile) -> pelite::Result<()> {
+	// Access the manifest in the resources
+	let resources = file.resources()?;
+	let manifest = resources.manifest()?;
+
+	// Print the requested execution level
+	if let Some(level) = manifest.requested_execution_level() {
+		println!("requestedExecutionLevel: {}", level);
+	}
+
+	// Print the dependent assemblies
+	for assembly in manifest.dependent_assemblies() {
+		println!("{} {}", assembly.name.unwrap_or(""), assembly.version.unwrap_or(""));
+	}
+
+	Ok(())
+}
+```
+*/
+
+use std::borrow::Cow;
+use std::{char, fmt, str};
+
+use error::{Error, Result};
+use image::*;
+
+use super::{Resources, DataEntry};
+
+//----------------------------------------------------------------
+
+/// Text encoding of the manifest.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Encoding {
+	/// UTF-8 without byte order mark.
+	Utf8,
+	/// UTF-8 with byte order mark.
+	Utf8Bom,
+	/// Little endian UTF-16, with or without byte order mark.
+	Utf16LE,
+	/// Big endian UTF-16 with byte order mark.
+	Utf16BE,
+}
+
+/// Identity of a dependent assembly.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct AssemblyIdentity<'s> {
+	pub type_: Option<&'s str>,
+	pub name: Option<&'s str>,
+	pub version: Option<&'s str>,
+	pub processor_architecture: Option<&'s str>,
+	pub public_key_token: Option<&'s str>,
+	pub language: Option<&'s str>,
+}
+
+//----------------------------------------------------------------
+
+/// Application manifest.
+#[derive(Clone)]
+pub struct Manifest<'a> {
+	encoding: Encoding,
+	text: Cow<'a, str>,
+}
+
+impl<'a> Manifest<'a> {
+	/// Decodes the bytes of the manifest resource.
+	///
+	/// Returns [`Err(Corrupt)`](../../enum.Error.html#variant.Corrupt) if the text isn't valid UTF-8 or UTF-16.
+	pub fn from_bytes(bytes: &'a [u8]) -> Result<Manifest<'a>> {
+		let (encoding, text) = if bytes.starts_with(&[0xEF, 0xBB, 0xBF]) {
+			(Encoding::Utf8Bom, Cow::Borrowed(str::from_utf8(&bytes[3..]).map_err(|_| Error::Corrupt)?))
+		}
+		else if bytes.starts_with(&[0xFF, 0xFE]) {
+			(Encoding::Utf16LE, Cow::Owned(decode_utf16(&bytes[2..], u16::from_le)?))
+		}
+		else if bytes.starts_with(&[0xFE, 0xFF]) {
+			(Encoding::Utf16BE, Cow::Owned(decode_utf16(&bytes[2..], u16::from_be)?))
+		}
+		// Without byte order mark the document starts with an ascii `<`
+		else if bytes.len() >= 2 && bytes[0] != 0 && bytes[1] == 0 {
+			(Encoding::Utf16LE, Cow::Owned(decode_utf16(bytes, u16::from_le)?))
+		}
+		else {
+			(Encoding::Utf8, Cow::Borrowed(str::from_utf8(bytes).map_err(|_| Error::Corrupt)?))
+		};
+		Ok(Manifest { encoding, text })
+	}
+	/// Gets the text encoding.
+	pub fn encoding(&self) -> Encoding {
+		self.encoding
+	}
+	/// Gets the decoded text.
+	///
+	/// Trailing nul characters some resource compilers pad the manifest with are stripped.
+	pub fn text(&self) -> &str {
+		self.text.trim_end_matches('\0')
+	}
+	/// Gets the `level` of the `requestedExecutionLevel`, eg. `asInvoker`, `highestAvailable` or `requireAdministrator`.
+	pub fn requested_execution_level(&self) -> Option<&str> {
+		self.find("requestedExecutionLevel").and_then(|tag| tag.attr("level"))
+	}
+	/// Gets the `uiAccess` of the `requestedExecutionLevel`.
+	pub fn ui_access(&self) -> Option<bool> {
+		self.find("requestedExecutionLevel").and_then(|tag| tag.attr("uiAccess")).and_then(parse_bool)
+	}
+	/// Gets the text of the legacy `dpiAware` setting, eg. `true` or `true/pm`.
+	pub fn dpi_aware(&self) -> Option<&str> {
+		self.find("dpiAware").map(|tag| tag.text)
+	}
+	/// Gets the text of the `dpiAwareness` setting, eg. `permonitorv2,permonitor`.
+	pub fn dpi_awareness(&self) -> Option<&str> {
+		self.find("dpiAwareness").map(|tag| tag.text)
+	}
+	/// Gets the `longPathAware` setting.
+	pub fn long_path_aware(&self) -> Option<bool> {
+		self.find("longPathAware").and_then(|tag| parse_bool(tag.text))
+	}
+	/// Gets the GUIDs of the `supportedOS` compatibility entries, eg. `{8e0f7a12-bfb3-4fe8-b9a5-48fd50a15a9a}` for Windows 10.
+	pub fn supported_os(&self) -> Vec<&str> {
+		self.tags()
+			.filter(|tag| !tag.end && tag.name == "supportedOS")
+			.filter_map(|tag| tag.attr("Id"))
+			.collect()
+	}
+	/// Gets the identities of the dependent assemblies.
+	pub fn dependent_assemblies(&self) -> Vec<AssemblyIdentity> {
+		let mut assemblies = Vec::new();
+		let mut dependent = false;
+		for tag in self.tags() {
+			match tag.name {
+				"dependentAssembly" => dependent = !tag.end && !tag.empty,
+				"assemblyIdentity" if dependent && !tag.end => {
+					assemblies.push(AssemblyIdentity {
+						type_: tag.attr("type"),
+						name: tag.attr("name"),
+						version: tag.attr("version"),
+						processor_architecture: tag.attr("processorArchitecture"),
+						public_key_token: tag.attr("publicKeyToken"),
+						language: tag.attr("language"),
+					});
+				},
+				_ => (),
+			}
+		}
+		assemblies
+	}
+	fn tags(&self) -> Tags {
+		Tags { text: self.text() }
+	}
+	fn find(&self, name: &str) -> Option<Tag> {
+		self.tags().find(|tag| !tag.end && tag.name == name)
+	}
+}
+
+fn decode_utf16(bytes: &[u8], from: fn(u16) -> u16) -> Result<String> {
+	let words = bytes.chunks(2).filter(|word| word.len() == 2).map(|word| from(word[0] as u16 | (word[1] as u16) << 8));
+	char::decode_utf16(words).collect::<::std::result::Result<String, _>>().map_err(|_| Error::Corrupt)
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+	match value.trim() {
+		"true" | "True" | "TRUE" => Some(true),
+		"false" | "False" | "FALSE" => Some(false),
+		_ => None,
+	}
+}
+
+//----------------------------------------------------------------
+
+// Start, end or empty element tag
+#[derive(Copy, Clone, Debug)]
+struct Tag<'s> {
+	// Local name without namespace prefix
+	name: &'s str,
+	attrs: &'s str,
+	// Trimmed text following the tag up to the next tag
+	text: &'s str,
+	end: bool,
+	empty: bool,
+}
+impl<'s> Tag<'s> {
+	fn attr(&self, name: &str) -> Option<&'s str> {
+		let mut attrs = self.attrs;
+		loop {
+			let eq = attrs.find('=')?;
+			let key = attrs[..eq].trim();
+			let rest = attrs[eq + 1..].trim_start();
+			let quote = rest.chars().next()?;
+			if quote != '"' && quote != '\'' {
+				return None;
+			}
+			let end = rest[1..].find(quote)?;
+			let value = &rest[1..end + 1];
+			if local_name(key) == name {
+				return Some(value);
+			}
+			attrs = &rest[end + 2..];
+		}
+	}
+}
+
+fn local_name(name: &str) -> &str {
+	name.rsplit(':').next().unwrap_or(name)
+}
+
+// Iterator over the element tags, skips declarations, processing instructions and comments
+#[derive(Clone)]
+struct Tags<'s> {
+	text: &'s str,
+}
+impl<'s> Iterator for Tags<'s> {
+	type Item = Tag<'s>;
+	fn next(&mut self) -> Option<Tag<'s>> {
+		loop {
+			let start = self.text.find('<')?;
+			let text = &self.text[start + 1..];
+			// Comments may contain `>`
+			let close = if text.starts_with("!--") { "-->" } else { ">" };
+			let end = match text.find(close) {
+				Some(end) => end,
+				None => {
+					self.text = "";
+					return None;
+				},
+			};
+			let inner = &text[..end];
+			self.text = &text[end + close.len()..];
+			if inner.starts_with('!') || inner.starts_with('?') {
+				continue;
+			}
+			let (end, inner) = if inner.starts_with('/') { (true, &inner[1..]) } else { (false, inner) };
+			let (empty, inner) = if inner.ends_with('/') { (true, &inner[..inner.len() - 1]) } else { (false, inner) };
+			let name_len = inner.find(|chr: char| chr.is_whitespace()).unwrap_or(inner.len());
+			let name = local_name(&inner[..name_len]);
+			let attrs = &inner[name_len..];
+			let text = self.text[..self.text.find('<').unwrap_or(self.text.len())].trim();
+			return Some(Tag { name, attrs, text, end, empty });
+		}
+	}
+}
+
+//----------------------------------------------------------------
+
+impl<'a> DataEntry<'a> {
+	/// Interprets the data as a manifest.
+	pub fn manifest(&self) -> Result<Manifest<'a>> {
+		Manifest::from_bytes(self.data()?)
+	}
+}
+
+impl<'a> Resources<'a> {
+	/// Gets the manifest.
+	///
+	/// Takes the first language of the first manifest with id `CREATEPROCESS_MANIFEST_RESOURCE_ID`, `ISOLATIONAWARE_MANIFEST_RESOURCE_ID` or `ISOLATIONAWARE_NOSTATICIMPORT_MANIFEST_RESOURCE_ID`.
+	/// Returns [`Err(Null)`](../../enum.Error.html#variant.Null) if there is no manifest.
+	pub fn manifest(&self) -> Result<Manifest<'a>> {
+		let ids = [CREATEPROCESS_MANIFEST_RESOURCE_ID, ISOLATIONAWARE_MANIFEST_RESOURCE_ID, ISOLATIONAWARE_NOSTATICIMPORT_MANIFEST_RESOURCE_ID];
+		for &id in &ids {
+			match self.manifest_by_id(id) {
+				Err(Error::Null) => continue,
+				result => return result,
+			}
+		}
+		Err(Error::Null)
+	}
+	/// Gets the first language of the manifest with the given id.
+	///
+	/// Returns [`Err(Null)`](../../enum.Error.html#variant.Null) if there is no such manifest.
+	pub fn manifest_by_id(&self, id: u16) -> Result<Manifest<'a>> {
+		self.first_data(RT_MANIFEST, Some(id))?.manifest()
+	}
+}
+
+//----------------------------------------------------------------
+// Formatting
+
+impl<'a> fmt::Debug for Manifest<'a> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("Manifest")
+			.field("encoding", &self.encoding)
+			.field("requested_execution_level", &self.requested_execution_level())
+			.field("ui_access", &self.ui_access())
+			.field("dpi_aware", &self.dpi_aware())
+			.field("dpi_awareness", &self.dpi_awareness())
+			.field("long_path_aware", &self.long_path_aware())
+			.field("supported_os", &self.supported_os())
+			.field("dependent_assemblies", &self.dependent_assemblies())
+			.finish()
+	}
+}
+
+impl<'a> fmt::Display for Manifest<'a> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.write_str(self.text())
+	}
+}
+
+//----------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn fields() {
+		let text = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<assembly xmlns="urn:schemas-microsoft-com:asm.v1" manifestVersion="1.0" xmlns:asmv3="urn:schemas-microsoft-com:asm.v3">
+  <!-- <requestedExecutionLevel level='highestAvailable'/> -->
+  <trustInfo xmlns="urn:schemas-microsoft-com:asm.v3">
+    <security><requestedPrivileges>
+      <requestedExecutionLevel level="requireAdministrator" uiAccess="false"/>
+    </requestedPrivileges></security>
+  </trustInfo>
+  <compatibility xmlns="urn:schemas-microsoft-com:compatibility.v1"><application>
+    <supportedOS Id="{e2011457-1546-43c5-a5fe-008deee3d3f0}"/>
+    <supportedOS Id="{8e0f7a12-bfb3-4fe8-b9a5-48fd50a15a9a}"/>
+  </application></compatibility>
+  <asmv3:application><asmv3:windowsSettings>
+    <dpiAware xmlns="http://schemas.microsoft.com/SMI/2005/WindowsSettings">true/pm</dpiAware>
+    <dpiAwareness xmlns="http://schemas.microsoft.com/SMI/2016/WindowsSettings"> permonitorv2,permonitor </dpiAwareness>
+    <ws2:longPathAware xmlns:ws2="http://schemas.microsoft.com/SMI/2016/WindowsSettings">true</ws2:longPathAware>
+  </asmv3:windowsSettings></asmv3:application>
+  <assemblyIdentity type="win32" name="Demo" version="1.0.0.0"/>
+  <dependency><dependentAssembly>
+    <assemblyIdentity type='win32' name='Microsoft.Windows.Common-Controls' version='6.0.0.0' processorArchitecture='*' publicKeyToken='6595b64144ccf1df' language='*'/>
+  </dependentAssembly></dependency>
+</assembly>
+"#;
+		let utf16: Vec<u8> = [0xFEFFu16].iter().cloned().chain(text.encode_utf16()).flat_map(|word| vec![word as u8, (word >> 8) as u8]).collect();
+
+		for bytes in &[text.as_bytes(), &utf16[..]] {
+			let manifest = Manifest::from_bytes(bytes).unwrap();
+			assert_eq!(manifest.text(), text);
+			assert_eq!(manifest.requested_execution_level(), Some("requireAdministrator"));
+			assert_eq!(manifest.ui_access(), Some(false));
+			assert_eq!(manifest.dpi_aware(), Some("true/pm"));
+			assert_eq!(manifest.dpi_awareness(), Some("permonitorv2,permonitor"));
+			assert_eq!(manifest.long_path_aware(), Some(true));
+			assert_eq!(manifest.supported_os(), ["{e2011457-1546-43c5-a5fe-008deee3d3f0}", "{8e0f7a12-bfb3-4fe8-b9a5-48fd50a15a9a}"]);
+			let assemblies = manifest.dependent_assemblies();
+			assert_eq!(assemblies.len(), 1);
+			assert_eq!(assemblies[0].name, Some("Microsoft.Windows.Common-Controls"));
+			assert_eq!(assemblies[0].public_key_token, Some("6595b64144ccf1df"));
+		}
+	}
+}
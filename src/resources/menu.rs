@@ -0,0 +1,223 @@
+/*!
+Menu Templates.
+
+The `RT_MENU` resources contain either a standard menu template or an extended `MENUEX` template.
+Both are decoded into the same tree of menu items borrowing their text from the resource data.
+
+# Examples
+
+```
+# #![allow(unused_variables)]
+use pelite::pe64::{Pe, PeFile};
+use pelite::resources::menu::MenuItem;
+
+# #[allow(dead_code)]
+fn example(file: PeFile) -> Result<(), pelite::resources::FindError> {
+	let resources = file.resources()?;
+	let menu = resources.find_data("/Menus/101/1033")?.menu()?;
+
+	fn print(items: &[MenuItem], depth: usize) {
+		for item in items {
+			println!("{:width$}{} ({})", "", item.text, item.id, width = depth * 2);
+			print(&item.children, depth + 1);
+		}
+	}
+	print(&menu.items, 0);
+
+	Ok(())
+}
+```
+*/
+
+use error::{Error, Result};
+use image::*;
+use util::WideSlice;
+
+use super::DataEntry;
+use super::template::Words;
+
+// Popup menus nest, limit how deep to protect the stack
+const MAX_DEPTH: usize = 32;
+
+//----------------------------------------------------------------
+
+/// Menu item.
+#[derive(Clone, Debug)]
+pub struct MenuItem<'a> {
+	/// The `MF_*` flags for standard templates.
+	///
+	/// The option flags for extended templates, `0x01` for popups and `0x80` for the last item.
+	pub flags: u16,
+	/// The `MFT_*` type flags, only in extended templates.
+	pub ty: u32,
+	/// The `MFS_*` state flags, only in extended templates.
+	pub state: u32,
+	/// Command identifier, zero for popups in standard templates.
+	pub id: u32,
+	/// Help context identifier of popups, only in extended templates.
+	pub help_id: u32,
+	/// Menu item text, empty for separators.
+	pub text: &'a WideSlice,
+	/// Items of the popup menu opened by this item.
+	pub children: Vec<MenuItem<'a>>,
+}
+
+/// Menu template.
+#[derive(Clone, Debug)]
+pub struct Menu<'a> {
+	/// Decoded from a `MENUEX` template.
+	pub extended: bool,
+	/// Help context identifier of the menu, only in extended templates.
+	pub help_id: u32,
+	pub items: Vec<MenuItem<'a>>,
+}
+
+impl<'a> Menu<'a> {
+	/// Decodes the bytes of a menu resource.
+	pub fn new(bytes: &'a [u8]) -> Result<Menu<'a>> {
+		let mut words = Words::new(bytes)?;
+		let version = words.u16()?;
+		let offset = words.u16()?;
+		match version {
+			0 => {
+				// Standard templates have no additional header
+				if offset != 0 {
+					return Err(Error::Corrupt);
+				}
+				let items = standard(&mut words, 0)?;
+				Ok(Menu { extended: false, help_id: 0, items })
+			},
+			1 => {
+				// The offset to the items is relative to the end of the offset field
+				if offset < 4 || offset & 1 != 0 {
+					return Err(Error::Corrupt);
+				}
+				let help_id = words.u32()?;
+				words.bytes(offset as usize - 4)?;
+				let items = extended(&mut words, 0)?;
+				Ok(Menu { extended: true, help_id, items })
+			},
+			_ => Err(Error::BadMagic),
+		}
+	}
+}
+
+fn standard<'a>(words: &mut Words<'a>, depth: usize) -> Result<Vec<MenuItem<'a>>> {
+	if depth >= MAX_DEPTH {
+		return Err(Error::Corrupt);
+	}
+	let mut items = Vec::new();
+	loop {
+		let flags = words.u16()?;
+		let id = if flags & MF_POPUP == 0 { words.u16()? as u32 } else { 0 };
+		let text = words.sz()?;
+		let children = if flags & MF_POPUP != 0 { standard(words, depth + 1)? } else { Vec::new() };
+		items.push(MenuItem { flags, ty: 0, state: 0, id, help_id: 0, text, children });
+		if flags & MF_END != 0 {
+			return Ok(items);
+		}
+	}
+}
+
+fn extended<'a>(words: &mut Words<'a>, depth: usize) -> Result<Vec<MenuItem<'a>>> {
+	if depth >= MAX_DEPTH {
+		return Err(Error::Corrupt);
+	}
+	let mut items = Vec::new();
+	loop {
+		// Every item is aligned to 32-bit
+		words.align();
+		let ty = words.u32()?;
+		let state = words.u32()?;
+		let id = words.u32()?;
+		let flags = words.u16()?;
+		let text = words.sz()?;
+		let (help_id, children) = if flags & 0x01 != 0 {
+			words.align();
+			let help_id = words.u32()?;
+			(help_id, extended(words, depth + 1)?)
+		}
+		else {
+			(0, Vec::new())
+		};
+		items.push(MenuItem { flags, ty, state, id, help_id, text, children });
+		if flags & 0x80 != 0 {
+			return Ok(items);
+		}
+	}
+}
+
+impl<'a> DataEntry<'a> {
+	/// Interprets the data as a menu template.
+	pub fn menu(&self) -> Result<Menu<'a>> {
+		Menu::new(self.data()?)
+	}
+}
+
+//----------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn bytes(words: &[u16]) -> &[u8] {
+		unsafe { ::std::slice::from_raw_parts(words.as_ptr() as *const u8, words.len() * 2) }
+	}
+
+	#[test]
+	fn standard() {
+		// &File { &Open (100), separator, E&xit (101) }, &Help (102)
+		let words = [
+			0, 0,
+			MF_POPUP, 0x26, 0x46, 0x69, 0x6C, 0x65, 0,
+			0, 100, 0x26, 0x4F, 0x70, 0x65, 0x6E, 0,
+			0, 0, 0,
+			MF_END, 101, 0x45, 0x26, 0x78, 0x69, 0x74, 0,
+			MF_END, 102, 0x26, 0x48, 0x65, 0x6C, 0x70, 0,
+		];
+		let menu = Menu::new(bytes(&words)).unwrap();
+		assert!(!menu.extended);
+		assert_eq!(menu.items.len(), 2);
+		assert_eq!(menu.items[0].text, "&File");
+		assert_eq!(menu.items[0].children.len(), 3);
+		assert_eq!(menu.items[0].children[0].id, 100);
+		assert_eq!(menu.items[0].children[1].text, "");
+		assert_eq!(menu.items[0].children[2].text, "E&xit");
+		assert_eq!(menu.items[1].id, 102);
+		assert_eq!(menu.items[1].text, "&Help");
+	}
+
+	#[test]
+	fn extended() {
+		// &File (popup, help id 7) { &Open (100) }
+		let words = [
+			1, 4, 0, 0,
+			0, 0, 0, 0, 0, 0, 0x81, 0x26, 0x46, 0x69, 0x6C, 0x65, 0, 0,
+			7, 0,
+			0, 0, 0, 0, 100, 0, 0x80, 0x26, 0x4F, 0x70, 0x65, 0x6E, 0,
+		];
+		let menu = Menu::new(bytes(&words)).unwrap();
+		assert!(menu.extended);
+		assert_eq!(menu.items.len(), 1);
+		assert_eq!(menu.items[0].text, "&File");
+		assert_eq!(menu.items[0].help_id, 7);
+		assert_eq!(menu.items[0].children.len(), 1);
+		assert_eq!(menu.items[0].children[0].id, 100);
+		assert_eq!(menu.items[0].children[0].text, "&Open");
+	}
+
+	#[test]
+	fn errors() {
+		assert_eq!(Menu::new(bytes(&[2, 0])).unwrap_err(), Error::BadMagic);
+		assert_eq!(Menu::new(bytes(&[1, 5, 0, 0])).unwrap_err(), Error::Corrupt);
+		// Items without an end flag run out of bounds
+		assert_eq!(Menu::new(bytes(&[0, 0, 0, 100, 0x41, 0])).unwrap_err(), Error::OOB);
+		assert_eq!(Menu::new(bytes(&[0, 0, MF_END, 100, 0x41])).unwrap_err(), Error::OOB);
+		// Popups nested too deep are corrupt
+		let mut words = vec![0, 0];
+		for _ in 0..MAX_DEPTH + 1 {
+			words.extend_from_slice(&[MF_POPUP, 0x41, 0]);
+		}
+		assert_eq!(Menu::new(bytes(&words)).unwrap_err(), Error::Corrupt);
+	}
+}
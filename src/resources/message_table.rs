@@ -0,0 +1,257 @@
+/*!
+Message Tables.
+
+The `RT_MESSAGETABLE` resources contain a `MESSAGE_RESOURCE_DATA` with blocks of consecutive message ids.
+Every block points at its `MESSAGE_RESOURCE_ENTRY` entries, one for every message id in the block, with ANSI, UTF-16 or UTF-8 text.
+
+# Examples
+
+```
+# #![allow(unused_variables)]
+use pelite::pe64::{Pe, PeFile};
+
+# #[allow(dead_code)]
+fn example(file: PeFile) -> pelite::Result<()> {
+	let resources = file.resources()?;
+	let message_table = resources.message_table()?;
+
+	// Look up a message by its id
+	let text = message_table.get(0x40000001)?;
+	println!("{}", text);
+
+	// Print all the messages
+	for (id, text) in message_table.iter() {
+		println!("{:08X}: {}", id, text);
+	}
+
+	Ok(())
+}
+```
+*/
+
+use std::{fmt, mem, slice};
+
+use error::{Error, Result};
+use image::*;
+use util::WideSlice;
+
+use super::{Resources, DataEntry};
+
+//----------------------------------------------------------------
+
+/// Message text.
+///
+/// Trailing nul characters are stripped, the text typically ends in a line break.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Text<'a> {
+	/// Text in the ANSI code page of the message table language.
+	Ansi(&'a [u8]),
+	/// UTF-16 text.
+	Unicode(&'a WideSlice),
+	/// Text which is supposed to be UTF-8 but isn't validated.
+	Utf8(&'a [u8]),
+}
+
+//----------------------------------------------------------------
+
+/// Message table.
+#[derive(Copy, Clone)]
+pub struct MessageTable<'a> {
+	bytes: &'a [u8],
+	blocks: &'a [MESSAGE_RESOURCE_BLOCK],
+}
+
+impl<'a> MessageTable<'a> {
+	/// Interprets the bytes of a message table resource.
+	pub fn new(bytes: &'a [u8]) -> Result<MessageTable<'a>> {
+		if bytes.len() < mem::size_of::<MESSAGE_RESOURCE_DATA>() {
+			return Err(Error::OOB);
+		}
+		let image = unsafe { &*(bytes.as_ptr() as *const MESSAGE_RESOURCE_DATA) };
+		let len = image.NumberOfBlocks as usize;
+		let size = usize::checked_mul(len, mem::size_of::<MESSAGE_RESOURCE_BLOCK>()).ok_or(Error::Overflow)?;
+		if bytes.len() - mem::size_of::<MESSAGE_RESOURCE_DATA>() < size {
+			return Err(Error::OOB);
+		}
+		let blocks = unsafe {
+			let p = bytes.as_ptr().offset(mem::size_of::<MESSAGE_RESOURCE_DATA>() as isize) as *const MESSAGE_RESOURCE_BLOCK;
+			slice::from_raw_parts(p, len)
+		};
+		Ok(MessageTable { bytes, blocks })
+	}
+	/// Gets the underlying message resource blocks.
+	pub fn blocks(&self) -> &'a [MESSAGE_RESOURCE_BLOCK] {
+		self.blocks
+	}
+	/// Looks up a message by its id.
+	///
+	/// Returns [`Err(Null)`](../../enum.Error.html#variant.Null) if there is no such message.
+	pub fn get(&self, id: u32) -> Result<Text<'a>> {
+		let block = self.blocks.iter().find(|block| block.LowId <= id && id <= block.HighId).ok_or(Error::Null)?;
+		let mut offset = block.OffsetToEntries as usize;
+		for _ in block.LowId..id {
+			offset += self.entry(offset)?.0;
+		}
+		self.entry(offset).map(|(_, text)| text)
+	}
+	/// Iterates over all the messages as `(id, text)` pairs.
+	///
+	/// Stops iterating a block at its first corrupt entry.
+	pub fn iter(&self) -> Messages<'a> {
+		let state = self.blocks.first().map(|block| (block.LowId, block.OffsetToEntries as usize)).unwrap_or((0, 0));
+		Messages { table: *self, block: 0, id: state.0, offset: state.1 }
+	}
+	// Reads the entry at the offset, returns its length and text
+	fn entry(&self, offset: usize) -> Result<(usize, Text<'a>)> {
+		let header = self.bytes.get(offset..offset + mem::size_of::<MESSAGE_RESOURCE_ENTRY>()).ok_or(Error::OOB)?;
+		let header = unsafe { &*(header.as_ptr() as *const MESSAGE_RESOURCE_ENTRY) };
+		let len = header.Length as usize;
+		if len < mem::size_of::<MESSAGE_RESOURCE_ENTRY>() {
+			return Err(Error::Corrupt);
+		}
+		let bytes = self.bytes.get(offset + 4..offset + len).ok_or(Error::OOB)?;
+		let text = match header.Flags {
+			MESSAGE_RESOURCE_UNICODE => {
+				if bytes.as_ptr() as usize & 1 != 0 {
+					return Err(Error::Misalign);
+				}
+				let words = unsafe { slice::from_raw_parts(bytes.as_ptr() as *const u16, bytes.len() / 2) };
+				let len = words.iter().rposition(|&word| word != 0).map(|i| i + 1).unwrap_or(0);
+				Text::Unicode(WideSlice::from_words(&words[..len]))
+			},
+			flags => {
+				let len = bytes.iter().rposition(|&byte| byte != 0).map(|i| i + 1).unwrap_or(0);
+				if flags == MESSAGE_RESOURCE_UTF8 { Text::Utf8(&bytes[..len]) } else { Text::Ansi(&bytes[..len]) }
+			},
+		};
+		Ok((len, text))
+	}
+}
+
+impl<'a> IntoIterator for MessageTable<'a> {
+	type Item = (u32, Text<'a>);
+	type IntoIter = Messages<'a>;
+	fn into_iter(self) -> Messages<'a> {
+		self.iter()
+	}
+}
+
+/// Iterator over the messages.
+#[derive(Clone)]
+pub struct Messages<'a> {
+	table: MessageTable<'a>,
+	block: usize,
+	id: u32,
+	offset: usize,
+}
+impl<'a> Messages<'a> {
+	fn next_block(&mut self) {
+		self.block += 1;
+		if let Some(block) = self.table.blocks.get(self.block) {
+			self.id = block.LowId;
+			self.offset = block.OffsetToEntries as usize;
+		}
+	}
+}
+impl<'a> Iterator for Messages<'a> {
+	type Item = (u32, Text<'a>);
+	fn next(&mut self) -> Option<(u32, Text<'a>)> {
+		loop {
+			let block = self.table.blocks.get(self.block)?;
+			if self.id < block.LowId || self.id > block.HighId {
+				self.next_block();
+				continue;
+			}
+			match self.table.entry(self.offset) {
+				Ok((len, text)) => {
+					let id = self.id;
+					self.offset += len;
+					// Careful with the last message id
+					match self.id.checked_add(1) {
+						Some(next) => self.id = next,
+						None => self.next_block(),
+					}
+					return Some((id, text));
+				},
+				Err(_) => self.next_block(),
+			}
+		}
+	}
+}
+
+//----------------------------------------------------------------
+
+impl<'a> DataEntry<'a> {
+	/// Interprets the data as a message table.
+	pub fn message_table(&self) -> Result<MessageTable<'a>> {
+		MessageTable::new(self.data()?)
+	}
+}
+
+impl<'a> Resources<'a> {
+	/// Gets the message table.
+	///
+	/// Takes the first language of the first message table resource.
+	/// Returns [`Err(Null)`](../../enum.Error.html#variant.Null) if there is no message table.
+	pub fn message_table(&self) -> Result<MessageTable<'a>> {
+		self.first_data(RT_MESSAGETABLE, None)?.message_table()
+	}
+}
+
+//----------------------------------------------------------------
+// Formatting
+
+impl<'a> fmt::Display for Text<'a> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			// Without knowing the code page show the bytes as latin-1
+			Text::Ansi(bytes) => bytes.iter().map(|&byte| fmt::Write::write_char(f, byte as char)).collect(),
+			Text::Unicode(string) => string.fmt(f),
+			Text::Utf8(bytes) => String::from_utf8_lossy(bytes).fmt(f),
+		}
+	}
+}
+
+impl<'a> fmt::Debug for MessageTable<'a> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.write_str("Message Table\n")?;
+		for (id, text) in self.iter() {
+			write!(f, "  {:08X}: {:?}\n", id, text.to_string())?;
+		}
+		Ok(())
+	}
+}
+
+//----------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn messages() {
+		// Two blocks, ids 1..=2 and 0x100
+		let words: [u16; 36] = [
+			2, 0,
+			1, 0, 2, 0, 28, 0,
+			0x100, 0, 0x100, 0, 52, 0,
+			// 1: ANSI "Hi\r\n"
+			12, 0, 0x6948, 0x0A0D, 0, 0,
+			// 2: Unicode "Yo"
+			12, 1, 0x59, 0x6F, 0, 0,
+			// 0x100: Unicode "Hello"
+			16, 1, 0x48, 0x65, 0x6C, 0x6C, 0x6F, 0,
+			0, 0,
+		];
+		let bytes = unsafe { slice::from_raw_parts(words.as_ptr() as *const u8, words.len() * 2) };
+		let table = MessageTable::new(bytes).unwrap();
+		assert_eq!(table.get(1), Ok(Text::Ansi(b"Hi\r\n")));
+		assert_eq!(table.get(2).unwrap().to_string(), "Yo");
+		assert_eq!(table.get(2), Ok(Text::Unicode(WideSlice::from_words(&[0x59, 0x6F]))));
+		assert_eq!(table.get(0x100).unwrap().to_string(), "Hello");
+		assert_eq!(table.get(3), Err(Error::Null));
+
+		let ids: Vec<u32> = table.iter().map(|(id, _)| id).collect();
+		assert_eq!(ids, [1, 2, 0x100]);
+	}
+}
@@ -13,6 +13,19 @@ use util::WideStr;
 mod find;
 pub use self::find::FindError;
 
+pub mod version_info;
+pub mod manifest;
+pub mod group_icon;
+pub mod string_table;
+pub mod dialog;
+pub mod menu;
+pub mod accelerator;
+pub mod message_table;
+pub mod bitmap;
+pub mod build;
+pub mod mui;
+mod template;
+
 //----------------------------------------------------------------
 
 /// Resources filesystem.
@@ -42,6 +55,59 @@ impl<'a> Resources<'a> {
 	pub fn root(self) -> Result<Directory<'a>> {
 		Directory::from(self, 0)
 	}
+	// Gets the directory of a resource type.
+	// Returns `Err(Null)` if there are no resources of this type.
+	fn type_dir(self, ty: u16) -> Result<Directory<'a>> {
+		find_id(self.root()?, Some(ty))?.dir().ok_or(Error::Corrupt)
+	}
+	// Gets the first language of a resource by its type and id, or of the first resource of this type.
+	// Returns `Err(Null)` if there is no such resource.
+	fn first_data(self, ty: u16, id: Option<u16>) -> Result<DataEntry<'a>> {
+		let dir = find_id(self.type_dir(ty)?, id)?.dir().ok_or(Error::Corrupt)?;
+		find_id(dir, None)?.data().ok_or(Error::Corrupt)
+	}
+}
+
+// Finds the entry by its id or the first entry, returns `Err(Null)` if not found.
+fn find_id<'a>(dir: Directory<'a>, id: Option<u16>) -> Result<Entry<'a>> {
+	let entry = dir.entries().find(|entry| match (entry.name(), id) {
+		(Ok(Name::Id(name)), Some(id)) => name == id as u32,
+		(_, None) => true,
+		_ => false,
+	});
+	entry.ok_or(Error::Null)?.entry()
+}
+
+/// Maximum nesting of resource directories followed by the walkers.
+///
+/// Regular resources are only three directories deep: the types, names and languages.
+pub const MAX_DEPTH: usize = 32;
+
+// Tracks the directories on the current path of the recursive walkers to guarantee termination.
+// Directories shared by several parents are fine, only a directory containing itself is a cycle.
+struct Visited(Vec<usize>);
+impl Visited {
+	fn new() -> Visited {
+		Visited(Vec::new())
+	}
+	// Returns `Err(Corrupt)` if the directory is its own ancestor or nests too deep.
+	fn enter(&mut self, dir: Directory) -> Result<()> {
+		let offset = dir.offset();
+		if self.0.len() >= MAX_DEPTH || self.0.contains(&offset) {
+			return Err(Error::Corrupt);
+		}
+		self.0.push(offset);
+		Ok(())
+	}
+	// Returns from the most recently entered directory.
+	fn leave(&mut self) {
+		self.0.pop();
+	}
+}
+
+// Resource names are compared ignoring ASCII case.
+fn ascii_upper(word: u16) -> u16 {
+	if word >= 0x61 && word <= 0x7A { word - 0x20 } else { word }
 }
 
 //----------------------------------------------------------------
@@ -68,6 +134,10 @@ impl<'a> Directory<'a> {
 		}
 		Ok(Directory { resources, image })
 	}
+	// Offset of the directory in the resources
+	fn offset(&self) -> usize {
+		self.image as *const _ as usize - self.resources.data.as_ptr() as usize
+	}
 	/// Gets the resources.
 	pub fn resources(&self) -> Resources<'a> {
 		self.resources
@@ -112,6 +182,33 @@ impl<'a> Directory<'a> {
 		};
 		Entries { resources: self.resources, iter: slice.iter() }
 	}
+	/// Walks all the entries in this directory tree depth first.
+	///
+	/// The visitor is called with the path of names from this directory to the entry before descending into it.
+	///
+	/// Returns [`Err(Corrupt)`](../enum.Error.html#variant.Corrupt) if a directory contains itself, eg. when a subdirectory points back at its parent,
+	/// or if the directories nest deeper than [`MAX_DEPTH`](constant.MAX_DEPTH.html).
+	/// This guarantees termination for any resources.
+	pub fn walk<F: FnMut(&[Name<'a>], Entry<'a>)>(&self, mut f: F) -> Result<()> {
+		let mut visited = Visited::new();
+		visited.enter(*self)?;
+		let mut path = Vec::new();
+		walk_rec(*self, &mut visited, &mut path, &mut f)
+	}
+}
+fn walk_rec<'a, F: FnMut(&[Name<'a>], Entry<'a>)>(dir: Directory<'a>, visited: &mut Visited, path: &mut Vec<Name<'a>>, f: &mut F) -> Result<()> {
+	for child in dir.entries() {
+		let entry = child.entry()?;
+		path.push(child.name()?);
+		f(path, entry);
+		if let Entry::Directory(dir) = entry {
+			visited.enter(dir)?;
+			walk_rec(dir, visited, path, f)?;
+			visited.leave();
+		}
+		path.pop();
+	}
+	Ok(())
 }
 
 //----------------------------------------------------------------
@@ -167,6 +264,30 @@ pub enum Name<'a> {
 	Str(&'a WideStr),
 }
 
+impl<'a> Name<'a> {
+	pub const RT_CURSOR: Name<'a>       = Name::Id(RT_CURSOR as u32);
+	pub const RT_BITMAP: Name<'a>       = Name::Id(RT_BITMAP as u32);
+	pub const RT_ICON: Name<'a>         = Name::Id(RT_ICON as u32);
+	pub const RT_MENU: Name<'a>         = Name::Id(RT_MENU as u32);
+	pub const RT_DIALOG: Name<'a>       = Name::Id(RT_DIALOG as u32);
+	pub const RT_STRING: Name<'a>       = Name::Id(RT_STRING as u32);
+	pub const RT_FONTDIR: Name<'a>      = Name::Id(RT_FONTDIR as u32);
+	pub const RT_FONT: Name<'a>         = Name::Id(RT_FONT as u32);
+	pub const RT_ACCELERATOR: Name<'a>  = Name::Id(RT_ACCELERATOR as u32);
+	pub const RT_RCDATA: Name<'a>       = Name::Id(RT_RCDATA as u32);
+	pub const RT_MESSAGETABLE: Name<'a> = Name::Id(RT_MESSAGETABLE as u32);
+	pub const RT_GROUP_CURSOR: Name<'a> = Name::Id(RT_GROUP_CURSOR as u32);
+	pub const RT_GROUP_ICON: Name<'a>   = Name::Id(RT_GROUP_ICON as u32);
+	pub const RT_VERSION: Name<'a>      = Name::Id(RT_VERSION as u32);
+	pub const RT_DLGINCLUDE: Name<'a>   = Name::Id(RT_DLGINCLUDE as u32);
+	pub const RT_PLUGPLAY: Name<'a>     = Name::Id(RT_PLUGPLAY as u32);
+	pub const RT_VXD: Name<'a>          = Name::Id(RT_VXD as u32);
+	pub const RT_ANICURSOR: Name<'a>    = Name::Id(RT_ANICURSOR as u32);
+	pub const RT_ANIICON: Name<'a>      = Name::Id(RT_ANIICON as u32);
+	pub const RT_HTML: Name<'a>         = Name::Id(RT_HTML as u32);
+	pub const RT_MANIFEST: Name<'a>     = Name::Id(RT_MANIFEST as u32);
+}
+
 //----------------------------------------------------------------
 
 /// Data or directory entry.
@@ -235,9 +356,15 @@ impl<'a> DirectoryEntry<'a> {
 		self.image.Offset & 0x80000000 != 0
 	}
 	/// Returns the directory or data entry for this entry.
+	///
+	/// Returns [`Err(Corrupt)`](../enum.Error.html#variant.Corrupt) if a subdirectory points back at the root directory.
+	/// Other cycles are only detected by the walkers, see [`Directory::walk`](struct.Directory.html#method.walk).
 	pub fn entry(&self) -> Result<Entry<'a>> {
 		if self.is_dir() {
 			let offset = (self.image.Offset & !0x80000000) as usize;
+			if offset == 0 {
+				return Err(Error::Corrupt);
+			}
 			Directory::from(self.resources, offset).map(Entry::Directory)
 		}
 		else {
@@ -309,18 +436,17 @@ pub static TREE_ART_A: TreeArt = TreeArt {
 ///
 /// Specify if this is the root directory to have its children ids printed with their names instead.
 pub fn tree_fmt(f: &mut fmt::Formatter, dir: Directory, art: &TreeArt, root: bool) -> fmt::Result {
-	let mut margin = [false; 32];
-	tree_fmt_rec(f, &mut margin, if root { !0 } else { 0 }, art, dir)
+	let mut margin = [false; MAX_DEPTH];
+	let mut visited = Visited::new();
+	if let Err(err) = visited.enter(dir) {
+		return write!(f, "{:?}\n", Err::<(), _>(err));
+	}
+	tree_fmt_rec(f, &mut margin, &mut visited, if root { !0 } else { 0 }, art, dir)
 }
-fn tree_fmt_rec(f: &mut fmt::Formatter, margin: &mut [bool; 32], depth: u32, art: &TreeArt, dir: Directory) -> fmt::Result {
+fn tree_fmt_rec(f: &mut fmt::Formatter, margin: &mut [bool; MAX_DEPTH], visited: &mut Visited, depth: u32, art: &TreeArt, dir: Directory) -> fmt::Result {
 	// Encode if root in depth
 	let (root, depth) = if depth == !0 { (true, 0) } else { (false, depth) };
 
-	// Quiet failsafe, should never happen
-	if depth as usize >= margin.len() {
-		return Ok(());
-	}
-
 	let mut entries = dir.entries();
 	while let Some(e) = entries.next() {
 		// Print the margin
@@ -354,7 +480,19 @@ fn tree_fmt_rec(f: &mut fmt::Formatter, margin: &mut [bool; 32], depth: u32, art
 		// If it's a directory, print it recursively
 		if let Ok(Entry::Directory(dir)) = e.entry() {
 			margin[depth as usize] = is_last;
-			tree_fmt_rec(f, margin, depth + 1, art, dir)?;
+			match visited.enter(dir) {
+				Ok(()) => {
+					tree_fmt_rec(f, margin, visited, depth + 1, art, dir)?;
+					visited.leave();
+				},
+				// Print the error in place of the directory's entries
+				Err(err) => {
+					for &is_last in &margin[0..depth as usize + 1] {
+						f.write_str(if is_last { &art.en } else { &art.it }.margin)?;
+					}
+					write!(f, "{}{:?}\n", art.en.file, Err::<(), _>(err))?;
+				},
+			}
 		}
 	}
 	Ok(())
@@ -379,23 +517,42 @@ impl<'a> fmt::Debug for Resources<'a> {
 
 impl<'a> fmt::Debug for Directory<'a> {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-		self.image.fmt(f)?;
-		for entry in self.entries() {
-			write!(f, "{:?}", entry)?;
-			match entry.entry() {
-				Ok(Entry::DataEntry(data_entry)) => write!(f, "{:?}", data_entry),
-				Ok(Entry::Directory(directory)) => write!(f, "{:?}", directory),
-				e @ Err(_) => write!(f, "{:?}", e),
-			}?;
+		let mut visited = Visited::new();
+		match visited.enter(*self) {
+			Ok(()) => directory_fmt_rec(f, &mut visited, *self),
+			Err(err) => write!(f, "{:?}", Err::<(), _>(err)),
 		}
-		Ok(())
 	}
 }
+fn directory_fmt_rec(f: &mut fmt::Formatter, visited: &mut Visited, dir: Directory) -> fmt::Result {
+	fmt::Debug::fmt(dir.image, f)?;
+	for entry in dir.entries() {
+		write!(f, "{:?}", entry)?;
+		match entry.entry() {
+			Ok(Entry::DataEntry(data_entry)) => write!(f, "{:?}", data_entry),
+			Ok(Entry::Directory(directory)) => {
+				match visited.enter(directory) {
+					Ok(()) => {
+						directory_fmt_rec(f, visited, directory)?;
+						visited.leave();
+						Ok(())
+					},
+					Err(err) => write!(f, "{:?}", Err::<(), _>(err)),
+				}
+			},
+			e @ Err(_) => write!(f, "{:?}", e),
+		}?;
+	}
+	Ok(())
+}
 
 impl<'a> fmt::Debug for DirectoryEntry<'a> {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
 		let a = if self.is_dir() { "<DIR>" } else { "<FILE>" };
-		write!(f, "{}: {:?}\n", a, self.name().unwrap())
+		match self.name() {
+			Ok(name) => write!(f, "{}: {:?}\n", a, name),
+			Err(err) => write!(f, "{}: {:?}\n", a, Err::<(), _>(err)),
+		}
 	}
 }
 
@@ -404,3 +561,61 @@ impl<'a> fmt::Debug for DataEntry<'a> {
 		self.image.fmt(f)
 	}
 }
+
+//----------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn bytes(dwords: &[u32]) -> &[u8] {
+		unsafe { slice::from_raw_parts(dwords.as_ptr() as *const u8, dwords.len() * 4) }
+	}
+
+	#[test]
+	fn cycles() {
+		// The subdirectory at offset 24 contains itself
+		let dwords = [
+			0, 0, 0, 1 << 16, 1, 0x80000000 | 24,
+			0, 0, 0, 1 << 16, 2, 0x80000000 | 24,
+		];
+		let resources = Resources::new(bytes(&dwords), 0);
+		let root = resources.root().unwrap();
+		let mut count = 0;
+		assert_eq!(root.walk(|_, _| count += 1), Err(Error::Corrupt));
+		assert_eq!(count, 2);
+		assert!(format!("{:?}", resources).contains("Err(Corrupt)"));
+		assert!(format!("{:?}", root).contains("Err(Corrupt)"));
+
+		// The subdirectory points back at the root
+		let dwords = [0, 0, 0, 1 << 16, 1, 0x80000000];
+		let resources = Resources::new(bytes(&dwords), 0);
+		let root = resources.root().unwrap();
+		assert_eq!(root.entries().next().unwrap().entry().err(), Some(Error::Corrupt));
+		assert_eq!(root.walk(|_, _| ()), Err(Error::Corrupt));
+	}
+
+	#[test]
+	fn shared() {
+		// Both types share the names directory at offset 32
+		let dwords = [
+			0, 0, 0, 2 << 16, 1, 0x80000000 | 32, 2, 0x80000000 | 32,
+			0, 0, 0, 1 << 16, 3, 0x80000000 | 56,
+			0, 0, 0, 1 << 16, 1033, 80,
+			80, 4, 0, 0,
+		];
+		let resources = Resources::new(bytes(&dwords), 0);
+		let root = resources.root().unwrap();
+		let mut count = 0;
+		assert_eq!(root.walk(|_, _| count += 1), Ok(()));
+		assert_eq!(count, 6);
+		assert!(!format!("{:?}", resources).contains("Err(Corrupt)"));
+		assert!(!format!("{:?}", root).contains("Err(Corrupt)"));
+
+		let copy = build::DirectoryBuf::from_dir(root).unwrap();
+		for ty in 1..3 {
+			let data = copy.get(&build::NameBuf::from(ty), &build::NameBuf::from(3), 1033).unwrap();
+			assert_eq!(data, &build::DataBuf::new(vec![80, 0, 0, 0]));
+		}
+	}
+}
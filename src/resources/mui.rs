@@ -0,0 +1,377 @@
+/*!
+MUI Resource Configuration.
+
+Multilingual modules are split in a language neutral (LN) module with the code and a satellite `.mui` file per language with the localizable resources.
+Both contain a `MUI` resource describing which resource types live where and a checksum tying the satellites to their module.
+
+The satellites are found next to the module in a directory named after their language, eg. `en-US\name.dll.mui`.
+
+# Examples
+
+```
+# #![allow(unused_variables)]
+use pelite::pe64::{Pe, PeFile};
+use pelite::resources::mui;
+
+# #[allow(dead_code)]
+fn example(file: PeFile) -> pelite::Result<()> {
+	let config = file.resources()?.mui()?;
+
+	// The resource types in the satellites
+	for ty in config.mui_name_types()? {
+		println!("{}", ty);
+	}
+	println!("{:?}", config.mui_id_types()?);
+
+	// Find the matching English satellite
+	let path = mui::find_satellite("C:\\Windows\\System32\\notepad.exe", &config, "en-US")?;
+	println!("{}", path.display());
+
+	Ok(())
+}
+```
+*/
+
+use std::{fmt, slice};
+use std::path::{Path, PathBuf};
+
+use error::{Error, Result};
+use util::WideSlice;
+
+use super::{Resources, DataEntry, Name};
+
+//----------------------------------------------------------------
+
+pub const MUI_SIGNATURE: u32 = 0xFECDFECD;
+
+/// File type of a language neutral module.
+pub const MUI_FILETYPE_LN: u32 = 0x11;
+/// File type of a satellite `.mui` file.
+pub const MUI_FILETYPE_MUI: u32 = 0x12;
+
+pub const MUI_FALLBACK_INTERNAL: u32 = 1;
+pub const MUI_FALLBACK_EXTERNAL: u32 = 2;
+
+// Size of the fixed header
+const HEADER_SIZE: usize = 0x84;
+
+//----------------------------------------------------------------
+
+/// MUI resource configuration.
+#[derive(Copy, Clone)]
+pub struct MuiConfig<'a> {
+	bytes: &'a [u8],
+}
+
+impl<'a> MuiConfig<'a> {
+	/// Interprets the bytes of a `MUI` resource.
+	///
+	/// Returns [`Err(BadMagic)`](../../enum.Error.html#variant.BadMagic) if the signature doesn't match.
+	pub fn new(bytes: &'a [u8]) -> Result<MuiConfig<'a>> {
+		if bytes.as_ptr() as usize & 3 != 0 {
+			return Err(Error::Misalign);
+		}
+		if bytes.len() < HEADER_SIZE {
+			return Err(Error::OOB);
+		}
+		let config = MuiConfig { bytes };
+		if config.dword(0x00) != MUI_SIGNATURE {
+			return Err(Error::BadMagic);
+		}
+		let size = config.dword(0x04) as usize;
+		if size < HEADER_SIZE || size > bytes.len() {
+			return Err(Error::Corrupt);
+		}
+		Ok(MuiConfig { bytes: &bytes[..size] })
+	}
+	/// Gets the version of the configuration, `0x00010000`.
+	pub fn version(&self) -> u32 {
+		self.dword(0x08)
+	}
+	/// Gets the file type, [`MUI_FILETYPE_LN`](constant.MUI_FILETYPE_LN.html) or [`MUI_FILETYPE_MUI`](constant.MUI_FILETYPE_MUI.html).
+	pub fn file_type(&self) -> u32 {
+		self.dword(0x10)
+	}
+	/// Returns if this is the configuration of a language neutral module.
+	pub fn is_ln(&self) -> bool {
+		self.file_type() == MUI_FILETYPE_LN
+	}
+	/// Returns if this is the configuration of a satellite `.mui` file.
+	pub fn is_mui(&self) -> bool {
+		self.file_type() == MUI_FILETYPE_MUI
+	}
+	pub fn system_attributes(&self) -> u32 {
+		self.dword(0x14)
+	}
+	/// Gets where the ultimate fallback language is found, [`MUI_FALLBACK_INTERNAL`](constant.MUI_FALLBACK_INTERNAL.html) or [`MUI_FALLBACK_EXTERNAL`](constant.MUI_FALLBACK_EXTERNAL.html).
+	pub fn fallback_location(&self) -> u32 {
+		self.dword(0x18)
+	}
+	/// Gets the service checksum, changes with servicing of the resources.
+	pub fn service_checksum(&self) -> &'a [u8; 16] {
+		unsafe { &*(self.bytes[0x1C..0x2C].as_ptr() as *const [u8; 16]) }
+	}
+	/// Gets the checksum shared by a module and its satellites.
+	pub fn checksum(&self) -> &'a [u8; 16] {
+		unsafe { &*(self.bytes[0x2C..0x3C].as_ptr() as *const [u8; 16]) }
+	}
+	/// Gets the named resource types in the language neutral module.
+	pub fn main_name_types(&self) -> Result<NameTypes<'a>> {
+		self.name_types(0x54)
+	}
+	/// Gets the resource type ids in the language neutral module.
+	pub fn main_id_types(&self) -> Result<&'a [u32]> {
+		self.id_types(0x5C)
+	}
+	/// Gets the named resource types in the satellites.
+	pub fn mui_name_types(&self) -> Result<NameTypes<'a>> {
+		self.name_types(0x64)
+	}
+	/// Gets the resource type ids in the satellites.
+	pub fn mui_id_types(&self) -> Result<&'a [u32]> {
+		self.id_types(0x6C)
+	}
+	/// Gets the language name, eg. `en-US`.
+	///
+	/// Returns [`Err(Null)`](../../enum.Error.html#variant.Null) if there is none, as is typical for language neutral modules.
+	pub fn language(&self) -> Result<&'a WideSlice> {
+		self.string(0x74)
+	}
+	/// Gets the name of the ultimate fallback language.
+	///
+	/// Returns [`Err(Null)`](../../enum.Error.html#variant.Null) if there is none.
+	pub fn fallback_language(&self) -> Result<&'a WideSlice> {
+		self.string(0x7C)
+	}
+	/// Verifies a satellite belongs to this language neutral module.
+	///
+	/// Returns [`Err(BadMagic)`](../../enum.Error.html#variant.BadMagic) if the file types or checksums don't match.
+	pub fn verify(&self, satellite: &MuiConfig) -> Result<()> {
+		if !self.is_ln() || !satellite.is_mui() || self.checksum() != satellite.checksum() {
+			return Err(Error::BadMagic);
+		}
+		Ok(())
+	}
+	fn dword(&self, offset: usize) -> u32 {
+		let b = &self.bytes[offset..offset + 4];
+		b[0] as u32 | (b[1] as u32) << 8 | (b[2] as u32) << 16 | (b[3] as u32) << 24
+	}
+	// Gets the bytes of an offset and size pair, empty if the size is zero
+	fn area(&self, offset: usize, align: usize) -> Result<&'a [u8]> {
+		let start = self.dword(offset) as usize;
+		let size = self.dword(offset + 4) as usize;
+		if size == 0 {
+			return Ok(&[]);
+		}
+		if start < HEADER_SIZE || start & (align - 1) != 0 || size & (align - 1) != 0 {
+			return Err(Error::Corrupt);
+		}
+		let end = start.checked_add(size).ok_or(Error::Overflow)?;
+		self.bytes.get(start..end).ok_or(Error::OOB)
+	}
+	// The area of utf16 words
+	fn words(&self, offset: usize) -> Result<&'a [u16]> {
+		let bytes = self.area(offset, 2)?;
+		if bytes.is_empty() {
+			return Ok(&[]);
+		}
+		Ok(unsafe { slice::from_raw_parts(bytes.as_ptr() as *const u16, bytes.len() / 2) })
+	}
+	fn name_types(&self, offset: usize) -> Result<NameTypes<'a>> {
+		Ok(NameTypes { words: self.words(offset)? })
+	}
+	fn id_types(&self, offset: usize) -> Result<&'a [u32]> {
+		let bytes = self.area(offset, 4)?;
+		if bytes.is_empty() {
+			return Ok(&[]);
+		}
+		Ok(unsafe { slice::from_raw_parts(bytes.as_ptr() as *const u32, bytes.len() / 4) })
+	}
+	fn string(&self, offset: usize) -> Result<&'a WideSlice> {
+		let mut names = self.name_types(offset)?;
+		names.next().ok_or(Error::Null)
+	}
+}
+
+/// Iterator over the named resource types.
+///
+/// The names are stored as a list of nul terminated strings ending in an empty string.
+#[derive(Clone)]
+pub struct NameTypes<'a> {
+	words: &'a [u16],
+}
+impl<'a> Iterator for NameTypes<'a> {
+	type Item = &'a WideSlice;
+	fn next(&mut self) -> Option<&'a WideSlice> {
+		let len = self.words.iter().position(|&word| word == 0).unwrap_or(self.words.len());
+		if len == 0 {
+			self.words = &[];
+			return None;
+		}
+		let string = WideSlice::from_words(&self.words[..len]);
+		self.words = self.words.get(len + 1..).unwrap_or(&[]);
+		Some(string)
+	}
+}
+
+//----------------------------------------------------------------
+
+impl<'a> DataEntry<'a> {
+	/// Interprets the data as a MUI resource configuration.
+	pub fn mui(&self) -> Result<MuiConfig<'a>> {
+		MuiConfig::new(self.data()?)
+	}
+}
+
+impl<'a> Resources<'a> {
+	/// Gets the MUI resource configuration.
+	///
+	/// Returns [`Err(Null)`](../../enum.Error.html#variant.Null) if there is no `MUI` resource.
+	pub fn mui(&self) -> Result<MuiConfig<'a>> {
+		let ty = self.root()?.entries().find(|entry| match entry.name() {
+			Ok(Name::Str(name)) => name == "MUI",
+			_ => false,
+		});
+		let names = ty.ok_or(Error::Null)?.entry()?.dir().ok_or(Error::Corrupt)?;
+		let langs = super::find_id(names, None)?.dir().ok_or(Error::Corrupt)?;
+		super::find_id(langs, None)?.data().ok_or(Error::Corrupt)?.mui()
+	}
+}
+
+//----------------------------------------------------------------
+
+/// Gets the path of a satellite file, next to the module in a directory named after the language.
+///
+/// For example `C:\Windows\System32\notepad.exe` in `en-US` has its satellite at `C:\Windows\System32\en-US\notepad.exe.mui`.
+pub fn satellite_path<P: AsRef<Path> + ?Sized>(module: &P, language: &str) -> PathBuf {
+	let module = module.as_ref();
+	let mut file_name = module.file_name().unwrap_or_default().to_os_string();
+	file_name.push(".mui");
+	let mut path = module.parent().map(Path::to_path_buf).unwrap_or_default();
+	path.push(language);
+	path.push(file_name);
+	path
+}
+
+/// Locates the satellite file of a language neutral module and verifies it belongs to the module.
+///
+/// Returns [`Err(Null)`](../../enum.Error.html#variant.Null) if the satellite file or its `MUI` resource doesn't exist
+/// and [`Err(BadMagic)`](../../enum.Error.html#variant.BadMagic) if the checksums don't match.
+#[cfg(any(windows, unix))]
+pub fn find_satellite<P: AsRef<Path> + ?Sized>(module: &P, config: &MuiConfig, language: &str) -> Result<PathBuf> {
+	let path = satellite_path(module, language);
+	let file_map = ::FileMap::open(&path).map_err(|_| Error::Null)?;
+	// Satellites are resource only images of either bitness
+	let bytes = file_map.as_ref();
+	let resources = match ::pe64::PeFile::from_bytes(bytes) {
+		Ok(file) => ::pe64::Pe::resources(file)?,
+		Err(Error::BadMagic) => ::pe32::Pe::resources(::pe32::PeFile::from_bytes(bytes)?)?,
+		Err(err) => return Err(err),
+	};
+	config.verify(&resources.mui()?)?;
+	Ok(path)
+}
+
+//----------------------------------------------------------------
+// Formatting
+
+impl<'a> fmt::Debug for MuiConfig<'a> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let file_type = match self.file_type() {
+			MUI_FILETYPE_LN => "LN",
+			MUI_FILETYPE_MUI => "MUI",
+			_ => "Unknown",
+		};
+		write!(f, "MuiConfig {} version {:#X}\n", file_type, self.version())?;
+		f.write_str("  Checksum: ")?;
+		for byte in self.checksum() {
+			write!(f, "{:02X}", byte)?;
+		}
+		f.write_str("\n")?;
+		if let Ok(language) = self.language() {
+			write!(f, "  Language: {}\n", language)?;
+		}
+		if let Ok(language) = self.fallback_language() {
+			write!(f, "  Fallback: {}\n", language)?;
+		}
+		if let Ok(types) = self.mui_id_types() {
+			write!(f, "  MUI types: {:?}", types)?;
+			for name in self.mui_name_types().into_iter().flatten() {
+				write!(f, " {:?}", name)?;
+			}
+			f.write_str("\n")?;
+		}
+		Ok(())
+	}
+}
+
+//----------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn config(file_type: u32, checksum: u8, language: &str) -> Vec<u32> {
+		let mut dwords = vec![0u32; HEADER_SIZE / 4];
+		dwords[0] = MUI_SIGNATURE;
+		dwords[2] = 0x00010000;
+		dwords[4] = file_type;
+		dwords[6] = MUI_FALLBACK_EXTERNAL;
+		dwords[0x2C / 4] = checksum as u32;
+		// MUI id types: RT_STRING and RT_VERSION
+		dwords[0x6C / 4] = dwords.len() as u32 * 4;
+		dwords[0x70 / 4] = 8;
+		dwords.extend_from_slice(&[6, 16]);
+		// MUI name types: MUI
+		dwords[0x64 / 4] = dwords.len() as u32 * 4;
+		dwords[0x68 / 4] = 12;
+		dwords.extend_from_slice(&[0x55004D, 0x49, 0]);
+		// Language name
+		let mut words: Vec<u16> = language.encode_utf16().collect();
+		words.push(0);
+		if words.len() % 2 != 0 {
+			words.push(0);
+		}
+		dwords[0x74 / 4] = dwords.len() as u32 * 4;
+		dwords[0x78 / 4] = words.len() as u32 * 2;
+		dwords.extend(words.chunks(2).map(|pair| pair[0] as u32 | (pair[1] as u32) << 16));
+		dwords[1] = dwords.len() as u32 * 4;
+		dwords
+	}
+	fn bytes(dwords: &[u32]) -> &[u8] {
+		unsafe { slice::from_raw_parts(dwords.as_ptr() as *const u8, dwords.len() * 4) }
+	}
+
+	#[test]
+	fn parse() {
+		let dwords = config(MUI_FILETYPE_MUI, 42, "en-US");
+		let config = MuiConfig::new(bytes(&dwords)).unwrap();
+		assert!(config.is_mui());
+		assert_eq!(config.fallback_location(), MUI_FALLBACK_EXTERNAL);
+		assert_eq!(config.checksum()[0], 42);
+		assert_eq!(config.mui_id_types(), Ok(&[6, 16][..]));
+		let names: Vec<String> = config.mui_name_types().unwrap().map(|name| name.to_string().unwrap()).collect();
+		assert_eq!(names, ["MUI"]);
+		assert_eq!(config.main_id_types(), Ok(&[][..]));
+		assert_eq!(config.main_name_types().unwrap().count(), 0);
+		assert!(config.language().unwrap() == "en-US");
+		assert_eq!(config.fallback_language().err(), Some(Error::Null));
+	}
+
+	#[test]
+	fn verify() {
+		let ln = config(MUI_FILETYPE_LN, 1, "");
+		let ln = MuiConfig::new(bytes(&ln)).unwrap();
+		let good = config(MUI_FILETYPE_MUI, 1, "en-US");
+		let bad = config(MUI_FILETYPE_MUI, 2, "en-US");
+		assert_eq!(ln.verify(&MuiConfig::new(bytes(&good)).unwrap()), Ok(()));
+		assert_eq!(ln.verify(&MuiConfig::new(bytes(&bad)).unwrap()), Err(Error::BadMagic));
+		assert_eq!(ln.verify(&ln), Err(Error::BadMagic));
+	}
+
+	#[test]
+	fn path() {
+		let path = satellite_path("dir/notepad.exe", "en-US");
+		assert_eq!(path, Path::new("dir").join("en-US").join("notepad.exe.mui"));
+	}
+}
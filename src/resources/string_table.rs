@@ -0,0 +1,184 @@
+/*!
+String Tables.
+
+The `RT_STRING` resources store the strings in blocks of 16 length prefixed UTF-16 strings.
+The string with id `id` is found in the block with id `(id >> 4) + 1` at index `id & 15`, absent strings have zero length.
+
+# Examples
+
+```
+# #![allow(unused_variables)]
+use pelite::pe64::{Pe, PeFile};
+
+# #[allow(dead_code)]
+fn example(file: PeFile) -> pelite::Result<()> {
+	let resources = file.resources()?;
+
+	// Look up a string by its id in any language
+	let string = resources.string_table(101, None)?;
+	println!("{}", string);
+
+	// Print all the strings
+	for (id, lang, string) in resources.strings()? {
+		println!("{} {}: {}", id, lang, string);
+	}
+
+	Ok(())
+}
+```
+*/
+
+use std::{fmt, iter, slice};
+
+use error::{Error, Result};
+use image::*;
+use util::WideStr;
+
+use super::{Resources, DataEntry, Entries, Entry, Name, find_id};
+
+//----------------------------------------------------------------
+
+/// Block of 16 strings.
+#[derive(Copy, Clone)]
+pub struct StringBlock<'a> {
+	words: &'a [u16],
+}
+
+impl<'a> StringBlock<'a> {
+	/// Interprets the bytes of a string table resource.
+	///
+	/// Returns [`Err(Misalign)`](../../enum.Error.html#variant.Misalign) if the bytes aren't 16-bit aligned.
+	pub fn new(bytes: &'a [u8]) -> Result<StringBlock<'a>> {
+		if bytes.as_ptr() as usize & 1 != 0 {
+			return Err(Error::Misalign);
+		}
+		let words = unsafe { slice::from_raw_parts(bytes.as_ptr() as *const u16, bytes.len() / 2) };
+		Ok(StringBlock { words })
+	}
+	/// Gets the string at the index in this block.
+	///
+	/// Absent strings are returned as empty strings, returns `None` if the index is out of bounds.
+	pub fn get(&self, index: usize) -> Option<&'a WideStr> {
+		self.iter().nth(index)
+	}
+	/// Iterates over the strings in this block, including the empty ones.
+	pub fn iter(&self) -> BlockIter<'a> {
+		BlockIter { words: self.words, index: 0 }
+	}
+}
+
+/// Iterator over the strings of a block.
+///
+/// Stops early if the block is truncated.
+#[derive(Clone)]
+pub struct BlockIter<'a> {
+	words: &'a [u16],
+	index: usize,
+}
+impl<'a> Iterator for BlockIter<'a> {
+	type Item = &'a WideStr;
+	fn next(&mut self) -> Option<&'a WideStr> {
+		if self.index >= 16 || self.words.is_empty() {
+			return None;
+		}
+		let len = self.words[0] as usize + 1;
+		let words = match self.words.get(..len) {
+			Some(words) => words,
+			None => {
+				self.words = &[];
+				return None;
+			},
+		};
+		self.words = &self.words[len..];
+		self.index += 1;
+		// The strings are prefixed by their length
+		Some(unsafe { WideStr::from_words_unchecked(words) })
+	}
+}
+
+//----------------------------------------------------------------
+
+/// Iterator over all the strings as `(id, lang, string)` triples.
+///
+/// Empty strings and corrupt blocks are skipped.
+#[derive(Clone)]
+pub struct Strings<'a> {
+	blocks: Entries<'a>,
+	langs: Option<(u32, Entries<'a>)>,
+	strings: Option<(u32, u16, iter::Enumerate<BlockIter<'a>>)>,
+}
+impl<'a> Iterator for Strings<'a> {
+	type Item = (u16, u16, &'a WideStr);
+	fn next(&mut self) -> Option<(u16, u16, &'a WideStr)> {
+		loop {
+			// Strings in the current block
+			if let Some((base, lang, ref mut strings)) = self.strings {
+				if let Some((index, string)) = strings.find(|&(_, string)| !string.is_empty()) {
+					return Some(((base + index as u32) as u16, lang, string));
+				}
+			}
+			self.strings = None;
+			// Languages of the current block
+			if let Some((block, ref mut langs)) = self.langs {
+				if let Some(entry) = langs.next() {
+					if let (Ok(Name::Id(lang)), Ok(Entry::DataEntry(data))) = (entry.name(), entry.entry()) {
+						if let Ok(string_block) = data.data().and_then(StringBlock::new) {
+							self.strings = Some(((block - 1) << 4, lang as u16, string_block.iter().enumerate()));
+						}
+					}
+					continue;
+				}
+			}
+			self.langs = None;
+			// Next block
+			let entry = self.blocks.next()?;
+			if let (Ok(Name::Id(block)), Ok(Entry::Directory(dir))) = (entry.name(), entry.entry()) {
+				if block >= 1 && block <= 0x1000 {
+					self.langs = Some((block, dir.entries()));
+				}
+			}
+		}
+	}
+}
+
+//----------------------------------------------------------------
+
+impl<'a> DataEntry<'a> {
+	/// Interprets the data as a block of strings.
+	pub fn string_block(&self) -> Result<StringBlock<'a>> {
+		StringBlock::new(self.data()?)
+	}
+}
+
+impl<'a> Resources<'a> {
+	/// Gets a string by its id.
+	///
+	/// Takes the given language or the first language if none is given.
+	/// Returns [`Err(Null)`](../../enum.Error.html#variant.Null) if there is no such string.
+	pub fn string_table(&self, id: u16, lang: Option<u16>) -> Result<&'a WideStr> {
+		let block = (id >> 4) + 1;
+		let dir = find_id(self.type_dir(RT_STRING)?, Some(block))?.dir().ok_or(Error::Corrupt)?;
+		let data = find_id(dir, lang)?.data().ok_or(Error::Corrupt)?;
+		match data.string_block()?.get((id & 15) as usize) {
+			Some(string) if !string.is_empty() => Ok(string),
+			Some(_) => Err(Error::Null),
+			None => Err(Error::OOB),
+		}
+	}
+	/// Gets an iterator over all the strings as `(id, lang, string)` triples.
+	///
+	/// Returns [`Err(Null)`](../../enum.Error.html#variant.Null) if there are no string tables.
+	pub fn strings(&self) -> Result<Strings<'a>> {
+		let blocks = self.type_dir(RT_STRING)?.entries();
+		Ok(Strings { blocks, langs: None, strings: None })
+	}
+}
+
+//----------------------------------------------------------------
+// Formatting
+
+impl<'a> fmt::Debug for StringBlock<'a> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_list().entries(self.iter()).finish()
+	}
+}
@@ -0,0 +1,62 @@
+/*!
+Word cursor for the variable length resource templates.
+*/
+
+use std::slice;
+
+use error::{Error, Result};
+use util::WideSlice;
+
+//----------------------------------------------------------------
+
+// Cursor over the words of a dialog or menu template
+#[derive(Clone)]
+pub struct Words<'a> {
+	words: &'a [u16],
+	offset: usize,
+}
+
+impl<'a> Words<'a> {
+	pub fn new(bytes: &'a [u8]) -> Result<Words<'a>> {
+		if bytes.as_ptr() as usize & 1 != 0 {
+			return Err(Error::Misalign);
+		}
+		let words = unsafe { slice::from_raw_parts(bytes.as_ptr() as *const u16, bytes.len() / 2) };
+		Ok(Words { words, offset: 0 })
+	}
+	pub fn u16(&mut self) -> Result<u16> {
+		let word = *self.words.get(self.offset).ok_or(Error::OOB)?;
+		self.offset += 1;
+		Ok(word)
+	}
+	// Steps back one word after peeking at it
+	pub fn unread(&mut self) {
+		self.offset -= 1;
+	}
+	pub fn i16(&mut self) -> Result<i16> {
+		self.u16().map(|word| word as i16)
+	}
+	pub fn u32(&mut self) -> Result<u32> {
+		let lo = self.u16()?;
+		let hi = self.u16()?;
+		Ok(lo as u32 | (hi as u32) << 16)
+	}
+	// Aligns to 32-bit relative to the start of the template
+	pub fn align(&mut self) {
+		self.offset = usize::min((self.offset + 1) & !1, self.words.len());
+	}
+	// Reads a nul terminated string
+	pub fn sz(&mut self) -> Result<&'a WideSlice> {
+		let len = self.words.get(self.offset..).ok_or(Error::OOB)?
+			.iter().position(|&word| word == 0).ok_or(Error::OOB)?;
+		let string = WideSlice::from_words(&self.words[self.offset..self.offset + len]);
+		self.offset += len + 1;
+		Ok(string)
+	}
+	// Reads bytes, the length is rounded up to whole words
+	pub fn bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+		let words = self.words.get(self.offset..self.offset + (len + 1) / 2).ok_or(Error::OOB)?;
+		self.offset += words.len();
+		Ok(unsafe { slice::from_raw_parts(words.as_ptr() as *const u8, len) })
+	}
+}
@@ -0,0 +1,371 @@
+/*!
+Version Information.
+
+The version resource contains a `VS_VERSIONINFO` structure, a hierarchy of 32-bit aligned blocks each with a UTF-16 key and an optional value:
+
+* The root block `VS_VERSION_INFO` with the `VS_FIXEDFILEINFO` as its value.
+* The `StringFileInfo` block with a string table for every language and code page, the key is the language and code page in hexadecimal eg. `040904B0`.
+* The `VarFileInfo` block with a `Translation` value listing the languages and code pages of the string tables.
+
+# Examples
+
+```
+# #![allow(unused_variables)]
+use pelite::pe64::{Pe, PeFile};
+
+# #[allow(dead_code)]
+fn example(file: PeFile) -> pelite::Result<()> {
+	// Access the version info in the resources
+	let resources = file.resources()?;
+	let version_info = resources.version_info()?;
+
+	// Print the file version from the fixed file info
+	if let Some(version) = version_info.file_version() {
+		println!("FileVersion: {}", version);
+	}
+
+	// Print the product name for every language
+	for &lang in version_info.translation() {
+		if let Some(product_name) = version_info.value(lang, "ProductName") {
+			println!("{}: {}", lang, product_name);
+		}
+	}
+
+	Ok(())
+}
+```
+*/
+
+use std::{fmt, mem, slice};
+
+use error::{Error, Result};
+use image::*;
+use util::WideSlice;
+
+use super::{Resources, DataEntry};
+
+//----------------------------------------------------------------
+
+/// Language and code page pair.
+///
+/// Identifies a string table and is listed in the `VarFileInfo\Translation` value.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[repr(C)]
+pub struct Language {
+	/// Language identifier, eg. `0x0409` for English (United States).
+	pub lang_id: u16,
+	/// Code page, eg. `1200` for UTF-16.
+	pub charset_id: u16,
+}
+
+impl Language {
+	/// Parses the language from a string table key.
+	///
+	/// The key is made up of 8 hexadecimal digits, the language identifier followed by the code page.
+	pub fn from_key(key: &WideSlice) -> Option<Language> {
+		if key.len() != 8 {
+			return None;
+		}
+		let mut value = 0u32;
+		for &word in key.iter() {
+			if word >= 0x80 {
+				return None;
+			}
+			value = value << 4 | (word as u8 as char).to_digit(16)?;
+		}
+		Some(Language { lang_id: (value >> 16) as u16, charset_id: value as u16 })
+	}
+}
+
+/// Four part version number.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct Version {
+	pub major: u16,
+	pub minor: u16,
+	pub patch: u16,
+	pub build: u16,
+}
+
+impl Version {
+	/// Constructs the version from the most and least significant dwords as stored in the `VS_FIXEDFILEINFO`.
+	pub fn from_ms_ls(ms: u32, ls: u32) -> Version {
+		Version {
+			major: (ms >> 16) as u16,
+			minor: ms as u16,
+			patch: (ls >> 16) as u16,
+			build: ls as u16,
+		}
+	}
+}
+
+//----------------------------------------------------------------
+
+// Every block in the version info hierarchy, the value is in words
+#[derive(Copy, Clone)]
+struct Block<'a> {
+	key: &'a WideSlice,
+	is_text: bool,
+	value: &'a [u16],
+	children: &'a [u16],
+}
+
+// Aligns a word offset to 32-bit
+fn align(offset: usize) -> usize {
+	(offset + 1) & !1
+}
+
+// Parses the block at the start of the words, returns the block and the words following it
+fn parse_block(words: &[u16]) -> Result<(Block, &[u16])> {
+	if words.len() < 3 {
+		return Err(Error::OOB);
+	}
+	// The header is wLength, wValueLength and wType
+	let len = (words[0] as usize + 1) / 2;
+	if len < 3 {
+		return Err(Error::Corrupt);
+	}
+	let block = words.get(..len).ok_or(Error::OOB)?;
+	let is_text = block[2] == 1;
+	// The key follows the header and is nul terminated
+	let key_len = block[3..].iter().position(|&word| word == 0).ok_or(Error::Corrupt)?;
+	let key = WideSlice::from_words(&block[3..3 + key_len]);
+	// The value length is in words for text and in bytes for binary values
+	let value_start = align(3 + key_len + 1);
+	let value_len = if is_text { block[1] as usize } else { (block[1] as usize + 1) / 2 };
+	// Some compilers write the length of text values in bytes, stay within the block
+	let value_start = value_start.min(len);
+	let value_end = usize::min(value_start + value_len, len);
+	let value = &block[value_start..value_end];
+	let children = &block[usize::min(align(value_end), len)..];
+	let rest = &words[usize::min(align(len), words.len())..];
+	Ok((Block { key, is_text, value, children }, rest))
+}
+
+impl<'a> Block<'a> {
+	// Interprets the value as text without its nul terminator
+	fn text(&self) -> Option<&'a WideSlice> {
+		if !self.is_text || self.value.is_empty() {
+			return None;
+		}
+		let len = self.value.iter().position(|&word| word == 0).unwrap_or(self.value.len());
+		Some(WideSlice::from_words(&self.value[..len]))
+	}
+	fn blocks(&self) -> Blocks<'a> {
+		Blocks { words: self.children }
+	}
+	fn child(&self, key: &str) -> Option<Block<'a>> {
+		self.blocks().find(|block| *block.key == *key)
+	}
+}
+
+// Iterator over the child blocks, stops at the first corrupt block
+#[derive(Clone)]
+struct Blocks<'a> {
+	words: &'a [u16],
+}
+impl<'a> Iterator for Blocks<'a> {
+	type Item = Block<'a>;
+	fn next(&mut self) -> Option<Block<'a>> {
+		if self.words.is_empty() {
+			return None;
+		}
+		match parse_block(self.words) {
+			Ok((block, rest)) => {
+				self.words = rest;
+				Some(block)
+			},
+			Err(_) => {
+				self.words = &[];
+				None
+			},
+		}
+	}
+}
+
+//----------------------------------------------------------------
+
+/// Version info.
+#[derive(Copy, Clone)]
+pub struct VersionInfo<'a> {
+	root: Block<'a>,
+}
+
+impl<'a> VersionInfo<'a> {
+	/// Interprets the bytes of the version resource.
+	///
+	/// Returns [`Err(Misalign)`](../../enum.Error.html#variant.Misalign) if the bytes aren't 16-bit aligned
+	/// and [`Err(BadMagic)`](../../enum.Error.html#variant.BadMagic) if the root block isn't `VS_VERSION_INFO`.
+	pub fn try_from(bytes: &'a [u8]) -> Result<VersionInfo<'a>> {
+		if bytes.as_ptr() as usize & 1 != 0 {
+			return Err(Error::Misalign);
+		}
+		let words = unsafe { slice::from_raw_parts(bytes.as_ptr() as *const u16, bytes.len() / 2) };
+		let (root, _) = parse_block(words)?;
+		if *root.key != *"VS_VERSION_INFO" {
+			return Err(Error::BadMagic);
+		}
+		Ok(VersionInfo { root })
+	}
+	/// Gets the fixed file info.
+	///
+	/// Returns `None` if it is missing or its signature doesn't match.
+	pub fn fixed(&self) -> Option<&'a VS_FIXEDFILEINFO> {
+		let value = self.root.value;
+		if value.len() * 2 < mem::size_of::<VS_FIXEDFILEINFO>() {
+			return None;
+		}
+		let fixed = unsafe { &*(value.as_ptr() as *const VS_FIXEDFILEINFO) };
+		if fixed.dwSignature != VS_FFI_SIGNATURE {
+			return None;
+		}
+		Some(fixed)
+	}
+	/// Gets the file version from the fixed file info.
+	pub fn file_version(&self) -> Option<Version> {
+		self.fixed().map(|fixed| Version::from_ms_ls(fixed.dwFileVersionMS, fixed.dwFileVersionLS))
+	}
+	/// Gets the product version from the fixed file info.
+	pub fn product_version(&self) -> Option<Version> {
+		self.fixed().map(|fixed| Version::from_ms_ls(fixed.dwProductVersionMS, fixed.dwProductVersionLS))
+	}
+	/// Gets the string tables in the `StringFileInfo` block.
+	pub fn string_tables(&self) -> StringTables<'a> {
+		let blocks = match self.root.child("StringFileInfo") {
+			Some(string_file_info) => string_file_info.blocks(),
+			None => Blocks { words: &[] },
+		};
+		StringTables { blocks }
+	}
+	/// Gets the string table for the language.
+	pub fn string_table(&self, lang: Language) -> Option<StringTable<'a>> {
+		self.string_tables().find(|table| table.lang() == Some(lang))
+	}
+	/// Looks up a string, eg. `FileDescription` or `ProductName`, in the string table for the language.
+	pub fn value(&self, lang: Language, key: &str) -> Option<&'a WideSlice> {
+		self.string_table(lang).and_then(|table| table.get(key))
+	}
+	/// Gets the languages listed in the `VarFileInfo\Translation` value.
+	pub fn translation(&self) -> &'a [Language] {
+		let value = self.root.child("VarFileInfo")
+			.and_then(|var_file_info| var_file_info.child("Translation"))
+			.map(|translation| translation.value)
+			.unwrap_or(&[]);
+		unsafe { slice::from_raw_parts(value.as_ptr() as *const Language, value.len() / 2) }
+	}
+}
+
+//----------------------------------------------------------------
+
+/// String table for a language and code page.
+#[derive(Copy, Clone)]
+pub struct StringTable<'a> {
+	block: Block<'a>,
+}
+impl<'a> StringTable<'a> {
+	/// Gets the key of the string table.
+	pub fn key(&self) -> &'a WideSlice {
+		self.block.key
+	}
+	/// Parses the language and code page from the key.
+	pub fn lang(&self) -> Option<Language> {
+		Language::from_key(self.block.key)
+	}
+	/// Gets the key value pairs.
+	pub fn strings(&self) -> Strings<'a> {
+		Strings { blocks: self.block.blocks() }
+	}
+	/// Looks up a string by its key.
+	pub fn get(&self, key: &str) -> Option<&'a WideSlice> {
+		self.strings().find(|&(k, _)| *k == *key).map(|(_, value)| value)
+	}
+}
+
+/// Iterator over the string tables.
+#[derive(Clone)]
+pub struct StringTables<'a> {
+	blocks: Blocks<'a>,
+}
+impl<'a> Iterator for StringTables<'a> {
+	type Item = StringTable<'a>;
+	fn next(&mut self) -> Option<StringTable<'a>> {
+		self.blocks.next().map(|block| StringTable { block })
+	}
+}
+
+/// Iterator over the key value pairs of a string table.
+///
+/// Strings without a value are returned as empty strings.
+#[derive(Clone)]
+pub struct Strings<'a> {
+	blocks: Blocks<'a>,
+}
+impl<'a> Iterator for Strings<'a> {
+	type Item = (&'a WideSlice, &'a WideSlice);
+	fn next(&mut self) -> Option<(&'a WideSlice, &'a WideSlice)> {
+		self.blocks.next().map(|block| {
+			let value = block.text().unwrap_or_else(|| WideSlice::from_words(&[]));
+			(block.key, value)
+		})
+	}
+}
+
+//----------------------------------------------------------------
+
+impl<'a> DataEntry<'a> {
+	/// Interprets the data as version info.
+	pub fn version_info(&self) -> Result<VersionInfo<'a>> {
+		VersionInfo::try_from(self.data()?)
+	}
+}
+
+impl<'a> Resources<'a> {
+	/// Gets the version info.
+	///
+	/// Takes the first language of the first version resource.
+	/// Returns [`Err(Null)`](../../enum.Error.html#variant.Null) if there is no version resource.
+	pub fn version_info(&self) -> Result<VersionInfo<'a>> {
+		self.first_data(RT_VERSION, None)?.version_info()
+	}
+}
+
+//----------------------------------------------------------------
+// Formatting
+
+impl fmt::Display for Language {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{:04X}{:04X}", self.lang_id, self.charset_id)
+	}
+}
+
+impl fmt::Display for Version {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}.{}.{}.{}", self.major, self.minor, self.patch, self.build)
+	}
+}
+
+impl<'a> fmt::Debug for VersionInfo<'a> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.write_str("Version Info\n")?;
+		if let Some(fixed) = self.fixed() {
+			fixed.fmt(f)?;
+		}
+		for table in self.string_tables() {
+			write!(f, "{:?}", table)?;
+		}
+		f.write_str("Translation\n")?;
+		for lang in self.translation() {
+			write!(f, "  {}\n", lang)?;
+		}
+		Ok(())
+	}
+}
+
+impl<'a> fmt::Debug for StringTable<'a> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "String Table {}\n", self.key())?;
+		for (key, value) in self.strings() {
+			write!(f, "  {}: {:?}\n", key, value)?;
+		}
+		Ok(())
+	}
+}
@@ -0,0 +1,240 @@
+/*!
+Signature Database.
+
+Named [patterns](../pattern/index.html) kept in a text file and checked against every new build of a program.
+
+# Format
+
+Every line holds a signature, blank lines and lines starting with `#` are ignored:
+
+```text
+NAME [count=COUNT] [slot=SLOT]: PATTERN
+```
+
+* `NAME` identifies the signature and must be unique within the database.
+* `COUNT` is the number of matches expected, defaults to `1`.
+  A signature whose number of matches differs has gone stale and must be updated.
+* `SLOT` is the save slot reported for every match, either its index or the name of a named capture.
+  Defaults to `0`, the start of the match.
+* `PATTERN` is the pattern in the syntax accepted by [`parse_named`](../pattern/fn.parse_named.html).
+
+```text
+# Demo signatures
+Checksum: 4C8B41'? 4C2BC2 ????????? 0FB60A 420FB60402 2BC8 75% 8B15${'} 85 C9
+g_Table slot=table: 4C8B41'? 4C2BC2 ????????? 0FB60A 420FB60402 2BC8 75% 8B15${'<table>} 85 C9
+Calls count=3: E8${'}
+```
+
+Run the database with the `pescan` tool or the scanners of [PE32](../pe32/scanner/index.html) and [PE32+](../pe64/scanner/index.html).
+
+# Examples
+
+```
+# #![allow(unused_variables)]
+use pelite::pe64::{Pe, PeFile};
+use pelite::sigdb;
+
+# #[allow(dead_code)]
+fn example(file: PeFile, text: &str) -> Result<(), sigdb::ParseDbError> {
+	let scanner = file.scanner();
+	for entry in sigdb::parse(text)? {
+		// Collect the reported slot of every match
+		let mut save = vec![0; entry.save_len()];
+		let mut matches = scanner.matches_code(&entry.pattern);
+		let mut found = Vec::new();
+		while matches.next_into(&mut save) {
+			found.push(save[entry.slot]);
+		}
+		if found.len() != entry.count {
+			println!("{} is stale: {} matches", entry.name, found.len());
+		}
+	}
+	Ok(())
+}
+```
+*/
+
+use std::{error, fmt, fs, io};
+use std::path::Path;
+
+use pattern as pat;
+
+//----------------------------------------------------------------
+
+/// Signature database parsing error.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ParseDbError {
+	line: usize,
+	kind: DbError,
+}
+impl ParseDbError {
+	/// Returns the line number of the error, starting from `1`.
+	pub fn line(&self) -> usize {
+		self.line
+	}
+	/// Returns the pattern error if the pattern is invalid.
+	pub fn pattern_error(&self) -> Option<pat::ParsePatError> {
+		match self.kind {
+			DbError::Pattern(err) => Some(err),
+			_ => None,
+		}
+	}
+}
+
+impl fmt::Display for ParseDbError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self.kind {
+			DbError::Pattern(ref err) => write!(f, "line {}: {}: {}", self.line, error::Error::description(self), err),
+			_ => write!(f, "line {}: {}", self.line, error::Error::description(self)),
+		}
+	}
+}
+
+impl error::Error for ParseDbError {
+	fn description(&self) -> &str {
+		match self.kind {
+			DbError::SyntaxError => "invalid syntax",
+			DbError::Pattern(_) => "invalid pattern",
+			DbError::BadCount => "bad count",
+			DbError::BadSlot => "bad slot",
+			DbError::DuplicateName => "duplicate name",
+		}
+	}
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum DbError {
+	SyntaxError,
+	Pattern(pat::ParsePatError),
+	BadCount,
+	BadSlot,
+	DuplicateName,
+}
+
+//----------------------------------------------------------------
+
+/// Signature database entry.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Entry {
+	/// Name of the signature.
+	pub name: String,
+	/// The parsed pattern.
+	pub pattern: pat::Pattern,
+	/// Number of matches expected.
+	pub count: usize,
+	/// Save slot reported for every match.
+	pub slot: usize,
+	/// Line number of the entry in the database, starting from `1`.
+	pub line: usize,
+}
+impl Entry {
+	/// Returns the size of the save buffer holding all the save slots of the pattern.
+	pub fn save_len(&self) -> usize {
+		save_len(&self.pattern)
+	}
+}
+
+// Returns the number of save slots written by the pattern
+fn save_len(pat: &[pat::Atom]) -> usize {
+	pat.iter().map(|&atom| match atom {
+		pat::Atom::Save(slot) | pat::Atom::ReadU8(slot) | pat::Atom::ReadI8(slot) | pat::Atom::ReadU16(slot) |
+		pat::Atom::ReadI16(slot) | pat::Atom::ReadU32(slot) | pat::Atom::ReadI32(slot) => slot as usize + 1,
+		pat::Atom::ReadU64(slot) => slot as usize + 2,
+		_ => 0,
+	}).max().unwrap_or(0)
+}
+
+/// Parses the signature database.
+///
+/// # Examples
+///
+/// ```
+/// use pelite::sigdb;
+///
+/// let entries = sigdb::parse("# Comment\nCallers count=2 slot=target: E8${'<target>}\n").unwrap();
+/// assert_eq!(entries[0].name, "Callers");
+/// assert_eq!((entries[0].count, entries[0].slot, entries[0].line), (2, 1, 2));
+///
+/// let err = sigdb::parse("Main: 4883EC28\nMain: E8${'}").unwrap_err();
+/// assert_eq!(err.to_string(), "line 2: duplicate name");
+/// ```
+pub fn parse(text: &str) -> Result<Vec<Entry>, ParseDbError> {
+	let mut entries: Vec<Entry> = Vec::new();
+	for (i, line) in text.lines().enumerate() {
+		let line_nr = i + 1;
+		let error = |kind| ParseDbError { line: line_nr, kind };
+		let line = line.trim();
+		if line.len() == 0 || line.starts_with('#') {
+			continue;
+		}
+		// The header ends at the first colon, patterns may contain colons in quotes
+		let colon = line.find(':').ok_or(error(DbError::SyntaxError))?;
+		let (pattern, names) = pat::parse_named(line[colon + 1..].trim()).map_err(|err| error(DbError::Pattern(err)))?;
+		let mut fields = line[..colon].split_whitespace();
+		let name = fields.next().ok_or(error(DbError::SyntaxError))?;
+		if entries.iter().any(|entry| entry.name == name) {
+			return Err(error(DbError::DuplicateName));
+		}
+		let mut count = 1;
+		let mut slot = 0;
+		for field in fields {
+			let eq = field.find('=').ok_or(error(DbError::SyntaxError))?;
+			let value = &field[eq + 1..];
+			match &field[..eq] {
+				"count" => count = value.parse().map_err(|_| error(DbError::BadCount))?,
+				"slot" => slot = value.parse().ok().or_else(|| names.slot(value)).ok_or(error(DbError::BadSlot))?,
+				_ => return Err(error(DbError::SyntaxError)),
+			}
+		}
+		if slot >= save_len(&pattern) {
+			return Err(error(DbError::BadSlot));
+		}
+		entries.push(Entry { name: name.to_string(), pattern, count, slot, line: line_nr });
+	}
+	Ok(entries)
+}
+
+/// Loads the signature database from a file.
+///
+/// Parsing errors are returned as [`io::ErrorKind::InvalidData`](https://doc.rust-lang.org/std/io/enum.ErrorKind.html).
+pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Vec<Entry>> {
+	let text = fs::read_to_string(path)?;
+	parse(&text).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err.to_string()))
+}
+
+//----------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn entries() {
+		let text = "\n  # Comment\nMain: 4883EC28 E8${'}\n\tTarget slot=1 : E8${'}\nValue slot=off count=0: 4C8B41 u1<off> \":\"\n";
+		let entries = parse(text).unwrap();
+		assert_eq!(entries.len(), 3);
+		assert_eq!((&*entries[0].name, entries[0].count, entries[0].slot, entries[0].line), ("Main", 1, 0, 3));
+		assert_eq!(entries[0].pattern, pat::parse("4883EC28 E8${'}").unwrap());
+		assert_eq!(entries[0].save_len(), 2);
+		assert_eq!((&*entries[1].name, entries[1].slot), ("Target", 1));
+		assert_eq!((&*entries[2].name, entries[2].count, entries[2].slot), ("Value", 0, 1));
+	}
+
+	#[test]
+	fn errors() {
+		let check = |text: &str, line, message: &str| {
+			let err = parse(text).unwrap_err();
+			assert_eq!((err.line(), err.to_string()), (line, message.to_string()));
+		};
+		check("Main 4883EC28", 1, "line 1: invalid syntax");
+		check("\n: 4883EC28", 2, "line 2: invalid syntax");
+		check("Main size=1: 4883EC28", 1, "line 1: invalid syntax");
+		check("Main count: 4883EC28", 1, "line 1: invalid syntax");
+		check("Main count=-1: 4883EC28", 1, "line 1: bad count");
+		check("Main slot=1: 4883EC28", 1, "line 1: bad slot");
+		check("Main slot=foo: 4883EC28 '<bar>", 1, "line 1: bad slot");
+		check("Main: 4883EC28\nMain: 4883EC28", 2, "line 2: duplicate name");
+		check("Main: 4883EC2", 1, "line 1: invalid pattern: unpaired hex digit");
+		assert!(parse("Main: 4883EC2").unwrap_err().pattern_error().is_some());
+	}
+}
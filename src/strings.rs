@@ -506,6 +506,44 @@ impl fmt::Debug for IMAGE_RESOURCE_DATA_ENTRY {
 	}
 }
 
+static VS_FF_STRINGS: [&str; 6] = ["DEBUG", "PRERELEASE", "PATCHED", "PRIVATEBUILD", "INFOINFERRED", "SPECIALBUILD"];
+fn stringify_file_type(ty: u32) -> Option<&'static str> {
+	match ty {
+		VFT_UNKNOWN => Some("Unknown"),
+		VFT_APP => Some("App"),
+		VFT_DLL => Some("DLL"),
+		VFT_DRV => Some("Driver"),
+		VFT_FONT => Some("Font"),
+		VFT_VXD => Some("VxD"),
+		VFT_STATIC_LIB => Some("Static Lib"),
+		_ => None,
+	}
+}
+impl fmt::Debug for VS_FIXEDFILEINFO {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f,
+			#"Fixed File Info\n",
+			#"  Signature:      {:·>8X}\n", self.dwSignature,
+			#"  StrucVersion:   {}.{}\n", self.dwStrucVersion >> 16, self.dwStrucVersion & 0xffff,
+			#"  FileVersion:    {}.{}.{}.{}\n", self.dwFileVersionMS >> 16, self.dwFileVersionMS & 0xffff, self.dwFileVersionLS >> 16, self.dwFileVersionLS & 0xffff,
+			#"  ProductVersion: {}.{}.{}.{}\n", self.dwProductVersionMS >> 16, self.dwProductVersionMS & 0xffff, self.dwProductVersionLS >> 16, self.dwProductVersionLS & 0xffff,
+			#"  FileFlagsMask:  {:·>8X}\n", self.dwFileFlagsMask,
+			#"  FileFlags:      {:·>8X}{}\n", self.dwFileFlags, Fmt(|f| {
+				for (i, name) in VS_FF_STRINGS.iter().enumerate() {
+					if self.dwFileFlags & self.dwFileFlagsMask & (1 << i) != 0 {
+						write!(f, " {}", name)?;
+					}
+				}
+				Ok(())
+			}),
+			#"  FileOS:         {:·>8X}\n", self.dwFileOS,
+			#"  FileType:       {}{}\n", self.dwFileType, Fmt(|f| stringify_file_type(self.dwFileType).map(|ty| write!(f, ": {}", ty)).unwrap_or(Ok(()))),
+			#"  FileSubtype:    {}\n", self.dwFileSubtype,
+			#"  FileDate:       {:·>8X}{:·>8X}\n", self.dwFileDateMS, self.dwFileDateLS,
+		)
+	}
+}
+
 //----------------------------------------------------------------
 
 pub fn stringify_reloc_type(tyoff: &IMAGE_BASE_RELOC_TYPEOFFSET) -> Option<&'static str> {
@@ -603,6 +641,15 @@ fn stringify_debug_type(ty: u32) -> Option<&'static str> {
 		IMAGE_DEBUG_TYPE_OMAP_FROM_SRC => Some("OMAP from src"),
 		IMAGE_DEBUG_TYPE_BORLAND => Some("Borland"),
 		IMAGE_DEBUG_TYPE_CLSID => Some("CLSID"),
+		IMAGE_DEBUG_TYPE_VC_FEATURE => Some("VC Feature"),
+		IMAGE_DEBUG_TYPE_POGO => Some("POGO"),
+		IMAGE_DEBUG_TYPE_ILTCG => Some("ILTCG"),
+		IMAGE_DEBUG_TYPE_MPX => Some("MPX"),
+		IMAGE_DEBUG_TYPE_REPRO => Some("Repro"),
+		IMAGE_DEBUG_TYPE_EMBEDDED_PORTABLE_PDB => Some("Embedded Portable PDB"),
+		IMAGE_DEBUG_TYPE_SPGO => Some("SPGO"),
+		IMAGE_DEBUG_TYPE_PDBCHECKSUM => Some("PDB Checksum"),
+		IMAGE_DEBUG_TYPE_EX_DLLCHARACTERISTICS => Some("Ex DllCharacteristics"),
 		_ => None,
 	}
 }
@@ -650,3 +697,33 @@ impl fmt::Debug for IMAGE_DEBUG_MISC {
 		)
 	}
 }
+impl fmt::Debug for IMAGE_DEBUG_VC_FEATURE {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f,
+			#"VC Feature\n",
+			#"  Pre-VC++ 11.00:   {}\n", self.PreVCPP11,
+			#"  C/C++:            {}\n", self.CCpp,
+			#"  /GS:              {}\n", self.GS,
+			#"  /sdl:             {}\n", self.SDL,
+			#"  guardN:           {}\n", self.GuardN,
+		)
+	}
+}
+impl fmt::Debug for FPO_DATA {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let frame = match self.Attributes >> 14 {
+			FRAME_FPO => "FPO",
+			FRAME_TRAP => "TRAP",
+			FRAME_TSS => "TSS",
+			_ => "NONFPO",
+		};
+		write!(f,
+			#"FPO\n",
+			#"  OffStart:         {:·>8X}\n", self.ulOffStart,
+			#"  ProcSize:         {:·>8X}\n", self.cbProcSize,
+			#"  Locals:           {}\n", self.cdwLocals,
+			#"  Params:           {}\n", self.cdwParams,
+			#"  Attributes:       {:·>4X}: {}\n", self.Attributes, frame,
+		)
+	}
+}
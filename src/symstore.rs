@@ -0,0 +1,234 @@
+/*!
+Symbol Store.
+
+Microsoft-style symbol stores file PDBs and images under a key derived from their debug info or headers:
+
+* `name.pdb/GUIDAGE/name.pdb` for PDBs referenced by a CodeView RSDS record.
+* `name.pdb/SIGAGE/name.pdb` for PDBs referenced by a CodeView NB10 record.
+* `name.dll/TIMESTAMPSIZEOFIMAGE/name.dll` for the images themselves.
+
+This module computes these keys and looks them up in local stores, either on disk or on a network share.
+
+# Examples
+
+```
+# #![allow(unused_variables)]
+use pelite::pe64::{Pe, PeFile};
+use pelite::symstore::{Key, SymStore};
+
+# #[allow(dead_code)]
+fn example(file: PeFile) -> pelite::Result<()> {
+	let store = SymStore::new(&["C:\\Symbols", "\\\\server\\symbols"]);
+
+	for dir in file.debug()? {
+		// Compute the key of the referenced PDB
+		if let Some(key) = dir.info()?.symstore_key() {
+			// Find it in the symbol store
+			if let Some(found) = store.find(&key) {
+				println!("{} -> {}", key, found.path.display());
+			}
+		}
+	}
+
+	// Compute the key of the image itself
+	let key = Key::image("Demo64.dll", file.file_header().TimeDateStamp, file.optional_header().SizeOfImage);
+	println!("{}", key);
+
+	Ok(())
+}
+```
+*/
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use image::GUID;
+
+//----------------------------------------------------------------
+
+/// Symbol store key.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct Key {
+	file_name: String,
+	index: String,
+}
+
+impl Key {
+	/// Key of a PDB referenced by a CodeView RSDS record.
+	///
+	/// The directory part of the PDB path is stripped.
+	pub fn pdb70(pdb_file_name: &str, guid: &GUID, age: u32) -> Key {
+		let d = guid.Data4;
+		let index = format!("{:08X}{:04X}{:04X}{:02X}{:02X}{:02X}{:02X}{:02X}{:02X}{:02X}{:02X}{:X}",
+			{ guid.Data1 }, { guid.Data2 }, { guid.Data3 }, d[0], d[1], d[2], d[3], d[4], d[5], d[6], d[7], age);
+		Key { file_name: base_name(pdb_file_name).to_owned(), index }
+	}
+	/// Key of a PDB referenced by a CodeView NB10 record.
+	///
+	/// The directory part of the PDB path is stripped.
+	pub fn pdb20(pdb_file_name: &str, signature: u32, age: u32) -> Key {
+		let index = format!("{:08X}{:X}", signature, age);
+		Key { file_name: base_name(pdb_file_name).to_owned(), index }
+	}
+	/// Key of an image from the file header `TimeDateStamp` and optional header `SizeOfImage`.
+	pub fn image(file_name: &str, time_date_stamp: u32, size_of_image: u32) -> Key {
+		let index = format!("{:08X}{:X}", time_date_stamp, size_of_image);
+		Key { file_name: base_name(file_name).to_owned(), index }
+	}
+	/// Returns the file name.
+	pub fn file_name(&self) -> &str {
+		&self.file_name
+	}
+	/// Returns the index directory name.
+	pub fn index(&self) -> &str {
+		&self.index
+	}
+	/// Returns the relative path of the file in a symbol store.
+	pub fn path(&self) -> PathBuf {
+		[&self.file_name, &self.index, &self.file_name].iter().collect()
+	}
+	/// Returns the relative path of the compressed file in a symbol store.
+	///
+	/// Compressed files have the last character of their extension replaced by an underscore, eg. `name.pd_`.
+	pub fn compressed_path(&self) -> PathBuf {
+		let mut compressed = self.file_name.clone();
+		compressed.pop();
+		compressed.push('_');
+		[&self.file_name, &self.index, &compressed].iter().collect()
+	}
+}
+
+// Strips both Windows and Unix style directories.
+fn base_name(path: &str) -> &str {
+	path.rsplit(|chr| chr == '\\' || chr == '/').next().unwrap_or(path)
+}
+
+//----------------------------------------------------------------
+
+/// File found in a symbol store.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Found {
+	/// Path to the file.
+	pub path: PathBuf,
+	/// The file is stored compressed as a cabinet archive and must be expanded before use.
+	pub compressed: bool,
+}
+
+/// Local symbol stores.
+#[derive(Clone, Debug, Default)]
+pub struct SymStore {
+	roots: Vec<PathBuf>,
+}
+
+impl SymStore {
+	/// Creates a resolver searching the given store roots in order.
+	pub fn new<P: AsRef<Path>>(roots: &[P]) -> SymStore {
+		SymStore { roots: roots.iter().map(|root| root.as_ref().to_path_buf()).collect() }
+	}
+	/// Adds a store root to search after the existing ones.
+	pub fn add_root<P: AsRef<Path>>(&mut self, root: P) {
+		self.roots.push(root.as_ref().to_path_buf());
+	}
+	/// Returns the store roots.
+	pub fn roots(&self) -> &[PathBuf] {
+		&self.roots
+	}
+	/// Finds the file for a key in the store roots.
+	///
+	/// Two-tier stores are recognized by an `index2.txt` file in their root, they file everything in a subdirectory named after the first two characters of the file name.
+	/// Uncompressed files are preferred over compressed files in the same store.
+	pub fn find(&self, key: &Key) -> Option<Found> {
+		// The stores are case-insensitive on Windows but not necessarily on network shares
+		let lower = Key { file_name: key.file_name.to_lowercase(), index: key.index.to_lowercase() };
+		let keys = [key, &lower];
+		let keys = if lower == *key { &keys[..1] } else { &keys[..] };
+		for root in &self.roots {
+			let two_tier = root.join("index2.txt").is_file();
+			for key in keys {
+				let root = if two_tier {
+					let prefix: String = key.file_name.chars().take(2).collect();
+					root.join(prefix)
+				}
+				else {
+					root.clone()
+				};
+				let path = root.join(key.path());
+				if path.is_file() {
+					return Some(Found { path, compressed: false });
+				}
+				let path = root.join(key.compressed_path());
+				if path.is_file() {
+					return Some(Found { path, compressed: true });
+				}
+			}
+		}
+		None
+	}
+}
+
+//----------------------------------------------------------------
+// Formatting
+
+impl fmt::Display for Key {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}/{}/{}", self.file_name, self.index, self.file_name)
+	}
+}
+
+//----------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn keys() {
+		let guid = GUID { Data1: 0x1234ABCD, Data2: 0x0A0B, Data3: 0xC0D, Data4: [0, 1, 2, 3, 0xA4, 0xB5, 0xC6, 0xD7] };
+		let key = Key::pdb70(r"D:\Projects\pelite\Demo.pdb", &guid, 0x1A);
+		assert_eq!(key.to_string(), "Demo.pdb/1234ABCD0A0B0C0D00010203A4B5C6D71A/Demo.pdb");
+		assert_eq!(key.compressed_path(), Path::new("Demo.pdb/1234ABCD0A0B0C0D00010203A4B5C6D71A/Demo.pd_"));
+
+		let key = Key::pdb20("/tmp/Demo.pdb", 0x3F2A0B1C, 2);
+		assert_eq!(key.to_string(), "Demo.pdb/3F2A0B1C2/Demo.pdb");
+
+		let key = Key::image("Demo64.dll", 0x59A41C3E, 0xE000);
+		assert_eq!(key.to_string(), "Demo64.dll/59A41C3EE000/Demo64.dll");
+	}
+
+	#[test]
+	fn find() {
+		use std::{env, fs, process};
+
+		let temp = env::temp_dir().join(format!("pelite-symstore-{}", process::id()));
+		let create = |path: PathBuf| {
+			fs::create_dir_all(path.parent().unwrap()).unwrap();
+			fs::write(path, b"").unwrap();
+		};
+		let key = Key::pdb20("Demo.pdb", 0x3F2A0B1C, 0xA);
+
+		// Flat store with the uncompressed and compressed files
+		let flat = temp.join("flat");
+		create(flat.join("Demo.pdb/3F2A0B1CA/Demo.pdb"));
+		create(flat.join("Demo.pdb/3F2A0B1CA/Demo.pd_"));
+		// Two-tier store with only the compressed file
+		let two_tier = temp.join("two-tier");
+		create(two_tier.join("index2.txt"));
+		create(two_tier.join("De/Demo.pdb/3F2A0B1CB/Demo.pd_"));
+		// Lowercase store filed by a case-insensitive file system
+		let lower = temp.join("lower");
+		create(lower.join("index2.txt"));
+		create(lower.join("de/demo.pdb/3f2a0b1cc/demo.pdb"));
+
+		let store = SymStore::new(&[&temp.join("missing"), &flat, &two_tier, &lower]);
+		let found = store.find(&key);
+		let found_two_tier = store.find(&Key::pdb20("Demo.pdb", 0x3F2A0B1C, 0xB));
+		let found_lower = store.find(&Key::pdb20("Demo.pdb", 0x3F2A0B1C, 0xC));
+		let not_found = store.find(&Key::pdb20("Demo.pdb", 0x3F2A0B1C, 0xD));
+		fs::remove_dir_all(&temp).unwrap();
+
+		assert_eq!(found, Some(Found { path: flat.join(key.path()), compressed: false }));
+		assert_eq!(found_two_tier, Some(Found { path: two_tier.join("De/Demo.pdb/3F2A0B1CB/Demo.pd_"), compressed: true }));
+		assert_eq!(found_lower, Some(Found { path: lower.join("de/demo.pdb/3f2a0b1cc/demo.pdb"), compressed: false }));
+		assert_eq!(not_found, None);
+	}
+}
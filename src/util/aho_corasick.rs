@@ -0,0 +1,122 @@
+/*!
+Aho-Corasick automaton to find many byte strings in a single pass.
+*/
+
+/// The initial state of the automaton.
+pub const ROOT: u32 = 0;
+
+struct Node {
+	// Transitions of the trie sorted by byte
+	edges: Vec<(u8, u32)>,
+	// Longest proper suffix which is also in the trie
+	fail: u32,
+	// Indices of the needles ending in this state, including those of its suffixes
+	out: Vec<usize>,
+}
+
+/// Aho-Corasick automaton.
+pub struct AhoCorasick {
+	nodes: Vec<Node>,
+}
+
+impl AhoCorasick {
+	/// Builds the automaton for the needles tagged by index.
+	///
+	/// Empty needles never match.
+	pub fn new<'a, I: IntoIterator<Item = (usize, &'a [u8])>>(needles: I) -> AhoCorasick {
+		let mut nodes = vec![Node { edges: Vec::new(), fail: ROOT, out: Vec::new() }];
+
+		// Build the trie
+		for (index, needle) in needles {
+			if needle.is_empty() {
+				continue;
+			}
+			let mut state = ROOT;
+			for &byte in needle {
+				state = match AhoCorasick::goto(&nodes, state, byte) {
+					Some(next) => next,
+					None => {
+						let next = nodes.len() as u32;
+						nodes.push(Node { edges: Vec::new(), fail: ROOT, out: Vec::new() });
+						let edges = &mut nodes[state as usize].edges;
+						let i = edges.binary_search_by_key(&byte, |&(byte, _)| byte).unwrap_err();
+						edges.insert(i, (byte, next));
+						next
+					},
+				};
+			}
+			nodes[state as usize].out.push(index);
+		}
+
+		// Link the failures breadth first so the suffixes are done before they are needed
+		let mut queue: Vec<u32> = nodes[ROOT as usize].edges.iter().map(|&(_, next)| next).collect();
+		let mut i = 0;
+		while i < queue.len() {
+			let state = queue[i];
+			i += 1;
+			for j in 0..nodes[state as usize].edges.len() {
+				let (byte, next) = nodes[state as usize].edges[j];
+				let mut fail = nodes[state as usize].fail;
+				let fail = loop {
+					if let Some(target) = AhoCorasick::goto(&nodes, fail, byte) {
+						break target;
+					}
+					if fail == ROOT {
+						break ROOT;
+					}
+					fail = nodes[fail as usize].fail;
+				};
+				let out = nodes[fail as usize].out.clone();
+				nodes[next as usize].fail = fail;
+				nodes[next as usize].out.extend(out);
+				queue.push(next);
+			}
+		}
+
+		AhoCorasick { nodes }
+	}
+	fn goto(nodes: &[Node], state: u32, byte: u8) -> Option<u32> {
+		let edges = &nodes[state as usize].edges;
+		edges.binary_search_by_key(&byte, |&(byte, _)| byte).ok().map(|i| edges[i].1)
+	}
+	/// Transitions to the next state after consuming a byte.
+	#[inline]
+	pub fn next(&self, mut state: u32, byte: u8) -> u32 {
+		loop {
+			if let Some(next) = AhoCorasick::goto(&self.nodes, state, byte) {
+				return next;
+			}
+			if state == ROOT {
+				return ROOT;
+			}
+			state = self.nodes[state as usize].fail;
+		}
+	}
+	/// Gets the indices of the needles ending in this state.
+	#[inline]
+	pub fn outputs(&self, state: u32) -> &[usize] {
+		&self.nodes[state as usize].out
+	}
+}
+
+//----------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn overlapping() {
+		let needles: [&[u8]; 5] = [b"he", b"she", b"his", b"hers", b""];
+		let ac = AhoCorasick::new(needles.iter().cloned().enumerate());
+		let mut state = ROOT;
+		let mut found = Vec::new();
+		for (i, &byte) in b"ushers".iter().enumerate() {
+			state = ac.next(state, byte);
+			let mut out = ac.outputs(state).to_vec();
+			out.sort();
+			found.extend(out.into_iter().map(|index| (i, index)));
+		}
+		assert_eq!(found, [(3, 0), (3, 1), (5, 3)]);
+	}
+}
@@ -10,12 +10,15 @@ mod wide_str;
 mod pod;
 mod offset;
 mod slice_len;
+pub(crate) mod aho_corasick;
+mod search;
 
 pub use self::c_str::CStr;
-pub use self::wide_str::WideStr;
+pub use self::wide_str::{WideStr, WideSlice};
 pub use self::pod::Pod;
 pub use self::offset::Offset;
 pub use self::slice_len::SliceLen;
+pub use self::search::{Backend, Finder};
 
 /// Splits a slice at the point defined by the callback.
 #[inline]
@@ -0,0 +1,261 @@
+/*!
+Vectorized substring search.
+*/
+
+use std::fmt;
+
+/// Rank of the bytes by their frequency in x86 and x64 code, higher is more common.
+///
+/// Derived from the code sections of the demo binaries.
+static BYTE_RANK: [u8; 256] = [
+	255, 240, 211, 216, 239, 235, 174, 161, 246, 137, 122, 142, 222, 224, 131, 229,
+	250, 202, 149, 101, 197, 236,  77,  36, 215, 157, 123, 143, 124, 132, 106, 150,
+	247, 167,  55, 113, 244, 228,  37,  26, 171,  38,  56, 175, 138,  78,  27,  10,
+	225, 189,  79, 233, 205, 166, 102, 103, 199, 186, 158, 198, 139, 176, 125, 144,
+	223, 226,  90, 151, 212, 238, 114,  91, 252, 217, 107,  57, 230, 220,  11,  80,
+	206, 133,  58, 179, 115, 213, 204, 209, 162, 203,  59, 183, 210, 218, 200, 195,
+	145,  12,  13, 116, 177, 180, 178,  28, 214,  14, 193,  92,  93,  60,  15,  29,
+	108,  16, 146,  61, 237, 245,  81,  39, 134,  30,   0,  40, 152, 153,  62,   1,
+	109, 126,  31, 248, 181, 231,  17,  63,  82, 249, 110, 253, 127, 234,  41,  42,
+	159,  32,  18,  19, 117,  43,   2,  20,  64,  44,  33,  45,  46,  21,  47,  22,
+	 65, 160,  48, 187,  66,  49,  50,  34,  51,   3,   4,  35,  67,   5,  68,  94,
+	 69,  83,  84,  23,  52,   6, 118,  95, 168, 163, 140, 119,  70,   7,  85,  86,
+	242, 201, 184, 241, 227,  87, 190, 221, 191, 172, 128, 154, 254,  96, 155, 129,
+	185, 135, 169,  71,  88,  24, 164,  72, 194, 156,  73, 147, 141,   8,  74, 111,
+	188,  53,  97,  75, 192, 136,  76,  25, 243, 165, 130, 219, 232, 120,  98,   9,
+	207, 173, 121,  99, 112, 104, 182, 170, 196,  89, 100,  54, 208, 105, 148, 251,
+];
+
+//----------------------------------------------------------------
+
+/// Search backend.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum Backend {
+	/// Scalar implementation available everywhere.
+	Portable,
+	/// SSE2 instructions on x86-64.
+	Sse2,
+	/// AVX2 instructions on x86-64, detected at runtime.
+	Avx2,
+}
+impl Backend {
+	/// Returns the fastest backend supported by the processor.
+	pub fn detect() -> Backend {
+		if Backend::Avx2.is_available() {
+			Backend::Avx2
+		}
+		else if Backend::Sse2.is_available() {
+			Backend::Sse2
+		}
+		else {
+			Backend::Portable
+		}
+	}
+	/// Returns if the backend is supported by the processor.
+	#[cfg(target_arch = "x86_64")]
+	pub fn is_available(self) -> bool {
+		match self {
+			Backend::Portable => true,
+			Backend::Sse2 => is_x86_feature_detected!("sse2"),
+			Backend::Avx2 => is_x86_feature_detected!("avx2"),
+		}
+	}
+	/// Returns if the backend is supported by the processor.
+	#[cfg(not(target_arch = "x86_64"))]
+	pub fn is_available(self) -> bool {
+		self == Backend::Portable
+	}
+}
+
+//----------------------------------------------------------------
+
+/// Substring searcher.
+///
+/// Candidates are found by looking for the two rarest bytes of the needle at their offsets, then the whole needle is compared.
+///
+/// # Examples
+///
+/// ```
+/// use pelite::util::Finder;
+///
+/// let finder = Finder::new(b"\xE8\x10\x00\x00\x00");
+/// assert_eq!(finder.find(b"\x90\x90\xE8\x00\xE8\x10\x00\x00\x00\xC3"), Some(4));
+/// assert_eq!(finder.find(b"\x90\x90\xE8\x10\x00\x00"), None);
+/// ```
+#[derive(Copy, Clone)]
+pub struct Finder<'a> {
+	needle: &'a [u8],
+	pair: (usize, usize),
+	backend: Backend,
+}
+impl<'a> Finder<'a> {
+	/// Creates a searcher for the needle with the fastest backend.
+	///
+	/// An empty needle matches at every position.
+	pub fn new(needle: &'a [u8]) -> Finder<'a> {
+		Finder::with_backend(needle, Backend::detect())
+	}
+	/// Creates a searcher for the needle with the given backend.
+	///
+	/// Falls back to the portable backend if the processor doesn't support it.
+	pub fn with_backend(needle: &'a [u8], backend: Backend) -> Finder<'a> {
+		let backend = if backend.is_available() { backend } else { Backend::Portable };
+		Finder { needle, pair: rare_pair(needle), backend }
+	}
+	/// Gets the needle.
+	pub fn needle(&self) -> &'a [u8] {
+		self.needle
+	}
+	/// Gets the offsets of the rarest pair of bytes in the needle.
+	///
+	/// The offsets are equal if the needle is a single byte.
+	pub fn pair(&self) -> (usize, usize) {
+		self.pair
+	}
+	/// Gets the backend.
+	pub fn backend(&self) -> Backend {
+		self.backend
+	}
+	/// Returns the position of the first occurrence of the needle in the haystack.
+	pub fn find(&self, haystack: &[u8]) -> Option<usize> {
+		if self.needle.len() == 0 {
+			return Some(0);
+		}
+		if haystack.len() < self.needle.len() {
+			return None;
+		}
+		match self.backend {
+			#[cfg(target_arch = "x86_64")]
+			Backend::Sse2 => unsafe { x86_64::find_sse2(self, haystack) },
+			#[cfg(target_arch = "x86_64")]
+			Backend::Avx2 => unsafe { x86_64::find_avx2(self, haystack) },
+			_ => self.find_portable(haystack, 0),
+		}
+	}
+	// Scalar search starting at the position
+	fn find_portable(&self, haystack: &[u8], mut i: usize) -> Option<usize> {
+		let (i1, i2) = self.pair;
+		let (b1, b2) = (self.needle[i1], self.needle[i2]);
+		while i + self.needle.len() <= haystack.len() {
+			if haystack[i + i1] == b1 && haystack[i + i2] == b2 && self.verify(haystack, i) {
+				return Some(i);
+			}
+			i += 1;
+		}
+		None
+	}
+	// Compares the needle at the candidate position
+	#[inline]
+	fn verify(&self, haystack: &[u8], i: usize) -> bool {
+		haystack.get(i..i + self.needle.len()) == Some(self.needle)
+	}
+}
+impl<'a> fmt::Debug for Finder<'a> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("Finder")
+			.field("needle", &self.needle)
+			.field("pair", &self.pair)
+			.field("backend", &self.backend)
+			.finish()
+	}
+}
+
+// Selects the offsets of the rarest and second rarest bytes
+fn rare_pair(needle: &[u8]) -> (usize, usize) {
+	let rank = |i: usize| BYTE_RANK[needle[i] as usize];
+	let rare1 = (0..needle.len()).min_by_key(|&i| rank(i)).unwrap_or(0);
+	// Prefer a different byte value, the same byte at two offsets filters little
+	let rare2 = (0..needle.len()).filter(|&i| needle[i] != needle[rare1]).min_by_key(|&i| rank(i))
+		.or_else(|| (0..needle.len()).find(|&i| i != rare1))
+		.unwrap_or(rare1);
+	(rare1, rare2)
+}
+
+//----------------------------------------------------------------
+
+#[cfg(target_arch = "x86_64")]
+mod x86_64 {
+	use std::arch::x86_64::*;
+	use std::cmp;
+	use super::Finder;
+
+	// Tests a vector of candidates at a time, the positions past the last full vector are left to the scalar search
+	macro_rules! find_vector {
+		($finder:expr, $haystack:expr, $size:expr, $splat:ident, $loadu:ident, $cmpeq:ident, $and:ident, $movemask:ident) => {{
+			let finder: &Finder = $finder;
+			let haystack: &[u8] = $haystack;
+			let (i1, i2) = finder.pair;
+			let v1 = $splat(finder.needle[i1] as i8);
+			let v2 = $splat(finder.needle[i2] as i8);
+			let last = cmp::max(i1, i2);
+			let ptr = haystack.as_ptr();
+			let mut i = 0;
+			while i + last + $size <= haystack.len() {
+				let c1 = $cmpeq($loadu(ptr.offset((i + i1) as isize) as *const _), v1);
+				let c2 = $cmpeq($loadu(ptr.offset((i + i2) as isize) as *const _), v2);
+				let mut mask = $movemask($and(c1, c2)) as u32;
+				while mask != 0 {
+					let candidate = i + mask.trailing_zeros() as usize;
+					if finder.verify(haystack, candidate) {
+						return Some(candidate);
+					}
+					mask &= mask - 1;
+				}
+				i += $size;
+			}
+			finder.find_portable(haystack, i)
+		}};
+	}
+
+	#[target_feature(enable = "sse2")]
+	pub unsafe fn find_sse2(finder: &Finder, haystack: &[u8]) -> Option<usize> {
+		find_vector!(finder, haystack, 16, _mm_set1_epi8, _mm_loadu_si128, _mm_cmpeq_epi8, _mm_and_si128, _mm_movemask_epi8)
+	}
+
+	#[target_feature(enable = "avx2")]
+	pub unsafe fn find_avx2(finder: &Finder, haystack: &[u8]) -> Option<usize> {
+		find_vector!(finder, haystack, 32, _mm256_set1_epi8, _mm256_loadu_si256, _mm256_cmpeq_epi8, _mm256_and_si256, _mm256_movemask_epi8)
+	}
+}
+
+//----------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn pairs() {
+		// The ModRM byte is rarer than the REX prefix and the opcode
+		assert_eq!(rare_pair(b"\x48\x8B\x05"), (2, 0));
+		// Nul bytes are the most common
+		assert_eq!(rare_pair(b"\xE8\x00\x00\x00\x00"), (0, 1));
+		assert_eq!(rare_pair(b"\xCC"), (0, 0));
+		// Repeated bytes fall back to the same byte at another offset
+		assert_eq!(rare_pair(b"\x00\x00\x00"), (0, 1));
+	}
+
+	#[test]
+	fn backends() {
+		// Xorshift for reproducible haystacks with many near misses
+		let mut state = 0x2545F491u32;
+		let mut haystack = vec![0u8; 3000];
+		for byte in &mut haystack {
+			state ^= state << 13;
+			state ^= state >> 17;
+			state ^= state << 5;
+			*byte = [0x00, 0x48, 0x8B, 0x05, 0xE8, 0xC3][state as usize % 6];
+		}
+		let needles: &[&[u8]] = &[b"\x48", b"\x48\x8B", b"\x48\x8B\x05\x00", b"\xE8\x00\x00\xC3\x48", b"\x05\x05\x05\x05\x05\x05", b"\xC3\xC3\xC3\xC3\xC3\xC3\xC3\xC3\xC3"];
+		for &needle in needles {
+			// Every suffix of the haystack moves the tail through the vector boundaries
+			for start in (0..haystack.len()).step_by(97) {
+				let haystack = &haystack[start..];
+				let expected = haystack.windows(needle.len()).position(|window| window == needle);
+				for &backend in &[Backend::Portable, Backend::Sse2, Backend::Avx2] {
+					assert_eq!(Finder::with_backend(needle, backend).find(haystack), expected, "{:?} {:02X?}", backend, needle);
+				}
+			}
+		}
+	}
+}
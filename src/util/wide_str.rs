@@ -1,5 +1,5 @@
 /*!
-Length word prefixed wide string.
+Wide strings.
 */
 
 use std::{char, fmt, mem, ops};
@@ -31,9 +31,7 @@ impl WideStr {
 
 impl PartialEq<str> for WideStr {
 	fn eq(&self, rhs: &str) -> bool {
-		let decoder = char::decode_utf16(self.as_ref().iter().cloned());
-		let chars = rhs.chars().map(Ok);
-		decoder.eq(chars)
+		eq_str(self.as_ref(), rhs)
 	}
 }
 
@@ -51,47 +49,120 @@ impl AsRef<[u16]> for WideStr {
 	}
 }
 
+//----------------------------------------------------------------
+
+/// Wide string slice without a length prefix.
+///
+/// Used by the resources whose strings are nul terminated or sized by their container, the nul terminator is not part of the string.
+#[derive(Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct WideSlice {
+	words: [u16],
+}
+
+impl WideSlice {
+	/// Interprets a word slice as a wide string.
+	pub fn from_words(words: &[u16]) -> &WideSlice {
+		unsafe { mem::transmute(words) }
+	}
+	/// Encodes the string as an UTF8 validated `String`.
+	pub fn to_string(&self) -> Result<String, char::DecodeUtf16Error> {
+		char::decode_utf16(self.words.iter().cloned()).collect()
+	}
+}
+
+impl PartialEq<str> for WideSlice {
+	fn eq(&self, rhs: &str) -> bool {
+		eq_str(&self.words, rhs)
+	}
+}
+
+impl ops::Deref for WideSlice {
+	type Target = [u16];
+	#[inline]
+	fn deref(&self) -> &[u16] {
+		&self.words
+	}
+}
+impl AsRef<[u16]> for WideSlice {
+	#[inline]
+	fn as_ref(&self) -> &[u16] {
+		&self.words
+	}
+}
+impl<'a> From<&'a WideStr> for &'a WideSlice {
+	fn from(string: &'a WideStr) -> &'a WideSlice {
+		WideSlice::from_words(string.as_ref())
+	}
+}
+
+// Compares the words decoded as utf16 with the string
+fn eq_str(words: &[u16], rhs: &str) -> bool {
+	let decoder = char::decode_utf16(words.iter().cloned());
+	let chars = rhs.chars().map(Ok);
+	decoder.eq(chars)
+}
+
 //----------------------------------------------------------------
 // Formatting
 
 impl fmt::Display for WideStr {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-		for chr in char::decode_utf16(self.as_ref().iter().cloned()) {
-			let chr = chr.unwrap_or(char::REPLACEMENT_CHARACTER);
-			fmt::Write::write_char(f, chr)?;
-		}
-		Ok(())
+		display(self.as_ref(), f)
 	}
 }
 
 impl fmt::Debug for WideStr {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-		f.write_str("L\"")?;
-		for chr in char::decode_utf16(self.as_ref().iter().cloned()) {
-			match chr {
-				Ok(chr) => {
-					match chr {
-						'\0' => f.write_str("\\0")?,
-						'\n' => f.write_str("\\n")?,
-						'\r' => f.write_str("\\r")?,
-						'\t' => f.write_str("\\t")?,
-						'"' => f.write_str("\\\"")?,
-						'\\' => f.write_str("\\\\")?,
-						_ => fmt::Write::write_char(f, chr)?,
-					};
-				},
-				Err(e) => write!(f, "\\u{:04X}", e.unpaired_surrogate())?,
-			};
-		}
-		f.write_str("\"")
+		debug(self.as_ref(), f)
+	}
+}
+
+impl fmt::Display for WideSlice {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		display(&self.words, f)
+	}
+}
+
+impl fmt::Debug for WideSlice {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		debug(&self.words, f)
+	}
+}
+
+fn display(words: &[u16], f: &mut fmt::Formatter) -> fmt::Result {
+	for chr in char::decode_utf16(words.iter().cloned()) {
+		let chr = chr.unwrap_or(char::REPLACEMENT_CHARACTER);
+		fmt::Write::write_char(f, chr)?;
 	}
+	Ok(())
+}
+
+fn debug(words: &[u16], f: &mut fmt::Formatter) -> fmt::Result {
+	f.write_str("L\"")?;
+	for chr in char::decode_utf16(words.iter().cloned()) {
+		match chr {
+			Ok(chr) => {
+				match chr {
+					'\0' => f.write_str("\\0")?,
+					'\n' => f.write_str("\\n")?,
+					'\r' => f.write_str("\\r")?,
+					'\t' => f.write_str("\\t")?,
+					'"' => f.write_str("\\\"")?,
+					'\\' => f.write_str("\\\\")?,
+					_ => fmt::Write::write_char(f, chr)?,
+				};
+			},
+			Err(e) => write!(f, "\\u{:04X}", e.unpaired_surrogate())?,
+		};
+	}
+	f.write_str("\"")
 }
 
 //----------------------------------------------------------------
 
 #[cfg(test)]
 mod tests {
-	use super::WideStr;
+	use super::{WideStr, WideSlice};
 
 	#[test]
 	fn units() {
@@ -101,4 +172,17 @@ mod tests {
 		assert_eq!(wide_str.len(), 6);
 		assert_eq!(wide_str.as_ref(), &WIDE_STR[1..]);
 	}
+
+	#[test]
+	fn slices() {
+		static WORDS: [u16; 8] = [6, 83, 84, 82, 73, 78, 71, 0];
+		let wide_slice = WideSlice::from_words(&WORDS[1..7]);
+		assert_eq!(wide_slice.to_string(), Ok(String::from("STRING")));
+		assert!(*wide_slice == *"STRING");
+		assert_eq!(format!("{:?}", wide_slice), "L\"STRING\"");
+		// Equal strings compare equal regardless of the words around them
+		let wide_str = unsafe { WideStr::from_words_unchecked(&WORDS[..7]) };
+		assert_eq!(<&WideSlice>::from(wide_str), wide_slice);
+		assert_eq!(WideSlice::from_words(&[0x4F, 0x4B]), WideSlice::from_words(&[0x81, 0x4F, 0x4B][1..]));
+	}
 }
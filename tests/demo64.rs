@@ -97,6 +97,195 @@ fn find_data() {
 	println!("\n{}", manifest);
 }
 
+#[test]
+fn find_resource() {
+	use pelite::resources::{FindError, Name};
+
+	let file_map = FileMap::open(FILE_NAME).unwrap();
+	let file = PeFile::from_bytes(&file_map).unwrap();
+	let resources = file.resources().unwrap();
+
+	// Exact match
+	let entry = resources.find_resource(Name::RT_MANIFEST, Name::Id(2), Some(1033)).unwrap();
+	assert_eq!(entry.data(), resources.find_data("/Manifest/2/1033").unwrap().data());
+	// Falls back to en-US
+	let entry = resources.find_resource(Name::RT_MANIFEST, Name::Id(2), Some(2057)).unwrap();
+	assert_eq!(entry.data(), resources.find_data("/Manifest/2/1033").unwrap().data());
+	// Falls back to the first available language
+	let entry = resources.find_resource(Name::RT_VERSION, Name::Id(1), Some(1031)).unwrap();
+	assert_eq!(entry.data(), resources.find_data("/Version/1/2057").unwrap().data());
+
+	assert_eq!(resources.find_resource(Name::RT_MANIFEST, Name::Id(7), None).err(), Some(FindError::NotFound));
+	assert_eq!(resources.find_resource(Name::RT_HTML, Name::Id(1), None).err(), Some(FindError::NotFound));
+}
+
+#[test]
+fn walk() {
+	use pelite::resources::Entry;
+
+	let file_map = FileMap::open(FILE_NAME).unwrap();
+	let file = PeFile::from_bytes(&file_map).unwrap();
+	let root = file.resources().unwrap().root().unwrap();
+
+	// Every data entry is found three directories deep
+	let mut data_entries = 0;
+	root.walk(|path, entry| {
+		if let Entry::DataEntry(_) = entry {
+			assert_eq!(path.len(), 3);
+			data_entries += 1;
+		}
+	}).unwrap();
+	assert!(data_entries > 0);
+}
+
+#[test]
+fn manifest() {
+	use pelite::resources::manifest::Encoding;
+
+	let file_map = FileMap::open(FILE_NAME).unwrap();
+	let file = PeFile::from_bytes(&file_map).unwrap();
+	let manifest = file.resources().unwrap().manifest().unwrap();
+
+	assert_eq!(manifest.encoding(), Encoding::Utf8);
+	assert_eq!(manifest.requested_execution_level(), Some("asInvoker"));
+	assert_eq!(manifest.ui_access(), Some(false));
+	assert_eq!(manifest.dpi_aware(), None);
+	assert!(manifest.dependent_assemblies().is_empty());
+}
+
+//----------------------------------------------------------------
+
+#[test]
+fn version_info() {
+	use pelite::resources::version_info::{Language, Version};
+
+	let file_map = FileMap::open(FILE_NAME).unwrap();
+	let file = PeFile::from_bytes(&file_map).unwrap();
+	let version_info = file.resources().unwrap().version_info().unwrap();
+
+	let version = Version { major: 1, minor: 0, patch: 0, build: 1 };
+	assert_eq!(version_info.file_version(), Some(version));
+	assert_eq!(version_info.product_version(), Some(version));
+
+	let lang = Language { lang_id: 2057, charset_id: 1200 };
+	assert_eq!(version_info.translation(), &[lang]);
+	assert_eq!(version_info.string_tables().count(), 1);
+	assert_eq!(version_info.value(lang, "ProductName").unwrap(), "AT1521");
+	assert_eq!(version_info.value(lang, "OriginalFilename").unwrap(), "Demo.dll");
+	assert_eq!(version_info.value(lang, "NotFound"), None);
+}
+
+#[test]
+fn group_icons() {
+	use pelite::resources::Name;
+
+	let file_map = FileMap::open(FILE_NAME).unwrap();
+	let file = PeFile::from_bytes(&file_map).unwrap();
+	let resources = file.resources().unwrap();
+
+	let groups: Vec<_> = resources.group_icons().unwrap().map(Result::unwrap).collect();
+	assert_eq!(groups.len(), 1);
+	let (name, group) = groups[0];
+	assert_eq!(name, Name::Id(103));
+	assert!(!group.is_cursor());
+	assert_eq!(group.entries().len(), 1);
+
+	let mut ico = Vec::new();
+	group.write(&mut ico).unwrap();
+	let image = group.image_data(&group.entries()[0]).unwrap();
+	assert_eq!(&ico[..6], &[0, 0, 1, 0, 1, 0]);
+	assert_eq!(&ico[18..22], &[22, 0, 0, 0]);
+	assert_eq!(&ico[22..], image);
+}
+
+#[test]
+fn string_table() {
+	use pelite::Error;
+
+	let file_map = FileMap::open(FILE_NAME).unwrap();
+	let file = PeFile::from_bytes(&file_map).unwrap();
+	let resources = file.resources().unwrap();
+
+	assert_eq!(resources.string_table(102, None).unwrap(), "This is a string");
+	assert_eq!(resources.string_table(102, Some(2057)).unwrap(), "This is a string");
+	assert_eq!(resources.string_table(102, Some(1033)), Err(Error::Null));
+	assert_eq!(resources.string_table(101, None), Err(Error::Null));
+	assert_eq!(resources.string_table(1, None), Err(Error::Null));
+
+	let strings: Vec<_> = resources.strings().unwrap().map(|(id, lang, _)| (id, lang)).collect();
+	assert_eq!(strings, [(102, 2057), (103, 2057)]);
+}
+
+#[test]
+fn accelerators() {
+	let file_map = FileMap::open(FILE_NAME).unwrap();
+	let file = PeFile::from_bytes(&file_map).unwrap();
+	let resources = file.resources().unwrap();
+	let accelerators = resources.find_data("/Accelerators/101/2057").unwrap().accelerators().unwrap();
+
+	let ids: Vec<u16> = accelerators.image().iter().map(|entry| entry.wId).collect();
+	assert_eq!(ids, [40001, 40002]);
+}
+
+#[test]
+fn mui_satellite() {
+	use std::{env, fs, process, slice};
+	use pelite::image::IMAGE_DIRECTORY_ENTRY_RESOURCE;
+	use pelite::resources::build::{DataBuf, DirectoryBuf, NameBuf};
+	use pelite::resources::mui::{self, MuiConfig, MUI_FILETYPE_LN, MUI_FILETYPE_MUI, MUI_SIGNATURE};
+
+	// Minimal configuration with the file type and checksum
+	fn config(file_type: u32, checksum: u8) -> [u32; 0x21] {
+		let mut dwords = [0u32; 0x21];
+		dwords[0] = MUI_SIGNATURE;
+		dwords[1] = 0x84;
+		dwords[2] = 0x00010000;
+		dwords[4] = file_type;
+		dwords[0x2C / 4] = checksum as u32;
+		dwords
+	}
+	fn bytes(dwords: &[u32]) -> &[u8] {
+		unsafe { slice::from_raw_parts(dwords.as_ptr() as *const u8, dwords.len() * 4) }
+	}
+
+	// Replace the resources of the demo with a MUI resource to make a satellite
+	let file_map = FileMap::open(FILE_NAME).unwrap();
+	let file = PeFile::from_bytes(&file_map).unwrap();
+	let dir = &file.data_directory()[IMAGE_DIRECTORY_ENTRY_RESOURCE];
+	let mut root = DirectoryBuf::new();
+	root.insert(NameBuf::from("MUI"), NameBuf::Id(1), 0, DataBuf::new(bytes(&config(MUI_FILETYPE_MUI, 42)).to_vec())).unwrap();
+	let mut rsrc = Vec::new();
+	root.write(dir.VirtualAddress, &mut rsrc).unwrap();
+	assert!(rsrc.len() <= dir.Size as usize);
+	let offset = file.rva_to_file_offset(dir.VirtualAddress).unwrap();
+	let mut satellite = file_map.as_ref().to_vec();
+	satellite[offset..offset + rsrc.len()].copy_from_slice(&rsrc);
+
+	// The module itself doesn't need to exist
+	let temp = env::temp_dir().join(format!("pelite-mui-{}", process::id()));
+	let module = temp.join("Demo64.dll");
+	fs::create_dir_all(temp.join("en-US")).unwrap();
+	fs::write(temp.join("en-US").join("Demo64.dll.mui"), &satellite).unwrap();
+	fs::create_dir_all(temp.join("de-DE")).unwrap();
+	fs::write(temp.join("de-DE").join("Demo64.dll.mui"), file_map.as_ref()).unwrap();
+
+	let ln = config(MUI_FILETYPE_LN, 42);
+	let ln = MuiConfig::new(bytes(&ln)).unwrap();
+	let other = config(MUI_FILETYPE_LN, 7);
+	let other = MuiConfig::new(bytes(&other)).unwrap();
+	let found = mui::find_satellite(&module, &ln, "en-US");
+	let mismatch = mui::find_satellite(&module, &other, "en-US");
+	// Satellites without a MUI resource or missing altogether aren't found
+	let no_mui = mui::find_satellite(&module, &ln, "de-DE");
+	let missing = mui::find_satellite(&module, &ln, "fr-FR");
+	fs::remove_dir_all(&temp).unwrap();
+
+	assert_eq!(found, Ok(temp.join("en-US").join("Demo64.dll.mui")));
+	assert_eq!(mismatch, Err(pelite::Error::BadMagic));
+	assert_eq!(no_mui, Err(pelite::Error::Null));
+	assert_eq!(missing, Err(pelite::Error::Null));
+}
+
 //----------------------------------------------------------------
 
 #[test]
@@ -123,6 +312,9 @@ fn debug() {
 			Info::CvRSDS { pdb_file_name, .. } => {
 				assert_eq!(pdb_file_name, r"D:\Projects\pelite\proto\Demo\x64\Release\Demo.pdb");
 			},
+			Info::VcFeature { image } => {
+				assert!(image.CCpp != 0);
+			},
 			_ => (),
 		};
 	}
@@ -143,10 +335,225 @@ fn scanner() {
 	assert_eq!(m.2, 0x5140);
 
 	// Test the edge cases of quicksearch
-	// See scanner code for an unfortunate edge case...
+	// The matches start in the range but may extend past its end
 	let pat = pelite::pattern::parse("0F1002 488BC1 0F1101 F20F104A10 F20F114910 C3").unwrap();
 	let m = scanner.find(&pat, 0x148F..0x14A3).unwrap();
 	assert_eq!(m.0, 0x1490);
-	let m = scanner.find(&pat, 0x1490..0x149F);
-	assert_eq!(m, None);
+	let m = scanner.find(&pat, 0x1490..0x149F).unwrap();
+	assert_eq!(m.0, 0x1490);
+	let m = scanner.find(&pat, 0x1490..0x1491).unwrap();
+	assert_eq!(m.0, 0x1490);
+	assert_eq!(scanner.find(&pat, 0x1491..0x14A3), None);
+
+	// Scan non-contiguous ranges
+	use pelite::pe64::scanner::Ranges;
+	let ranges = Ranges::from(vec![0x2000..0x3000, 0x1000..0x1100, 0x148F..0x1491, 0x10F0..0x1200]);
+	assert_eq!(ranges.as_slice(), &[0x1000..0x1200, 0x148F..0x1491, 0x2000..0x3000]);
+	assert_eq!(scanner.find(&pat, ranges).unwrap().0, 0x1490);
+	assert_eq!(scanner.find(&pat, &[0x1000..0x1490, 0x1491..0x2000][..]), None);
+	let pats = [&pat[..]];
+	assert_eq!(scanner.matches_many(&pats, &[0x1000..0x1100, 0x1490..0x1491][..]).map(|(_, m)| m.0).collect::<Vec<_>>(), [0x1490]);
+	assert_eq!(scanner.matches_many(&pats, &[0x1000..0x1490, 0x1491..0x2000][..]).count(), 0);
+
+	// Select the ranges by section
+	let text = scanner.named_ranges(".text");
+	assert_eq!(text.as_slice().len(), 1);
+	assert_eq!(scanner.code_ranges(), text);
+	assert!(text.as_slice()[0].start == 0x1000 && text.as_slice()[0].end > 0x14A3);
+	assert_eq!(scanner.named_ranges(".nope"), Ranges::new());
+
+	// Nibbles, masks, alternations and repetitions
+	let pat = pelite::pattern::parse("4?8B41'? 4C2B&FF C2 ????????? 0FB60A 42 0F&F0 B60402 2BC8 (EB|74|75)% 8B15${'} 85 C9").unwrap();
+	let m = scanner.find_code(&pat).unwrap();
+	assert_eq!(m.0, 0x12F0);
+	assert_eq!(m.2, 0x5140);
+	let pat = pelite::pattern::parse("0F1002 48[0-3] 8BC1 0F11 0?[1] F20F104A10 F20F114910 C3").unwrap();
+	let m = scanner.find_code(&pat).unwrap();
+	assert_eq!(m.0, 0x1490);
+
+	// Value captures and comparisons
+	let pat = pelite::pattern::parse("@4 4C8B41 u1 4C2BC2 ????????? 0FB60A 420FB60402 2BC8 75% 8B15 i4 ' =3").unwrap();
+	let m = scanner.find_code(&pat).unwrap();
+	assert_eq!(m.0, 0x12F0);
+	assert_eq!(m.1, 0x10);
+	assert_eq!(m.3.wrapping_add(m.2), 0x5140);
+	let pat = pelite::pattern::parse("@5 4C8B41 u1 4C2BC2").unwrap();
+	assert_eq!(scanner.find_code(&pat), None);
+	let pat = pelite::pattern::parse("4C8B41 ' ? =1 4C2BC2").unwrap();
+	assert_eq!(scanner.find_code(&pat), None);
+
+	// Named captures beyond the size of Match
+	let (pat, names) = pelite::pattern::parse_named("4C8B41 u1<off> 4C2BC2 ''''''' ????????? 0FB60A 420FB60402 2BC8 75% 8B15 ${'<global>} 85 C9").unwrap();
+	let mut save = [0; 16];
+	assert!(scanner.find_code_into(&pat, &mut save));
+	assert_eq!(save[0], 0x12F0);
+	assert_eq!(names.get("off", &save), Some(0x10));
+	assert_eq!(names.get("global", &save), Some(0x5140));
+	assert_eq!(names.slot("global"), Some(9));
+	let mut matches = scanner.matches_code(&pat);
+	assert!(matches.next_into(&mut save));
+	assert!(!matches.next_into(&mut save));
+
+	// Scan the code section as a raw byte slice, addresses are relative to its start
+	use pelite::pe64::scanner::{Scanner, Slice};
+	let code = &file.slice_bytes(0x1000).unwrap()[..file.optional_header().SizeOfCode as usize];
+	let image_base = file.optional_header().ImageBase;
+	let scanner = Scanner::new(Slice::new(code, image_base + 0x1000));
+	let pat = pelite::pattern::parse("4C8B41'? 4C2BC2 ????????? 0FB60A 420FB60402 2BC8 75% 8B15${'} 85 C9").unwrap();
+	let m = scanner.find(&pat, 0..code.len() as u32).unwrap();
+	assert_eq!(m.0, 0x2F0);
+	assert_eq!(m.2, 0x4140);
+
+	// Pointers are translated relative to the base address
+	let mut bytes = [0u8; 16];
+	bytes[..8].copy_from_slice(&[0x08, 0x10, 0x00, 0x40, 0x01, 0x00, 0x00, 0x00]);
+	bytes[8..10].copy_from_slice(&[0xAA, 0xBB]);
+	let scanner = Scanner::new(Slice::new(&bytes, 0x140001000));
+	let pat = pelite::pattern::parse("*{'AABB}").unwrap();
+	assert_eq!(scanner.exec(&pat, 0).map(|m| m.1), Some(8));
+	let scanner = Scanner::new(Slice::new(&bytes, 0x140002000));
+	assert_eq!(scanner.exec(&pat, 0), None);
+
+	// Jumps landing at the end of the address space fail to match
+	let bytes = [0xE9, 0xFA, 0xFF, 0xFF, 0xFF];
+	let scanner = Scanner::new(Slice::new(&bytes, 0x140001000));
+	assert_eq!(scanner.exec(&pelite::pattern::parse("E9$90").unwrap(), 0), None);
+	assert_eq!(scanner.exec(&pelite::pattern::parse("E9$'").unwrap(), 0).map(|m| m.1), Some(0xFFFFFFFF));
+
+	// Sections at the end of the address space don't overflow
+	let mut bytes = file_map.as_ref().to_vec();
+	let last = file.section_headers().as_ptr() as usize - file_map.as_ref().as_ptr() as usize + (file.section_headers().len() - 1) * 40;
+	bytes[last + 8..last + 16].copy_from_slice(&[0x00, 0x20, 0x00, 0x00, 0x00, 0xF0, 0xFF, 0xFF]);
+	let file = PeFile::from_bytes(&bytes).unwrap();
+	let pat = pelite::pattern::parse("0F1002 488BC1 0F1101 F20F104A10 F20F114910 C3").unwrap();
+	assert_eq!(file.scanner().find(&pat, 0x1000..0x2000).map(|m| m.0), Some(0x1490));
+}
+
+#[test]
+fn scanner_many() {
+	let file_map = FileMap::open(FILE_NAME).unwrap();
+	let file = PeFile::from_bytes(&file_map).unwrap();
+	let scanner = file.scanner();
+
+	let pats = [
+		pelite::pattern::parse("4C8B41'? 4C2BC2 ????????? 0FB60A 420FB60402 2BC8 75% 8B15${'} 85 C9").unwrap(),
+		pelite::pattern::parse("0F1002 488BC1 0F1101 F20F104A10 F20F114910 C3").unwrap(),
+		pelite::pattern::parse("C3").unwrap(),
+		pelite::pattern::parse("? 4C2BC2").unwrap(),
+		pelite::pattern::parse("\"not found\"").unwrap(),
+	];
+	let pats: Vec<&[pelite::pattern::Atom]> = pats.iter().map(|pat| &pat[..]).collect();
+
+	// Same matches as scanning for every pattern on its own
+	let found: Vec<_> = scanner.matches_many_code(&pats).collect();
+	for (index, pat) in pats.iter().enumerate() {
+		let mut many: Vec<_> = found.iter().filter(|&&(i, _)| i == index).map(|&(_, m)| m).collect();
+		many.sort_by_key(|m| m.0);
+		let single: Vec<_> = scanner.matches_code(pat).collect();
+		assert_eq!(many, single);
+	}
+	assert_eq!(found.iter().filter(|&&(i, _)| i == 0).count(), 1);
+}
+
+//----------------------------------------------------------------
+
+#[test]
+fn signature() {
+	use pelite::pe64::signature::Generator;
+	use pelite::pattern;
+
+	let file_map = FileMap::open(FILE_NAME).unwrap();
+	let file = PeFile::from_bytes(&file_map).unwrap();
+	let scanner = file.scanner();
+	let generator = Generator::new(scanner);
+
+	// Every exported function has a unique signature matching it
+	let optional_header = file.optional_header();
+	let code = optional_header.BaseOfCode..optional_header.BaseOfCode + optional_header.SizeOfCode;
+	let exports_by = file.exports().unwrap().by().unwrap();
+	for &rva in exports_by.functions().iter().filter(|&&rva| rva >= code.start && rva < code.end) {
+		let pat = generator.generate(rva).unwrap();
+		assert_eq!(scanner.find_code(&pat).unwrap().0, rva, "{}", pattern::display(&pat));
+	}
+
+	// The relative offset of the global is a wildcard
+	let pat = generator.generate(0x12D0).unwrap();
+	let text = pattern::display(&pat).to_string();
+	assert!(text.starts_with("8B0D ????"), "{}", text);
+	assert_eq!(pattern::parse(&text), Ok(pat));
+
+	// Anchor on a reference to the global
+	let pat = generator.generate_xref(0x5140).unwrap();
+	let m = scanner.find_code(&pat).unwrap();
+	assert_eq!(m.1, 0x5140);
+	assert_eq!(pattern::display(&pat).to_string(), "8B15${'}");
+
+	// Not in the code section
+	assert_eq!(generator.generate(0x5140), None);
+
+	// Sections and targets at the end of the address space don't overflow
+	let mut bytes = file_map.as_ref().to_vec();
+	let last = file.section_headers().as_ptr() as usize - file_map.as_ref().as_ptr() as usize + (file.section_headers().len() - 1) * 40;
+	bytes[last + 8..last + 16].copy_from_slice(&[0x00, 0x20, 0x00, 0x00, 0x00, 0xF0, 0xFF, 0xFF]);
+	let file = PeFile::from_bytes(&bytes).unwrap();
+	let generator = Generator::new(file.scanner());
+	assert_eq!(generator.generate(0xFFFFFFF0), None);
+	assert_eq!(generator.generate_xref(0xFFFFFFF0), None);
+	assert!(generator.generate(0x12D0).is_some());
+}
+
+//----------------------------------------------------------------
+
+#[test]
+fn lde() {
+	use pelite::lde::{self, Mode};
+
+	let file_map = FileMap::open(FILE_NAME).unwrap();
+	let file = PeFile::from_bytes(&file_map).unwrap();
+
+	// Step over the instructions of every exported function up to its return
+	let optional_header = file.optional_header();
+	let code = optional_header.BaseOfCode..optional_header.BaseOfCode + optional_header.SizeOfCode;
+	let exports_by = file.exports().unwrap().by().unwrap();
+	for &rva in exports_by.functions().iter().filter(|&&rva| rva >= code.start && rva < code.end) {
+		let bytes = file.slice_bytes(rva).unwrap();
+		let mut iter = lde::iter(bytes, Mode::X64);
+		assert!(iter.by_ref().any(|(offset, insn)| insn.len == 1 && bytes[offset] == 0xC3), "{:#X}", rva);
+		assert!(iter.offset() < 0x1000, "{:#X}", rva);
+	}
+
+	// The function loads a global with a RIP-relative operand
+	let bytes = file.slice_bytes(0x12D0).unwrap();
+	let insn = lde::decode(bytes, Mode::X64).unwrap();
+	assert_eq!(&bytes[..insn.opcode as usize + 2], &[0x8B, 0x0D]);
+	assert!(insn.rip);
+	let target = insn.target(bytes, 0x12D0).unwrap();
+	assert!(file.derva_copy::<u32>(target as u32).is_ok(), "{:#X}", target);
+}
+
+//----------------------------------------------------------------
+
+#[test]
+fn sigdb() {
+	let file_map = FileMap::open(FILE_NAME).unwrap();
+	let file = PeFile::from_bytes(&file_map).unwrap();
+	let scanner = file.scanner();
+
+	let entries = pelite::sigdb::load("demo/Demo64.sig").unwrap();
+	let results: Vec<(&str, Vec<u32>)> = entries.iter().map(|entry| {
+		let mut save = vec![0; entry.save_len()];
+		let mut matches = scanner.matches_code(&entry.pattern);
+		let mut found = Vec::new();
+		while matches.next_into(&mut save) {
+			found.push(save[entry.slot]);
+		}
+		assert_eq!(found.len(), entry.count, "{}", entry.name);
+		(&*entry.name, found)
+	}).collect();
+	assert_eq!(results, [
+		("Checksum", vec![0x12F0]),
+		("g_Table", vec![0x5140]),
+		("Checksum.offset", vec![0x10]),
+		("Copy16", vec![0x1490]),
+	]);
 }